@@ -9,7 +9,8 @@ members=[
     "snips-nlu-ffi",
     "snips-nlu-ffi/python/snips-nlu-python-ffi",
     "snips-nlu-lib",
-    "snips-nlu-cli"
+    "snips-nlu-cli",
+    "snips-nlu-server"
 ]' > Cargo.toml
 
 echo "Replacing snips-nlu-ffi url for local version"
@@ -1,33 +1,425 @@
 extern crate clap;
+#[macro_use]
+extern crate failure;
 extern crate serde_json;
 extern crate snips_nlu_lib;
 
-use clap::{Arg, App};
-use snips_nlu_lib::SnipsNluEngine;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use failure::{Error, ResultExt};
+use serde_json::Value;
+use snips_nlu_lib::evaluation::EvaluatedSlot;
+use snips_nlu_lib::{evaluate_engine, Dataset, EvaluationReport, SnipsNluEngine, SnipsNluError};
+use std::fs::File;
 use std::io;
-use std::io::Write;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process;
+
+type Result<T> = ::std::result::Result<T, Error>;
+
+/// The command failed for a reason which is not covered by a more specific exit code
+const EXIT_FAILURE: i32 = 1;
+/// The command line arguments are invalid
+const EXIT_USAGE: i32 = 2;
+/// The nlu engine or the test set could not be loaded
+const EXIT_MODEL_LOAD: i32 = 3;
+/// The inputs could not be read or the outputs could not be written
+const EXIT_IO: i32 = 4;
 
 fn main() {
-    let matches = App::new("snips-nlu-parse")
-        .about("Snips NLU interactive CLI for parsing intents")
-        .arg(Arg::with_name("NLU_ENGINE_DIR")
-            .required(true)
-            .takes_value(true)
-            .index(1)
-            .help("path to the trained nlu engine directory"))
-        .get_matches();
-    let engine_dir = matches.value_of("NLU_ENGINE_DIR").unwrap();
-
-    println!("\nLoading the nlu engine...");
-    let engine = SnipsNluEngine::from_path(engine_dir).unwrap();
+    let engine_arg = Arg::with_name("NLU_ENGINE")
+        .required(true)
+        .takes_value(true)
+        .index(1)
+        .help("path to the trained nlu engine directory, or to its zip archive");
+    let intents_arg = Arg::with_name("intents")
+        .long("intents")
+        .takes_value(true)
+        .use_delimiter(true)
+        .help("comma separated list of intents to consider, all intents are considered when \
+               omitted");
+
+    let app = App::new("snips-nlu")
+        .about("Snips NLU command line tools")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("parse")
+            .about("Parses a single query, or queries typed interactively when none is given")
+            .arg(engine_arg.clone())
+            .arg(Arg::with_name("QUERY")
+                .takes_value(true)
+                .index(2)
+                .help("query to parse"))
+            .arg(intents_arg.clone()))
+        .subcommand(SubCommand::with_name("batch")
+            .about("Parses queries in the json lines format, where each line is either a json \
+                    string or an object with an \"input\" field")
+            .arg(engine_arg.clone())
+            .arg(Arg::with_name("input")
+                .short("i")
+                .long("input")
+                .takes_value(true)
+                .help("path of the queries file, queries are read from stdin when omitted"))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("path of the results file, results are written to stdout when omitted"))
+            .arg(intents_arg.clone()))
+        .subcommand(SubCommand::with_name("inspect")
+            .about("Prints the metadata, intents, slots, entities and resources of an engine")
+            .arg(engine_arg.clone()))
+        .subcommand(SubCommand::with_name("extract-slot")
+            .about("Extracts the value of a slot from a query, as an answer to a slot request")
+            .arg(engine_arg.clone())
+            .arg(Arg::with_name("QUERY")
+                .required(true)
+                .takes_value(true)
+                .index(2)
+                .help("query containing the slot value"))
+            .arg(Arg::with_name("intent")
+                .long("intent")
+                .required(true)
+                .takes_value(true)
+                .help("intent of the slot"))
+            .arg(Arg::with_name("slot")
+                .long("slot")
+                .required(true)
+                .takes_value(true)
+                .help("name of the slot")))
+        .subcommand(SubCommand::with_name("tag")
+            .about("Prints the tokens of a query along with their features, and the tags decoded \
+                    by the slot filler of an intent with their marginal probabilities")
+            .arg(engine_arg.clone())
+            .arg(Arg::with_name("QUERY")
+                .required(true)
+                .takes_value(true)
+                .index(2)
+                .help("query to tag"))
+            .arg(Arg::with_name("intent")
+                .long("intent")
+                .required(true)
+                .takes_value(true)
+                .help("intent whose slot filler tags the query")))
+        .subcommand(SubCommand::with_name("evaluate")
+            .about("Evaluates a trained nlu engine on a labeled test set")
+            .arg(engine_arg.clone())
+            .arg(Arg::with_name("TEST_SET")
+                .required(true)
+                .takes_value(true)
+                .index(2)
+                .help("path to the test set, in the json format of training datasets"))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("path of the json report to write")))
+        .subcommand(SubCommand::with_name("version")
+            .about("Prints the version of the cli and the model versions it supports"));
+
+    let matches = match app.get_matches_safe() {
+        Ok(matches) => matches,
+        Err(error) => {
+            if error.use_stderr() {
+                eprintln!("{}", error.message);
+                process::exit(EXIT_USAGE);
+            }
+            println!("{}", error.message);
+            process::exit(0);
+        }
+    };
+
+    let result = match matches.subcommand() {
+        ("parse", Some(matches)) => parse(matches),
+        ("batch", Some(matches)) => batch(matches),
+        ("inspect", Some(matches)) => inspect(matches),
+        ("extract-slot", Some(matches)) => extract_slot(matches),
+        ("tag", Some(matches)) => tag(matches),
+        ("evaluate", Some(matches)) => evaluate(matches),
+        ("version", Some(_)) => version(),
+        _ => unreachable!(),
+    };
+
+    if let Err(error) = result {
+        eprintln!("Error: {}", error);
+        for cause in error.iter_causes() {
+            eprintln!("  caused by: {}", cause);
+        }
+        process::exit(exit_code(&error));
+    }
+}
+
+fn exit_code(error: &Error) -> i32 {
+    match SnipsNluError::find_in(error) {
+        Some(SnipsNluError::ModelLoad(_))
+        | Some(SnipsNluError::WrongModelVersion(_, _))
+        | Some(SnipsNluError::IncompatibleModel(_, _, _))
+        | Some(SnipsNluError::MissingFile(_))
+        | Some(SnipsNluError::InvalidJson(_))
+        | Some(SnipsNluError::InvalidModel(_))
+        | Some(SnipsNluError::UnknownProcessingUnit(_))
+        | Some(SnipsNluError::MissingResource(_))
+        | Some(SnipsNluError::CrfModelLoad(_))
+        | Some(SnipsNluError::InvalidDataset(_, _)) => EXIT_MODEL_LOAD,
+        Some(SnipsNluError::UnknownIntent(_)) | Some(SnipsNluError::UnknownSlot(_)) => EXIT_USAGE,
+        _ if error.iter_chain().any(|cause| cause.downcast_ref::<io::Error>().is_some()) => {
+            EXIT_IO
+        }
+        _ => EXIT_FAILURE,
+    }
+}
+
+fn load_engine(matches: &ArgMatches) -> Result<SnipsNluEngine> {
+    let engine_path = Path::new(matches.value_of("NLU_ENGINE").unwrap());
+    if engine_path.is_file() {
+        let zip_file = File::open(engine_path)
+            .with_context(|_| format!("Cannot open nlu engine archive {:?}", engine_path))?;
+        SnipsNluEngine::from_zip(zip_file)
+    } else {
+        SnipsNluEngine::from_path(engine_path)
+    }
+}
+
+fn intents_filter(matches: &ArgMatches) -> Option<Vec<String>> {
+    matches
+        .values_of("intents")
+        .map(|intents| intents.map(|intent| intent.to_string()).collect())
+}
+
+fn parse(matches: &ArgMatches) -> Result<()> {
+    let engine = load_engine(matches)?;
+    let intents_filter = intents_filter(matches);
+    let intents_filter = intents_filter.as_ref().map(|intents| &**intents);
+
+    if let Some(query) = matches.value_of("QUERY") {
+        let result = engine.parse(query, intents_filter)?;
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
 
     loop {
         print!("> ");
-        io::stdout().flush().unwrap();
+        io::stdout().flush()?;
         let mut query = String::new();
-        io::stdin().read_line(&mut query).unwrap();
-        let result = engine.parse(query.trim(), None).unwrap();
-        let result_json = serde_json::to_string_pretty(&result).unwrap();
-        println!("{}", result_json);
+        if io::stdin().read_line(&mut query)? == 0 {
+            // End of input
+            println!();
+            return Ok(());
+        }
+        let query = query.trim();
+        if query.is_empty() {
+            continue;
+        }
+        match engine.parse(query, intents_filter) {
+            Ok(result) => println!("{}", serde_json::to_string_pretty(&result)?),
+            Err(error) => eprintln!("Error: {}", error),
+        }
+    }
+}
+
+fn batch(matches: &ArgMatches) -> Result<()> {
+    let engine = load_engine(matches)?;
+    let intents_filter = intents_filter(matches);
+    let intents_filter = intents_filter.as_ref().map(|intents| &**intents);
+
+    let reader: Box<BufRead> = match matches.value_of("input") {
+        Some(path) => Box::new(BufReader::new(File::open(path)
+            .with_context(|_| format!("Cannot open input file '{}'", path))?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+    let mut queries = vec![];
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let query = parse_batch_query(&line)
+            .with_context(|_| format!("Invalid query at line {}", line_index + 1))?;
+        queries.push(query);
+    }
+
+    let inputs: Vec<&str> = queries.iter().map(|query| query.as_str()).collect();
+    let results = engine.parse_batch(&inputs, intents_filter)?;
+
+    let mut writer: Box<Write> = match matches.value_of("output") {
+        Some(path) => Box::new(BufWriter::new(File::create(path)
+            .with_context(|_| format!("Cannot create output file '{}'", path))?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    for result in results {
+        writeln!(writer, "{}", serde_json::to_string(&result)?)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn parse_batch_query(line: &str) -> Result<String> {
+    match serde_json::from_str(line)? {
+        Value::String(query) => Ok(query),
+        Value::Object(mut object) => match object.remove("input") {
+            Some(Value::String(query)) => Ok(query),
+            _ => Err(format_err!("Missing \"input\" string field")),
+        },
+        _ => Err(format_err!("Expected a json string or object")),
+    }
+}
+
+fn inspect(matches: &ArgMatches) -> Result<()> {
+    let engine = load_engine(matches)?;
+    println!("{}", serde_json::to_string_pretty(&engine.info())?);
+    Ok(())
+}
+
+fn extract_slot(matches: &ArgMatches) -> Result<()> {
+    let engine = load_engine(matches)?;
+    let query = matches.value_of("QUERY").unwrap();
+    let intent = matches.value_of("intent").unwrap();
+    let slot_name = matches.value_of("slot").unwrap();
+    let slot = engine.extract_slot(query.to_string(), intent, slot_name)?;
+    println!("{}", serde_json::to_string_pretty(&slot)?);
+    Ok(())
+}
+
+fn tag(matches: &ArgMatches) -> Result<()> {
+    let engine = load_engine(matches)?;
+    let query = matches.value_of("QUERY").unwrap();
+    let intent = matches.value_of("intent").unwrap();
+    let tagged_tokens = engine.tag_tokens(query, intent)?;
+    println!("{}", serde_json::to_string_pretty(&tagged_tokens)?);
+    Ok(())
+}
+
+fn version() -> Result<()> {
+    println!("snips-nlu {}", env!("CARGO_PKG_VERSION"));
+    println!("model version: {}", snips_nlu_lib::MODEL_VERSION);
+    println!("compatible model versions: {}", snips_nlu_lib::COMPATIBLE_MODEL_VERSIONS);
+    Ok(())
+}
+
+fn evaluate(matches: &ArgMatches) -> Result<()> {
+    let test_set_path = matches.value_of("TEST_SET").unwrap();
+
+    eprintln!("Loading the nlu engine...");
+    let engine = load_engine(matches)?;
+    let test_set = Dataset::from_path(test_set_path)?;
+
+    eprintln!("Evaluating the nlu engine...\n");
+    let report = evaluate_engine(&engine, &test_set)?;
+    print_report(&report);
+
+    if let Some(output_path) = matches.value_of("output") {
+        let output_file = File::create(output_path)
+            .with_context(|_| format!("Cannot create report file '{}'", output_path))?;
+        serde_json::to_writer_pretty(output_file, &report)?;
+        println!("\nReport written to {}", output_path);
+    }
+    Ok(())
+}
+
+fn print_report(report: &EvaluationReport) {
+    println!("Intents:");
+    println!("{:<30} {:>9} {:>9} {:>9}", "intent", "precision", "recall", "f1");
+    for (intent, metrics) in &report.intents {
+        let intent_metrics = &metrics.intent;
+        println!("{:<30} {:>9.3} {:>9.3} {:>9.3}",
+                 intent, intent_metrics.precision, intent_metrics.recall, intent_metrics.f1);
+    }
+
+    println!("\nSlots (exact match / token level f1):");
+    for (intent, metrics) in &report.intents {
+        for (slot_name, slot_metrics) in &metrics.slots {
+            println!("{:<45} {:>9.3} {:>9.3}",
+                     format!("{}/{}", intent, slot_name),
+                     slot_metrics.exact_match.f1,
+                     slot_metrics.token_level.f1);
+        }
+    }
+
+    println!("\nConfusion matrix (rows: expected, columns: found):");
+    let confusion_matrix = &report.confusion_matrix;
+    for (intent, row) in confusion_matrix.intents.iter().zip(&confusion_matrix.matrix) {
+        let counts: Vec<String> = row.iter().map(|count| format!("{:>5}", count)).collect();
+        println!("{:<30} {}", intent, counts.join(""));
+    }
+
+    println!("\nMisparsed utterances ({}):", report.parsing_errors.len());
+    for error in &report.parsing_errors {
+        println!("- \"{}\": expected {}, found {}",
+                 error.input, error.expected_intent, error.found_intent);
+        if error.expected_slots != error.found_slots {
+            let format_slots = |slots: &[EvaluatedSlot]| slots
+                .iter()
+                .map(|slot| format!("{}=\"{}\"", slot.slot_name, slot.value))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("    expected slots: [{}]", format_slots(&error.expected_slots));
+            println!("    found slots:    [{}]", format_slots(&error.found_slots));
+        }
+    }
+
+    println!("\nExact match accuracy: {:.3} ({} utterances)",
+             report.exact_match_accuracy, report.nb_utterances);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_batch_query_works() {
+        // Given
+        let lines = vec![r#""make me a coffee""#, r#"{"input": "make me a tea", "id": 2}"#];
+
+        // When
+        let queries: Vec<String> = lines
+            .into_iter()
+            .map(|line| parse_batch_query(line).unwrap())
+            .collect();
+
+        // Then
+        assert_eq!(vec!["make me a coffee".to_string(), "make me a tea".to_string()], queries);
+    }
+
+    #[test]
+    fn parse_batch_query_should_reject_invalid_lines() {
+        // Given
+        let lines = vec![
+            "make me a coffee",
+            r#"{"text": "make me a tea"}"#,
+            r#"{"input": 2}"#,
+            "[]",
+        ];
+
+        // When
+        let results: Vec<Result<String>> =
+            lines.into_iter().map(|line| parse_batch_query(line)).collect();
+
+        // Then
+        assert!(results.iter().all(|result| result.is_err()));
+    }
+
+    #[test]
+    fn exit_code_works() {
+        // Given
+        let usage_error: Error = SnipsNluError::UnknownIntent("MakeTea".to_string()).into();
+        let model_load_error: Error = Err::<(), _>(SnipsNluError::MissingFile("model".to_string()))
+            .with_context(|_| SnipsNluError::ModelLoad("engine".to_string()))
+            .unwrap_err()
+            .into();
+        let io_error: Error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "broken pipe"))
+            .with_context(|_| "Cannot write results")
+            .unwrap_err()
+            .into();
+        let other_error = format_err!("Something went wrong");
+
+        // When
+        let exit_codes = vec![
+            exit_code(&usage_error),
+            exit_code(&model_load_error),
+            exit_code(&io_error),
+            exit_code(&other_error),
+        ];
+
+        // Then
+        assert_eq!(vec![EXIT_USAGE, EXIT_MODEL_LOAD, EXIT_IO, EXIT_FAILURE], exit_codes);
     }
 }
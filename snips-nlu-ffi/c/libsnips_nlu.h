@@ -108,32 +108,104 @@ typedef struct CIntentParserResult{
     CSlotList *const slots;
 } CIntentParserResult;
 
+typedef struct CNluEngineResult {
+    CIntentParserResult *const parsing_result;
+    /**
+      * Upper bounds of the probabilities of the slots, in the same order as the slots of the
+      * parsing result. Each bound is the lowest marginal probability of the tags of the slot
+      * tokens, which is at least the probability of the whole slot.
+      * A negative value means that no score is available for the corresponding slot.
+      */
+    float *const slots_score_upper_bounds;
+    /**
+      * Fuzzy matching scores of the slots, in the same order as the slots of the parsing result.
+      * A negative value means that the corresponding slot value was not fuzzy matched.
+      */
+    float *const slots_match_scores;
+    int slots_size;
+} CNluEngineResult;
+
 typedef struct CSnipsNluEngine CSnipsNluEngine;
 
-typedef enum SNIPS_RESULT {
-	SNIPS_RESULT_OK = 0,
-	SNIPS_RESULT_KO = 1,
-} SNIPS_RESULT;
+typedef enum SNIPS_NLU_RESULT {
+	SNIPS_NLU_RESULT_OK = 0,
+	SNIPS_NLU_RESULT_ERROR = 1,
+	SNIPS_NLU_RESULT_MODEL_LOAD = 2,
+	SNIPS_NLU_RESULT_WRONG_MODEL_VERSION = 3,
+	SNIPS_NLU_RESULT_MISSING_FILE = 4,
+	SNIPS_NLU_RESULT_INVALID_JSON = 5,
+	SNIPS_NLU_RESULT_INVALID_MODEL = 6,
+	SNIPS_NLU_RESULT_UNKNOWN_PROCESSING_UNIT = 7,
+	SNIPS_NLU_RESULT_WRONG_PROCESSING_UNIT = 8,
+	SNIPS_NLU_RESULT_UNSUPPORTED_FEATURE = 9,
+	SNIPS_NLU_RESULT_MISSING_RESOURCE = 10,
+	SNIPS_NLU_RESULT_CRF_MODEL_LOAD = 11,
+	SNIPS_NLU_RESULT_UNKNOWN_INTENT = 12,
+	SNIPS_NLU_RESULT_UNKNOWN_SLOT = 13,
+	SNIPS_NLU_RESULT_UNSUPPORTED_LANGUAGE = 14,
+	SNIPS_NLU_RESULT_INCOMPATIBLE_MODEL = 15,
+	SNIPS_NLU_RESULT_INVALID_DATASET = 16,
+	SNIPS_NLU_RESULT_UNKNOWN_ASSISTANT = 17,
+	SNIPS_NLU_RESULT_UNKNOWN_ENTITY = 18,
+	SNIPS_NLU_RESULT_MISSING_SLOT_FILLER = 19,
+} SNIPS_NLU_RESULT;
+
+/* Former result type, kept for backward compatibility. As failures are now reported with
+   detailed codes, a result must be compared with SNIPS_RESULT_OK rather than SNIPS_RESULT_KO */
+typedef SNIPS_NLU_RESULT SNIPS_RESULT;
+#define SNIPS_RESULT_OK SNIPS_NLU_RESULT_OK
+#define SNIPS_RESULT_KO SNIPS_NLU_RESULT_ERROR
+
+SNIPS_NLU_RESULT snips_nlu_engine_create_from_file(char const* file_path, CSnipsNluEngine** client);
+
+SNIPS_NLU_RESULT snips_nlu_engine_create_from_dir(char const* root_dir, CSnipsNluEngine** client);
+
+SNIPS_NLU_RESULT snips_nlu_engine_create_from_zip(unsigned char const* zip, unsigned int zip_size, CSnipsNluEngine** client);
+
+SNIPS_NLU_RESULT snips_nlu_engine_reload_from_dir(CSnipsNluEngine const* client, char const* root_dir);
+
+SNIPS_NLU_RESULT snips_nlu_engine_reload_from_zip(CSnipsNluEngine const* client, unsigned char const* zip, unsigned int zip_size);
+
+SNIPS_NLU_RESULT snips_nlu_engine_run_parse(CSnipsNluEngine const* client, char const* input, CIntentParserResult** result);
+
+SNIPS_NLU_RESULT snips_nlu_engine_run_detailed_parse(CSnipsNluEngine const* client, char const* input, CNluEngineResult** result);
+
+SNIPS_NLU_RESULT snips_nlu_engine_run_parse_into_json(CSnipsNluEngine const* client, char const* input, char** result_json);
+
+/* Same as snips_nlu_engine_run_parse_into_json, where the json object additionally holds the
+   "rejectedIntent" field, and where each slot additionally holds the "scoreUpperBound" field along
+   with the optional "values" and "matchScore" fields.
+   This is the only function which groups coordinated values, such as "milk" and "eggs" in
+   "add milk and eggs", into a single slot whose "value" is the list of their values and whose
+   "values" are their slots. The other functions return each of these values as a separate slot. */
+SNIPS_NLU_RESULT snips_nlu_engine_run_detailed_parse_into_json(CSnipsNluEngine const* client, char const* input, char** result_json);
 
-SNIPS_RESULT snips_nlu_engine_create_from_file(char const* file_path, CSnipsNluEngine** client);
+/* The threshold must be between 0 and 1 */
+SNIPS_NLU_RESULT snips_nlu_engine_set_intent_probability_threshold(CSnipsNluEngine const* client, float threshold);
 
-SNIPS_RESULT snips_nlu_engine_create_from_dir(char const* root_dir, CSnipsNluEngine** client);
+/* The threshold must not be greater than 1, and a negative threshold disables fuzzy matching */
+SNIPS_NLU_RESULT snips_nlu_engine_set_fuzzy_matching_threshold(CSnipsNluEngine const* client, float threshold);
 
-SNIPS_RESULT snips_nlu_engine_create_from_zip(unsigned char const* zip, unsigned int zip_size, CSnipsNluEngine** client);
+/* Adds values to a custom entity, where values_json is a json list of
+   {"value": ..., "synonyms": [...]} objects. The values only live in the current engine, and are
+   lost when the engine is reloaded. */
+SNIPS_NLU_RESULT snips_nlu_engine_add_entity_values(CSnipsNluEngine const* client, char const* entity, char const* values_json);
 
-SNIPS_RESULT snips_nlu_engine_run_parse(CSnipsNluEngine const* client, char const* input, CIntentParserResult** result);
+/* Removes values from a custom entity, along with their synonyms, where values_json is a json list
+   of values */
+SNIPS_NLU_RESULT snips_nlu_engine_remove_entity_values(CSnipsNluEngine const* client, char const* entity, char const* values_json);
 
-SNIPS_RESULT snips_nlu_engine_run_parse_into_json(CSnipsNluEngine const* client, char const* input, char** result_json);
+SNIPS_NLU_RESULT snips_nlu_engine_destroy_string(char* string);
 
-SNIPS_RESULT snips_nlu_engine_destroy_string(char* string);
+SNIPS_NLU_RESULT snips_nlu_engine_destroy_client(CSnipsNluEngine* client);
 
-SNIPS_RESULT snips_nlu_engine_destroy_client(CSnipsNluEngine* client);
+SNIPS_NLU_RESULT snips_nlu_engine_destroy_result(CIntentParserResult* result);
 
-SNIPS_RESULT snips_nlu_engine_destroy_result(CIntentParserResult* result);
+SNIPS_NLU_RESULT snips_nlu_engine_destroy_detailed_result(CNluEngineResult* result);
 
-SNIPS_RESULT snips_nlu_engine_get_last_error(char **error);
+SNIPS_NLU_RESULT snips_nlu_engine_get_last_error(char **error);
 
-SNIPS_RESULT snips_nlu_engine_get_model_version(char **version);
+SNIPS_NLU_RESULT snips_nlu_engine_get_model_version(char **version);
 
 #ifdef __cplusplus
 }
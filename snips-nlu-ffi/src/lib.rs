@@ -9,6 +9,7 @@ extern crate lazy_static;
 extern crate libc;
 extern crate serde_json;
 extern crate snips_nlu_lib;
+extern crate snips_nlu_ontology;
 extern crate snips_nlu_ontology_ffi_macros;
 
 use failure::ResultExt;
@@ -18,32 +19,198 @@ use std::io::Cursor;
 use std::slice;
 use std::sync::Mutex;
 
-use snips_nlu_lib::SnipsNluEngine;
+use snips_nlu_lib::dataset::EntityValue;
+use snips_nlu_lib::{EngineHandle, NluEngineResult, SnipsNluError};
+use snips_nlu_ontology::IntentParserResult;
 use snips_nlu_ontology_ffi_macros::CIntentParserResult;
 
 use ffi_utils::*;
 
 type Result<T> = std::result::Result<T, failure::Error>;
 
-pub struct CSnipsNluEngine(std::sync::Mutex<SnipsNluEngine>);
+pub struct CSnipsNluEngine(EngineHandle);
 
+/// Result code of the engine functions, which identifies the kind of error when the call failed
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SNIPS_NLU_RESULT {
+    SNIPS_NLU_RESULT_OK = 0,
+    SNIPS_NLU_RESULT_ERROR = 1,
+    SNIPS_NLU_RESULT_MODEL_LOAD = 2,
+    SNIPS_NLU_RESULT_WRONG_MODEL_VERSION = 3,
+    SNIPS_NLU_RESULT_MISSING_FILE = 4,
+    SNIPS_NLU_RESULT_INVALID_JSON = 5,
+    SNIPS_NLU_RESULT_INVALID_MODEL = 6,
+    SNIPS_NLU_RESULT_UNKNOWN_PROCESSING_UNIT = 7,
+    SNIPS_NLU_RESULT_WRONG_PROCESSING_UNIT = 8,
+    SNIPS_NLU_RESULT_UNSUPPORTED_FEATURE = 9,
+    SNIPS_NLU_RESULT_MISSING_RESOURCE = 10,
+    SNIPS_NLU_RESULT_CRF_MODEL_LOAD = 11,
+    SNIPS_NLU_RESULT_UNKNOWN_INTENT = 12,
+    SNIPS_NLU_RESULT_UNKNOWN_SLOT = 13,
+    SNIPS_NLU_RESULT_UNSUPPORTED_LANGUAGE = 14,
+    SNIPS_NLU_RESULT_INCOMPATIBLE_MODEL = 15,
+    SNIPS_NLU_RESULT_INVALID_DATASET = 16,
+    SNIPS_NLU_RESULT_UNKNOWN_ASSISTANT = 17,
+    SNIPS_NLU_RESULT_UNKNOWN_ENTITY = 18,
+    SNIPS_NLU_RESULT_MISSING_SLOT_FILLER = 19,
+}
+
+impl<'a> From<&'a failure::Error> for SNIPS_NLU_RESULT {
+    fn from(error: &failure::Error) -> Self {
+        match SnipsNluError::find_in(error) {
+            Some(SnipsNluError::ModelLoad(_)) => SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_MODEL_LOAD,
+            Some(SnipsNluError::WrongModelVersion(_, _)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_WRONG_MODEL_VERSION,
+            Some(SnipsNluError::IncompatibleModel(_, _, _)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_INCOMPATIBLE_MODEL,
+            Some(SnipsNluError::MissingFile(_)) => SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_MISSING_FILE,
+            Some(SnipsNluError::InvalidJson(_)) => SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_INVALID_JSON,
+            Some(SnipsNluError::InvalidModel(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_INVALID_MODEL,
+            Some(SnipsNluError::UnknownProcessingUnit(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_UNKNOWN_PROCESSING_UNIT,
+            Some(SnipsNluError::WrongProcessingUnit(_, _)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_WRONG_PROCESSING_UNIT,
+            Some(SnipsNluError::UnsupportedFeature(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_UNSUPPORTED_FEATURE,
+            Some(SnipsNluError::MissingResource(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_MISSING_RESOURCE,
+            Some(SnipsNluError::CrfModelLoad(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_CRF_MODEL_LOAD,
+            Some(SnipsNluError::UnknownIntent(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_UNKNOWN_INTENT,
+            Some(SnipsNluError::UnknownSlot(_)) => SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_UNKNOWN_SLOT,
+            Some(SnipsNluError::UnknownEntity(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_UNKNOWN_ENTITY,
+            Some(SnipsNluError::MissingSlotFiller(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_MISSING_SLOT_FILLER,
+            Some(SnipsNluError::UnsupportedLanguage(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_UNSUPPORTED_LANGUAGE,
+            Some(SnipsNluError::InvalidDataset(_, _)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_INVALID_DATASET,
+            Some(SnipsNluError::UnknownAssistant(_)) =>
+                SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_UNKNOWN_ASSISTANT,
+            None => SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_ERROR,
+        }
+    }
+}
+
+/// Parsing result along with the score upper bounds and match scores of its slots
+#[repr(C)]
+pub struct CNluEngineResult {
+    pub parsing_result: *const CIntentParserResult,
+    /// Upper bounds of the probabilities of the slots, in the same order as the slots of the
+    /// parsing result. Each bound is the lowest marginal probability of the tags of the slot
+    /// tokens, which is at least the probability of the whole slot. A negative value means that no
+    /// score is available for the corresponding slot.
+    pub slots_score_upper_bounds: *const libc::c_float,
+    /// Fuzzy matching scores of the slots, in the same order as the slots of the parsing result.
+    /// A negative value means that the corresponding slot value was not fuzzy matched.
+    pub slots_match_scores: *const libc::c_float,
+    pub slots_size: libc::c_int,
+}
+
+impl From<NluEngineResult> for CNluEngineResult {
+    fn from(result: NluEngineResult) -> Self {
+        let (score_upper_bounds, match_scores): (Vec<_>, Vec<_>) = result
+            .slots
+            .as_ref()
+            .map(|slots| {
+                slots
+                    .iter()
+                    .flat_map(|slot| slot.individual_values())
+                    .map(|slot| {
+                        (
+                            slot.score_upper_bound.unwrap_or(-1.0),
+                            slot.match_score.unwrap_or(-1.0),
+                        )
+                    })
+                    .unzip()
+            })
+            .unwrap_or_else(|| (vec![], vec![]));
+        let slots_size = score_upper_bounds.len() as libc::c_int;
+        let slots_score_upper_bounds =
+            Box::into_raw(score_upper_bounds.into_boxed_slice()) as *const libc::c_float;
+        let slots_match_scores =
+            Box::into_raw(match_scores.into_boxed_slice()) as *const libc::c_float;
+        let parsing_result =
+            CIntentParserResult::from(IntentParserResult::from(result)).into_raw_pointer();
+        CNluEngineResult {
+            parsing_result,
+            slots_score_upper_bounds,
+            slots_match_scores,
+            slots_size,
+        }
+    }
+}
+
+impl Drop for CNluEngineResult {
+    fn drop(&mut self) {
+        let _ = unsafe { CIntentParserResult::from_raw_pointer(self.parsing_result) };
+        let _ = unsafe {
+            Box::from_raw(slice::from_raw_parts_mut(
+                self.slots_score_upper_bounds as *mut libc::c_float,
+                self.slots_size as usize,
+            ))
+        };
+        let _ = unsafe {
+            Box::from_raw(slice::from_raw_parts_mut(
+                self.slots_match_scores as *mut libc::c_float,
+                self.slots_size as usize,
+            ))
+        };
+    }
+}
+
+/// Returns the current engine of the handle, which stays alive until the end of the call even if
+/// the engine gets reloaded meanwhile
 macro_rules! get_nlu_engine {
     ($opaque:ident) => {{
-        unsafe { <CSnipsNluEngine as ffi_utils::RawBorrow<CSnipsNluEngine>>::raw_borrow($opaque) }?
+        get_engine_handle!($opaque).engine()
+    }};
+}
+
+macro_rules! get_engine_handle {
+    ($opaque:ident) => {{
+        &unsafe { <CSnipsNluEngine as ffi_utils::RawBorrow<CSnipsNluEngine>>::raw_borrow($opaque) }?
             .0
-            .lock()
-            .map_err(|e| format_err!("poisoning pointer: {}", e))?
     }};
 }
 
-generate_error_handling!(snips_nlu_engine_get_last_error);
+// Error handling of `ffi_utils`, whose `wrap!` macro records the message of the last error in
+// `LAST_ERROR`, with a getter returning a result code like the other engine functions
+lazy_static! {
+    static ref LAST_ERROR: Mutex<String> = Mutex::new("".to_string());
+}
+
+/// Same as `wrap!`, which keeps track of the last error message, but returns the result code
+/// corresponding to the kind of error
+macro_rules! wrap_with_code {
+    ($e:expr) => {{
+        let result = $e;
+        let result_code = match result {
+            Ok(_) => SNIPS_NLU_RESULT::SNIPS_NLU_RESULT_OK,
+            Err(ref error) => SNIPS_NLU_RESULT::from(error),
+        };
+        let _ = wrap!(result);
+        result_code
+    }};
+}
+
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_get_last_error(
+    error: *mut *const libc::c_char,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(get_last_error(error))
+}
 
 #[no_mangle]
 pub extern "C" fn snips_nlu_engine_create_from_dir(
     root_dir: *const libc::c_char,
     client: *mut *const CSnipsNluEngine,
-) -> SNIPS_RESULT {
-    wrap!(create_from_dir(root_dir, client))
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(create_from_dir(root_dir, client))
 }
 
 #[no_mangle]
@@ -51,8 +218,27 @@ pub extern "C" fn snips_nlu_engine_create_from_zip(
     zip: *const libc::c_uchar,
     zip_size: libc::c_uint,
     client: *mut *const CSnipsNluEngine,
-) -> SNIPS_RESULT {
-    wrap!(create_from_zip(zip, zip_size, client))
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(create_from_zip(zip, zip_size, client))
+}
+
+/// Swaps in the engine located in `root_dir`, while the calls in progress complete with the
+/// previous engine. The client keeps its current engine when the new one cannot be loaded.
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_reload_from_dir(
+    client: *const CSnipsNluEngine,
+    root_dir: *const libc::c_char,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(reload_from_dir(client, root_dir))
+}
+
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_reload_from_zip(
+    client: *const CSnipsNluEngine,
+    zip: *const libc::c_uchar,
+    zip_size: libc::c_uint,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(reload_from_zip(client, zip, zip_size))
 }
 
 #[no_mangle]
@@ -60,8 +246,17 @@ pub extern "C" fn snips_nlu_engine_run_parse(
     client: *const CSnipsNluEngine,
     input: *const libc::c_char,
     result: *mut *const CIntentParserResult,
-) -> SNIPS_RESULT {
-    wrap!(run_parse(client, input, result))
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(run_parse(client, input, result))
+}
+
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_run_detailed_parse(
+    client: *const CSnipsNluEngine,
+    input: *const libc::c_char,
+    result: *mut *const CNluEngineResult,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(run_detailed_parse(client, input, result))
 }
 
 #[no_mangle]
@@ -69,32 +264,94 @@ pub extern "C" fn snips_nlu_engine_run_parse_into_json(
     client: *const CSnipsNluEngine,
     input: *const libc::c_char,
     result_json: *mut *const libc::c_char,
-) -> SNIPS_RESULT {
-    wrap!(run_parse_into_json(client, input, result_json))
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(run_parse_into_json(client, input, result_json))
+}
+
+/// Same as `snips_nlu_engine_run_parse_into_json`, with the json of the detailed parsing result,
+/// which additionally holds the rejected intent and the score upper bounds of the slots
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_run_detailed_parse_into_json(
+    client: *const CSnipsNluEngine,
+    input: *const libc::c_char,
+    result_json: *mut *const libc::c_char,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(run_detailed_parse_into_json(client, input, result_json))
+}
+
+/// Sets the probability under which the intent of a parsing result is rejected, which must be
+/// between 0 and 1
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_set_intent_probability_threshold(
+    client: *const CSnipsNluEngine,
+    threshold: libc::c_float,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(set_intent_probability_threshold(client, threshold))
+}
+
+/// Enables the fuzzy matching of custom slot values with the given similarity threshold, or
+/// disables it when the threshold is negative
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_set_fuzzy_matching_threshold(
+    client: *const CSnipsNluEngine,
+    threshold: libc::c_float,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(set_fuzzy_matching_threshold(client, threshold))
+}
+
+/// Adds values to a custom entity of the current engine, where `values_json` is a json list of
+/// `{"value": ..., "synonyms": [...]}` objects. The values are lost when the engine is reloaded.
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_add_entity_values(
+    client: *const CSnipsNluEngine,
+    entity: *const libc::c_char,
+    values_json: *const libc::c_char,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(add_entity_values(client, entity, values_json))
+}
+
+/// Removes values from a custom entity of the current engine, along with their synonyms, where
+/// `values_json` is a json list of values
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_remove_entity_values(
+    client: *const CSnipsNluEngine,
+    entity: *const libc::c_char,
+    values_json: *const libc::c_char,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(remove_entity_values(client, entity, values_json))
 }
 
 #[no_mangle]
-pub extern "C" fn snips_nlu_engine_destroy_string(string: *mut libc::c_char) -> SNIPS_RESULT {
-    wrap!(unsafe { CString::from_raw_pointer(string) })
+pub extern "C" fn snips_nlu_engine_destroy_string(string: *mut libc::c_char) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(unsafe { CString::from_raw_pointer(string) })
 }
 
 #[no_mangle]
-pub extern "C" fn snips_nlu_engine_destroy_client(client: *mut CSnipsNluEngine) -> SNIPS_RESULT {
-    wrap!(unsafe { CSnipsNluEngine::from_raw_pointer(client) })
+pub extern "C" fn snips_nlu_engine_destroy_client(
+    client: *mut CSnipsNluEngine,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(unsafe { CSnipsNluEngine::from_raw_pointer(client) })
 }
 
 #[no_mangle]
 pub extern "C" fn snips_nlu_engine_destroy_result(
     result: *mut CIntentParserResult,
-) -> SNIPS_RESULT {
-    wrap!(unsafe { CIntentParserResult::from_raw_pointer(result) })
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(unsafe { CIntentParserResult::from_raw_pointer(result) })
+}
+
+#[no_mangle]
+pub extern "C" fn snips_nlu_engine_destroy_detailed_result(
+    result: *mut CNluEngineResult,
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(unsafe { CNluEngineResult::from_raw_pointer(result) })
 }
 
 #[no_mangle]
 pub extern "C" fn snips_nlu_engine_get_model_version(
     version: *mut *const libc::c_char,
-) -> SNIPS_RESULT {
-    wrap!(get_model_version(version))
+) -> SNIPS_NLU_RESULT {
+    wrap_with_code!(get_model_version(version))
 }
 
 fn create_from_dir(
@@ -103,9 +360,9 @@ fn create_from_dir(
 ) -> Result<()> {
     let root_dir = create_rust_string_from!(root_dir);
 
-    let nlu_engine = SnipsNluEngine::from_path(root_dir)?;
+    let engine_handle = EngineHandle::from_path(root_dir)?;
 
-    let raw_pointer = CSnipsNluEngine(Mutex::new(nlu_engine)).into_raw_pointer();
+    let raw_pointer = CSnipsNluEngine(engine_handle).into_raw_pointer();
     unsafe { *client = raw_pointer };
 
     Ok(())
@@ -118,13 +375,28 @@ fn create_from_zip(
 ) -> Result<()> {
     let slice = unsafe { slice::from_raw_parts(zip, zip_size as usize) };
     let reader = Cursor::new(slice.to_owned());
-    let nlu_engine = SnipsNluEngine::from_zip(reader)?;
-    let raw_pointer = CSnipsNluEngine(Mutex::new(nlu_engine)).into_raw_pointer();
+    let engine_handle = EngineHandle::from_zip(reader)?;
+    let raw_pointer = CSnipsNluEngine(engine_handle).into_raw_pointer();
     unsafe { *client = raw_pointer };
 
     Ok(())
 }
 
+fn reload_from_dir(client: *const CSnipsNluEngine, root_dir: *const libc::c_char) -> Result<()> {
+    let root_dir = create_rust_string_from!(root_dir);
+    get_engine_handle!(client).reload_from_path(root_dir)
+}
+
+fn reload_from_zip(
+    client: *const CSnipsNluEngine,
+    zip: *const libc::c_uchar,
+    zip_size: libc::c_uint,
+) -> Result<()> {
+    let slice = unsafe { slice::from_raw_parts(zip, zip_size as usize) };
+    let reader = Cursor::new(slice.to_owned());
+    get_engine_handle!(client).reload_from_zip(reader)
+}
+
 fn run_parse(
     client: *const CSnipsNluEngine,
     input: *const libc::c_char,
@@ -141,6 +413,22 @@ fn run_parse(
     Ok(())
 }
 
+fn run_detailed_parse(
+    client: *const CSnipsNluEngine,
+    input: *const libc::c_char,
+    result: *mut *const CNluEngineResult,
+) -> Result<()> {
+    let input = create_rust_string_from!(input);
+    let nlu_engine = get_nlu_engine!(client);
+
+    let results = nlu_engine.parse_detailed(&input, None)?;
+    let raw_pointer = CNluEngineResult::from(results).into_raw_pointer();
+
+    unsafe { *result = raw_pointer };
+
+    Ok(())
+}
+
 fn run_parse_into_json(
     client: *const CSnipsNluEngine,
     input: *const libc::c_char,
@@ -154,6 +442,79 @@ fn run_parse_into_json(
     point_to_string(result_json, serde_json::to_string(&results)?)
 }
 
+fn run_detailed_parse_into_json(
+    client: *const CSnipsNluEngine,
+    input: *const libc::c_char,
+    result_json: *mut *const libc::c_char,
+) -> Result<()> {
+    let input = create_rust_string_from!(input);
+    let nlu_engine = get_nlu_engine!(client);
+
+    let results = nlu_engine.parse_detailed(&input, None)?;
+
+    point_to_string(result_json, serde_json::to_string(&results)?)
+}
+
+fn set_fuzzy_matching_threshold(
+    client: *const CSnipsNluEngine,
+    threshold: libc::c_float,
+) -> Result<()> {
+    if threshold.is_nan() || threshold > 1.0 {
+        bail!("Invalid fuzzy matching threshold {}, expected a value lower than 1", threshold)
+    }
+    get_engine_handle!(client).update_config(|config| {
+        config.fuzzy_matching_threshold = if threshold < 0.0 { None } else { Some(threshold) }
+    });
+    Ok(())
+}
+
+fn set_intent_probability_threshold(
+    client: *const CSnipsNluEngine,
+    threshold: libc::c_float,
+) -> Result<()> {
+    if threshold.is_nan() || threshold < 0.0 || threshold > 1.0 {
+        bail!(
+            "Invalid intent probability threshold {}, expected a value between 0 and 1",
+            threshold
+        )
+    }
+    get_engine_handle!(client)
+        .update_config(|config| config.intent_probability_threshold = threshold);
+    Ok(())
+}
+
+fn add_entity_values(
+    client: *const CSnipsNluEngine,
+    entity: *const libc::c_char,
+    values_json: *const libc::c_char,
+) -> Result<()> {
+    let entity = create_rust_string_from!(entity);
+    let values_json = create_rust_string_from!(values_json);
+    let values: Vec<EntityValue> = serde_json::from_str(&values_json)
+        .with_context(|_| "Cannot deserialize the entity values")?;
+    get_nlu_engine!(client).add_entity_values(&entity, &values)
+}
+
+fn remove_entity_values(
+    client: *const CSnipsNluEngine,
+    entity: *const libc::c_char,
+    values_json: *const libc::c_char,
+) -> Result<()> {
+    let entity = create_rust_string_from!(entity);
+    let values_json = create_rust_string_from!(values_json);
+    let values: Vec<String> = serde_json::from_str(&values_json)
+        .with_context(|_| "Cannot deserialize the entity values")?;
+    get_nlu_engine!(client).remove_entity_values(&entity, &values)
+}
+
+fn get_last_error(error: *mut *const libc::c_char) -> Result<()> {
+    let last_error = LAST_ERROR
+        .lock()
+        .map_err(|_| format_err!("The last error cannot be retrieved"))?
+        .clone();
+    point_to_string(error, last_error)
+}
+
 fn get_model_version(version: *mut *const libc::c_char) -> Result<()> {
     point_to_string(version, snips_nlu_lib::MODEL_VERSION.to_string())
 }
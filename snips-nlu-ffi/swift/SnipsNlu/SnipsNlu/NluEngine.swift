@@ -253,12 +253,12 @@ public class NluEngine {
     private var client: OpaquePointer? = nil
 
     public init(nluEngineDirectoryURL: URL) throws {
-        guard snips_nlu_engine_create_from_dir(nluEngineDirectoryURL.path, &client) == SNIPS_RESULT_OK else { throw NluEngineError.getLast }
+        guard snips_nlu_engine_create_from_dir(nluEngineDirectoryURL.path, &client) == SNIPS_NLU_RESULT_OK else { throw NluEngineError.getLast }
     }
 
     public init(nluEngineZipData: Data) throws {
         try nluEngineZipData.withUnsafeBytes { (bytes: UnsafePointer<UInt8>) in
-            guard snips_nlu_engine_create_from_zip(bytes, UInt32(nluEngineZipData.count), &client) == SNIPS_RESULT_OK else { throw NluEngineError.getLast }
+            guard snips_nlu_engine_create_from_zip(bytes, UInt32(nluEngineZipData.count), &client) == SNIPS_NLU_RESULT_OK else { throw NluEngineError.getLast }
         }
     }
 
@@ -271,7 +271,7 @@ public class NluEngine {
 
     public func parse(string: String) throws -> IntentParserResult {
         var cResult: UnsafeMutablePointer<CIntentParserResult>? = nil;
-        guard snips_nlu_engine_run_parse(self.client, string, &cResult) == SNIPS_RESULT_OK else { throw NluEngineError.getLast }
+        guard snips_nlu_engine_run_parse(self.client, string, &cResult) == SNIPS_NLU_RESULT_OK else { throw NluEngineError.getLast }
         defer { snips_nlu_engine_destroy_result(cResult) }
         guard let result = cResult?.pointee else { throw NluEngineError(message: "Can't retrieve result")}
         return try IntentParserResult(cResult: result)
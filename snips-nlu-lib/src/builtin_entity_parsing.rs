@@ -1,22 +1,32 @@
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::sync::{Arc, Mutex, Weak};
 
 use lru_cache::LruCache;
+use memory::HeapSize;
 
 use snips_nlu_ontology::{BuiltinEntityKind, BuiltinEntity, Language};
 use snips_nlu_ontology_parsers::BuiltinEntityParser;
 
+const CACHE_SHARDS_COUNT: usize = 16;
 
 pub struct CachingBuiltinEntityParser {
     parser: BuiltinEntityParser,
-    cache: Mutex<EntityCache>,
+    // The cache is split into independently locked shards, so that concurrent parsing calls
+    // rarely compete for the same lock
+    cache_shards: Vec<Mutex<EntityCache>>,
 }
 
 impl CachingBuiltinEntityParser {
     pub fn new(lang: Language, cache_capacity: usize) -> Self {
+        let shard_capacity = (cache_capacity + CACHE_SHARDS_COUNT - 1) / CACHE_SHARDS_COUNT;
         CachingBuiltinEntityParser {
             parser: BuiltinEntityParser::new(lang),
-            cache: Mutex::new(EntityCache::new(cache_capacity)),
+            cache_shards: (0..CACHE_SHARDS_COUNT)
+                .map(|_| Mutex::new(EntityCache::new(shard_capacity)))
+                .collect(),
         }
     }
 
@@ -37,33 +47,64 @@ impl CachingBuiltinEntityParser {
                 .unwrap_or_else(|| vec![]),
         };
 
-        self.cache
-            .lock()
-            .unwrap()
-            .cache(&cache_key,
-                   |cache_key| self.parser.extract_entities(&cache_key.input, filter_entity_kinds))
+        let cache_shard = self.get_cache_shard(&cache_key);
+        let cached_value = cache_shard.lock().unwrap().get(&cache_key);
+        if let Some(value) = cached_value {
+            return value;
+        }
+        // The lock is not held while parsing so that other calls can use the shard meanwhile
+        let value = self.parser.extract_entities(&cache_key.input, filter_entity_kinds);
+        cache_shard.lock().unwrap().insert(cache_key, value.clone());
+        value
+    }
+
+    fn get_cache_shard(&self, cache_key: &CacheKey) -> &Mutex<EntityCache> {
+        let mut hasher = DefaultHasher::new();
+        cache_key.hash(&mut hasher);
+        let shard_index = hasher.finish() as usize % self.cache_shards.len();
+        &self.cache_shards[shard_index]
+    }
+}
+
+/// Only the cached entities are measured, as the size of the rules of the underlying parser is not
+/// exposed
+impl HeapSize for CachingBuiltinEntityParser {
+    fn heap_size(&self) -> usize {
+        self.cache_shards
+            .iter()
+            .map(|cache_shard| cache_shard.lock().unwrap().heap_size())
+            .sum()
     }
 }
 
 struct EntityCache(LruCache<CacheKey, Vec<BuiltinEntity>>);
 
+impl HeapSize for EntityCache {
+    fn heap_size(&self) -> usize {
+        self.0
+            .iter()
+            .map(|(key, entities)| {
+                mem::size_of::<(CacheKey, Vec<BuiltinEntity>)>()
+                    + key.input.heap_size()
+                    + key.kinds.capacity() * mem::size_of::<BuiltinEntityKind>()
+                    + entities.capacity() * mem::size_of::<BuiltinEntity>()
+                    + entities.iter().map(|entity| entity.value.heap_size()).sum::<usize>()
+            })
+            .sum()
+    }
+}
+
 impl EntityCache {
     fn new(capacity: usize) -> Self {
         EntityCache(LruCache::new(capacity))
     }
 
-    fn cache<F: Fn(&CacheKey) -> Vec<BuiltinEntity>>(
-        &mut self,
-        key: &CacheKey,
-        producer: F,
-    ) -> Vec<BuiltinEntity> {
-        let cached_value = self.0.get_mut(key).cloned();
-        if let Some(value) = cached_value {
-            return value;
-        }
-        let value = producer(key);
-        self.0.insert(key.clone(), value.clone());
-        value
+    fn get(&mut self, key: &CacheKey) -> Option<Vec<BuiltinEntity>> {
+        self.0.get_mut(key).cloned()
+    }
+
+    fn insert(&mut self, key: CacheKey, value: Vec<BuiltinEntity>) {
+        self.0.insert(key, value);
     }
 }
 
@@ -74,26 +115,22 @@ struct CacheKey {
 }
 
 lazy_static! {
-    static ref CACHED_PARSERS: Mutex<HashMap<Language, Arc<CachingBuiltinEntityParser>>> =
+    static ref CACHED_PARSERS: Mutex<HashMap<Language, Weak<CachingBuiltinEntityParser>>> =
         Mutex::new(HashMap::new());
 }
 
+/// Provides the builtin entity parser of a language, which is shared by all the engines of this
+/// language as long as at least one of them is alive
 pub struct BuiltinEntityParserFactory;
 
 impl BuiltinEntityParserFactory {
     pub fn get(lang: Language) -> Arc<CachingBuiltinEntityParser> {
-        CACHED_PARSERS
-            .lock()
-            .unwrap()
-            .entry(lang)
-            .or_insert_with(|| Arc::new(CachingBuiltinEntityParser::new(lang, 1000)))
-            .clone()
-    }
-
-    pub fn clear() {
-        CACHED_PARSERS
-            .lock()
-            .unwrap()
-            .clear();
+        let mut parsers = CACHED_PARSERS.lock().unwrap();
+        if let Some(parser) = parsers.get(&lang).and_then(|parser| parser.upgrade()) {
+            return parser;
+        }
+        let parser = Arc::new(CachingBuiltinEntityParser::new(lang, 1000));
+        parsers.insert(lang, Arc::downgrade(&parser));
+        parser
     }
 }
@@ -0,0 +1,101 @@
+use rand::Rng;
+
+use dataset::{Chunk, Dataset, Utterance};
+
+/// Returns the utterances of the intent, completed with generated utterances until
+/// `min_utterances` is reached.
+///
+/// Generated utterances are copies of existing ones in which the values of custom entities are
+/// replaced by other values, or synonyms, of the same entities.
+pub fn augment_utterances<R: Rng>(
+    dataset: &Dataset,
+    intent_name: &str,
+    min_utterances: usize,
+    rng: &mut R,
+) -> Vec<Utterance> {
+    let utterances = dataset
+        .intents
+        .get(intent_name)
+        .map(|intent| intent.utterances.clone())
+        .unwrap_or_else(|| vec![]);
+    if utterances.is_empty() {
+        return utterances;
+    }
+    let mut augmented_utterances = utterances.clone();
+    while augmented_utterances.len() < min_utterances {
+        let utterance = rng.choose(&utterances).unwrap();
+        let data = utterance
+            .data
+            .iter()
+            .map(|chunk| replace_entity_value(dataset, chunk, rng))
+            .collect();
+        augmented_utterances.push(Utterance { data });
+    }
+    augmented_utterances
+}
+
+fn replace_entity_value<R: Rng>(dataset: &Dataset, chunk: &Chunk, rng: &mut R) -> Chunk {
+    let entity_utterances = chunk
+        .entity
+        .as_ref()
+        .and_then(|entity_name| dataset.custom_entity(entity_name))
+        .map(|entity| entity.utterances())
+        .unwrap_or_else(|| vec![]);
+    match rng.choose(&entity_utterances) {
+        Some(value) => Chunk {
+            text: value.to_string(),
+            ..chunk.clone()
+        },
+        None => chunk.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use utils::seeded_rng;
+
+    #[test]
+    fn augment_utterances_works() {
+        // Given
+        let dataset: Dataset = serde_json::from_str(r#"{
+            "language": "en",
+            "intents": {
+                "MakeTea": {
+                    "utterances": [
+                        {
+                            "data": [
+                                {"text": "make me a "},
+                                {"text": "hot", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " tea"}
+                            ]
+                        }
+                    ]
+                }
+            },
+            "entities": {
+                "temperature": {
+                    "data": [
+                        {"value": "hot", "synonyms": []},
+                        {"value": "cold", "synonyms": []}
+                    ],
+                    "use_synonyms": true,
+                    "automatically_extensible": false
+                }
+            }
+        }"#).unwrap();
+        let mut rng = seeded_rng(Some(42));
+
+        // When
+        let utterances = augment_utterances(&dataset, "MakeTea", 10, &mut rng);
+
+        // Then
+        assert_eq!(10, utterances.len());
+        for utterance in utterances {
+            let text = utterance.text();
+            assert!(text == "make me a hot tea" || text == "make me a cold tea");
+            assert_eq!(Some("temp".to_string()), utterance.data[1].slot_name);
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use errors::*;
+use failure::ResultExt;
+use itertools::Itertools;
+use serde::de::{Deserialize, Deserializer, Error as DeserializeError};
+use serde_json;
+use snips_nlu_ontology::{BuiltinEntityKind, Language};
+use vfs::{FileSystem, LocalFileSystem};
+use utils::{EntityName, IntentName, SlotName};
+
+/// Training dataset, in the json format used by the snips-nlu trainer
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Dataset {
+    pub language: String,
+    pub intents: HashMap<IntentName, Intent>,
+    #[serde(deserialize_with = "deserialize_entities")]
+    pub entities: HashMap<EntityName, Entity>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Intent {
+    pub utterances: Vec<Utterance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Utterance {
+    pub data: Vec<Chunk>,
+}
+
+/// Part of an utterance, which corresponds to a slot when it refers to an entity
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Chunk {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<EntityName>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot_name: Option<SlotName>,
+}
+
+/// Entity of the dataset, which is builtin when its name starts with `snips/`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Entity {
+    Custom(CustomEntity),
+    Builtin(BuiltinEntity),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CustomEntity {
+    pub data: Vec<EntityValue>,
+    pub use_synonyms: bool,
+    pub automatically_extensible: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EntityValue {
+    pub value: String,
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+}
+
+/// Builtin entities, such as `snips/number`, have no data in the dataset
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BuiltinEntity {}
+
+const BUILTIN_ENTITY_PREFIX: &str = "snips/";
+
+/// Deserializes each entity according to its name, so that a malformed custom entity is reported
+/// instead of being taken for a builtin entity
+fn deserialize_entities<'de, D>(
+    deserializer: D,
+) -> ::std::result::Result<HashMap<EntityName, Entity>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let entities: HashMap<EntityName, serde_json::Value> = HashMap::deserialize(deserializer)?;
+    entities
+        .into_iter()
+        .map(|(entity_name, entity)| {
+            let entity = if entity_name.starts_with(BUILTIN_ENTITY_PREFIX) {
+                serde_json::from_value(entity).map(Entity::Builtin)
+            } else {
+                serde_json::from_value(entity).map(Entity::Custom)
+            }.map_err(|error| {
+                D::Error::custom(format!("invalid entity '{}': {}", entity_name, error))
+            })?;
+            Ok((entity_name, entity))
+        })
+        .collect()
+}
+
+impl Dataset {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_vfs(&LocalFileSystem, path)
+    }
+
+    /// Loads the dataset and validates it, see `validate`
+    pub fn from_vfs<P: AsRef<Path>>(file_system: &FileSystem, path: P) -> Result<Self> {
+        let path_str = path.as_ref().to_string_lossy().into_owned();
+        let dataset_file = file_system.open(path.as_ref())?;
+        let dataset: Self = serde_json::from_reader(dataset_file)
+            .with_context(|_| SnipsNluError::InvalidJson(path_str.clone()))?;
+        let errors = dataset.validate();
+        if !errors.is_empty() {
+            return Err(SnipsNluError::InvalidDataset(path_str, DatasetErrors(errors)).into());
+        }
+        Ok(dataset)
+    }
+
+    /// Checks the consistency of the dataset, and returns all the errors found, in the order in
+    /// which they appear in the intents and then in the entities
+    pub fn validate(&self) -> Vec<DatasetError> {
+        let mut errors = vec![];
+        let language = Language::from_str(&self.language).ok();
+        if language.is_none() {
+            errors.push(DatasetError::new(
+                "language".to_string(),
+                DatasetErrorKind::UnsupportedLanguage(self.language.clone()),
+            ));
+        }
+        for intent_name in self.intent_names() {
+            self.validate_intent(&intent_name, &mut errors);
+        }
+        for entity_name in self.entities.keys().sorted() {
+            if let Entity::Builtin(_) = self.entities[entity_name] {
+                let location = format!("entities.{}", entity_name);
+                match BuiltinEntityKind::from_identifier(entity_name) {
+                    Ok(kind) => match language {
+                        Some(language) if !kind.supported_languages().contains(&language) => {
+                            errors.push(DatasetError::new(
+                                location,
+                                DatasetErrorKind::UnsupportedBuiltinEntity(
+                                    entity_name.clone(),
+                                    self.language.clone(),
+                                ),
+                            ))
+                        }
+                        _ => (),
+                    },
+                    Err(_) => errors.push(DatasetError::new(
+                        location,
+                        DatasetErrorKind::UnknownBuiltinEntity(entity_name.clone()),
+                    )),
+                }
+            }
+        }
+        errors
+    }
+
+    fn validate_intent(&self, intent_name: &str, errors: &mut Vec<DatasetError>) {
+        let utterances = &self.intents[intent_name].utterances;
+        if utterances.is_empty() {
+            errors.push(DatasetError::new(
+                format!("intents.{}.utterances", intent_name),
+                DatasetErrorKind::EmptyIntent,
+            ));
+        }
+        let mut slot_names_to_entities: HashMap<&SlotName, &EntityName> = HashMap::new();
+        for (utterance_index, utterance) in utterances.iter().enumerate() {
+            for (chunk_index, chunk) in utterance.data.iter().enumerate() {
+                let location = format!(
+                    "intents.{}.utterances[{}].data[{}]",
+                    intent_name, utterance_index, chunk_index
+                );
+                let error_kind = match (chunk.slot_name.as_ref(), chunk.entity.as_ref()) {
+                    (Some(_), Some(entity)) if !self.entities.contains_key(entity) => {
+                        Some(DatasetErrorKind::UnknownEntity(entity.clone()))
+                    }
+                    (Some(slot_name), Some(entity)) => {
+                        let previous_entity =
+                            *slot_names_to_entities.entry(slot_name).or_insert(entity);
+                        if previous_entity != entity {
+                            Some(DatasetErrorKind::ConflictingSlotEntities(
+                                slot_name.clone(),
+                                entity.clone(),
+                                previous_entity.clone(),
+                            ))
+                        } else {
+                            None
+                        }
+                    }
+                    (None, Some(_)) => Some(DatasetErrorKind::MissingSlotName),
+                    (Some(_), None) => Some(DatasetErrorKind::MissingEntity),
+                    (None, None) => None,
+                };
+                if let Some(kind) = error_kind {
+                    errors.push(DatasetError::new(location, kind));
+                }
+            }
+        }
+    }
+
+    /// Returns the intent names in alphabetical order
+    pub fn intent_names(&self) -> Vec<IntentName> {
+        let mut intent_names: Vec<IntentName> = self.intents.keys().cloned().collect();
+        intent_names.sort();
+        intent_names
+    }
+
+    pub fn custom_entity(&self, entity_name: &str) -> Option<&CustomEntity> {
+        match self.entities.get(entity_name) {
+            Some(Entity::Custom(entity)) => Some(entity),
+            _ => None,
+        }
+    }
+}
+
+/// Validation error, located by its json path in the dataset file, such as
+/// `intents.MakeTea.utterances[2].data[1]`
+#[derive(Debug, Clone, PartialEq, Fail)]
+#[fail(display = "{}: {}", location, kind)]
+pub struct DatasetError {
+    pub location: String,
+    pub kind: DatasetErrorKind,
+}
+
+impl DatasetError {
+    fn new(location: String, kind: DatasetErrorKind) -> Self {
+        Self { location, kind }
+    }
+}
+
+/// All the validation errors of a dataset
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetErrors(pub Vec<DatasetError>);
+
+impl fmt::Display for DatasetErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.iter().join("; "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Fail)]
+pub enum DatasetErrorKind {
+    #[fail(display = "unsupported language '{}'", _0)]
+    UnsupportedLanguage(String),
+    #[fail(display = "intent has no utterance")]
+    EmptyIntent,
+    #[fail(display = "unknown entity '{}'", _0)]
+    UnknownEntity(EntityName),
+    #[fail(display = "chunk has an entity but no slot name")]
+    MissingSlotName,
+    #[fail(display = "chunk has a slot name but no entity")]
+    MissingEntity,
+    #[fail(display = "slot '{}' refers to entity '{}' but previously referred to '{}'", _0, _1,
+           _2)]
+    ConflictingSlotEntities(SlotName, EntityName, EntityName),
+    #[fail(display = "unknown builtin entity '{}'", _0)]
+    UnknownBuiltinEntity(EntityName),
+    #[fail(display = "builtin entity '{}' is not supported in language '{}'", _0, _1)]
+    UnsupportedBuiltinEntity(EntityName, String),
+}
+
+impl Utterance {
+    pub fn text(&self) -> String {
+        self.data.iter().map(|chunk| chunk.text.as_str()).collect()
+    }
+}
+
+impl CustomEntity {
+    /// Returns the values of the entity along with their synonyms, when they are used
+    pub fn utterances(&self) -> Vec<&str> {
+        self.data
+            .iter()
+            .flat_map(|entity_value| {
+                let synonyms: &[String] = if self.use_synonyms {
+                    &entity_value.synonyms
+                } else {
+                    &[]
+                };
+                Some(&entity_value.value).into_iter().chain(synonyms.iter())
+            })
+            .map(|utterance| utterance.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vfs::MemoryFileSystem;
+
+    #[test]
+    fn deserialize_works() {
+        // Given
+        let data = r#"{
+            "language": "en",
+            "intents": {
+                "MakeTea": {
+                    "utterances": [
+                        {
+                            "data": [
+                                {"text": "make me a "},
+                                {"text": "hot", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " tea"}
+                            ]
+                        }
+                    ]
+                }
+            },
+            "entities": {
+                "temperature": {
+                    "data": [{"value": "hot", "synonyms": ["boiling"]}],
+                    "use_synonyms": true,
+                    "automatically_extensible": false
+                },
+                "snips/number": {}
+            }
+        }"#;
+
+        // When
+        let dataset: Dataset = serde_json::from_str(data).unwrap();
+
+        // Then
+        let utterance = &dataset.intents["MakeTea"].utterances[0];
+        assert_eq!("make me a hot tea", &utterance.text());
+        assert_eq!(vec!["hot", "boiling"], dataset.custom_entity("temperature").unwrap().utterances());
+        assert_eq!(Some(&Entity::Builtin(BuiltinEntity {})), dataset.entities.get("snips/number"));
+        assert!(dataset.validate().is_empty());
+    }
+
+    #[test]
+    fn validate_should_locate_errors() {
+        // Given
+        let data = r#"{
+            "language": "en",
+            "intents": {
+                "MakeCoffee": {
+                    "utterances": []
+                },
+                "MakeTea": {
+                    "utterances": [
+                        {
+                            "data": [
+                                {"text": "make me a "},
+                                {"text": "hot", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " tea"}
+                            ]
+                        },
+                        {
+                            "data": [
+                                {"text": "make me "},
+                                {"text": "two", "entity": "snips/number", "slot_name": "temp"},
+                                {"text": " "},
+                                {"text": "green", "entity": "flavour", "slot_name": "flavour"},
+                                {"text": " teas", "entity": "beverage"}
+                            ]
+                        }
+                    ]
+                }
+            },
+            "entities": {
+                "temperature": {
+                    "data": [{"value": "hot", "synonyms": []}],
+                    "use_synonyms": true,
+                    "automatically_extensible": false
+                },
+                "snips/number": {},
+                "snips/unknown": {}
+            }
+        }"#;
+        let dataset: Dataset = serde_json::from_str(data).unwrap();
+
+        // When
+        let errors = dataset.validate();
+
+        // Then
+        let expected_errors = vec![
+            DatasetError::new(
+                "intents.MakeCoffee.utterances".to_string(),
+                DatasetErrorKind::EmptyIntent,
+            ),
+            DatasetError::new(
+                "intents.MakeTea.utterances[1].data[1]".to_string(),
+                DatasetErrorKind::ConflictingSlotEntities(
+                    "temp".to_string(),
+                    "snips/number".to_string(),
+                    "temperature".to_string(),
+                ),
+            ),
+            DatasetError::new(
+                "intents.MakeTea.utterances[1].data[3]".to_string(),
+                DatasetErrorKind::UnknownEntity("flavour".to_string()),
+            ),
+            DatasetError::new(
+                "intents.MakeTea.utterances[1].data[4]".to_string(),
+                DatasetErrorKind::MissingSlotName,
+            ),
+            DatasetError::new(
+                "entities.snips/unknown".to_string(),
+                DatasetErrorKind::UnknownBuiltinEntity("snips/unknown".to_string()),
+            ),
+        ];
+        assert_eq!(expected_errors, errors);
+    }
+
+    #[test]
+    fn from_vfs_should_reject_invalid_dataset() {
+        // Given
+        let mut file_system = MemoryFileSystem::new();
+        file_system.insert(
+            "dataset.json",
+            br#"{"language": "xx", "intents": {}, "entities": {}}"#.to_vec(),
+        );
+
+        // When
+        let error = Dataset::from_vfs(&file_system, "dataset.json").unwrap_err();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::InvalidDataset(path, errors)) => {
+                assert_eq!("dataset.json", path);
+                let expected_errors = DatasetErrors(vec![DatasetError::new(
+                    "language".to_string(),
+                    DatasetErrorKind::UnsupportedLanguage("xx".to_string()),
+                )]);
+                assert_eq!(&expected_errors, errors);
+                assert_eq!("language: unsupported language 'xx'", &errors.to_string());
+            }
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn deserialize_should_reject_malformed_custom_entity() {
+        // Given
+        let data = r#"{
+            "language": "en",
+            "intents": {},
+            "entities": {
+                "temperature": {
+                    "data": [{"value": "hot", "synonyms": []}],
+                    "use_synonyms": true
+                }
+            }
+        }"#;
+
+        // When
+        let result = serde_json::from_str::<Dataset>(data);
+
+        // Then
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("invalid entity 'temperature'"), error);
+    }
+}
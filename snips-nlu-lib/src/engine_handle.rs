@@ -0,0 +1,160 @@
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use errors::*;
+use nlu_engine::{EngineConfig, SnipsNluEngine};
+
+/// Shared handle on a `SnipsNluEngine` which can be replaced by a new engine without restarting
+/// the process.
+///
+/// Each call to `engine` returns the engine which is current at that time, so that the parsing
+/// calls in progress keep using the previous engine when a new one is swapped in. The previous
+/// engine, along with its resources, is released once the last of these calls completes.
+///
+/// The handle owns the runtime configuration, which is applied to every engine swapped in: it
+/// must be changed through the handle rather than on the engine itself.
+pub struct EngineHandle {
+    current: RwLock<CurrentEngine>,
+}
+
+struct CurrentEngine {
+    engine: Arc<SnipsNluEngine>,
+    config: EngineConfig,
+}
+
+impl EngineHandle {
+    pub fn new(engine: SnipsNluEngine) -> Self {
+        Self {
+            current: RwLock::new(CurrentEngine {
+                config: engine.config(),
+                engine: Arc::new(engine),
+            }),
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(SnipsNluEngine::from_path(path)?))
+    }
+
+    pub fn from_zip<R: io::Read + io::Seek>(reader: R) -> Result<Self> {
+        Ok(Self::new(SnipsNluEngine::from_zip(reader)?))
+    }
+
+    /// Returns the current engine
+    pub fn engine(&self) -> Arc<SnipsNluEngine> {
+        self.current.read().unwrap().engine.clone()
+    }
+
+    pub fn config(&self) -> EngineConfig {
+        self.current.read().unwrap().config.clone()
+    }
+
+    /// Updates the configuration of the handle and of its current engine
+    pub fn set_config(&self, config: EngineConfig) {
+        self.update_config(|current_config| *current_config = config)
+    }
+
+    /// Modifies the configuration of the handle and of its current engine, atomically with
+    /// respect to the other updates and to the engine swaps
+    pub fn update_config<F: FnOnce(&mut EngineConfig)>(&self, update: F) {
+        let mut current = self.current.write().unwrap();
+        update(&mut current.config);
+        current.engine.set_config(current.config.clone());
+    }
+
+    /// Atomically replaces the current engine, to which the configuration of the handle is
+    /// applied, and returns the previous one.
+    ///
+    /// The entity values added to the previous engine at runtime are not carried over, as the
+    /// entities of the new engine may differ: they must be added again to the new engine.
+    pub fn swap(&self, engine: SnipsNluEngine) -> Arc<SnipsNluEngine> {
+        let mut current = self.current.write().unwrap();
+        engine.set_config(current.config.clone());
+        ::std::mem::replace(&mut current.engine, Arc::new(engine))
+    }
+
+    /// Loads the engine located at `path` and swaps it in.
+    ///
+    /// The current engine is left untouched when the new one cannot be loaded.
+    pub fn reload_from_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.swap(SnipsNluEngine::from_path(path)?);
+        Ok(())
+    }
+
+    /// Same as `reload_from_path`, with an engine loaded from a zip archive
+    pub fn reload_from_zip<R: io::Read + io::Seek>(&self, reader: R) -> Result<()> {
+        self.swap(SnipsNluEngine::from_zip(reader)?);
+        Ok(())
+    }
+}
+
+impl From<SnipsNluEngine> for EngineHandle {
+    fn from(engine: SnipsNluEngine) -> Self {
+        Self::new(engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use nlu_engine::EngineConfig;
+    use utils::file_path;
+
+    #[test]
+    fn reload_should_keep_previous_engine_alive_and_config() {
+        // Given
+        let models_path = file_path("tests").join("models");
+        let handle = EngineHandle::from_path(models_path.join("trained_engine")).unwrap();
+        handle.set_config(EngineConfig {
+            intent_probability_threshold: 0.3,
+            fuzzy_matching_threshold: Some(0.8),
+        });
+        let previous_engine = handle.engine();
+
+        // When
+        let zip_file = fs::File::open(models_path.join("trained_engine.zip")).unwrap();
+        handle.reload_from_zip(zip_file).unwrap();
+
+        // Then
+        let engine = handle.engine();
+        assert!(!Arc::ptr_eq(&previous_engine, &engine));
+        assert_eq!(0.3, engine.config().intent_probability_threshold);
+        assert_eq!(Some(0.8), engine.config().fuzzy_matching_threshold);
+        let result = previous_engine.parse("Make me two cups of coffee", None).unwrap();
+        assert_eq!("MakeCoffee", &result.intent.unwrap().intent_name);
+    }
+
+    #[test]
+    fn reload_should_keep_current_engine_on_failure() {
+        // Given
+        let models_path = file_path("tests").join("models");
+        let handle = EngineHandle::from_path(models_path.join("trained_engine")).unwrap();
+        let current_engine = handle.engine();
+
+        // When
+        let result = handle.reload_from_path(models_path.join("unknown_engine"));
+
+        // Then
+        assert!(result.is_err());
+        assert!(Arc::ptr_eq(&current_engine, &handle.engine()));
+    }
+
+    #[test]
+    fn swap_should_apply_config_of_handle() {
+        // Given
+        let models_path = file_path("tests").join("models");
+        let handle = EngineHandle::from_path(models_path.join("trained_engine")).unwrap();
+        handle.update_config(|config| config.fuzzy_matching_threshold = Some(0.7));
+
+        // When
+        let previous_engine =
+            handle.swap(SnipsNluEngine::from_path(models_path.join("trained_engine")).unwrap());
+
+        // Then
+        assert_eq!(Some(0.7), previous_engine.config().fuzzy_matching_threshold);
+        assert_eq!(Some(0.7), handle.engine().config().fuzzy_matching_threshold);
+        assert_eq!(Some(0.7), handle.config().fuzzy_matching_threshold);
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use engine_handle::EngineHandle;
+use errors::*;
+use nlu_engine::{EngineConfig, SnipsNluEngine};
+use memory::HeapSize;
+use resources::SharedResources;
+use snips_nlu_ontology::IntentParserResult;
+
+/// Engines of several assistants, possibly of different languages, hosted by the same process.
+///
+/// Engines which have loaded identical resources share them, and the memory of an engine is
+/// released once it has been unloaded and the parsing calls in progress have completed.
+#[derive(Default)]
+pub struct EngineRegistry {
+    engines: RwLock<BTreeMap<String, EngineHandle>>,
+}
+
+/// Approximate memory used by an engine of the registry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EngineMemoryUsage {
+    pub language: String,
+    /// Bytes used by the models of the engine, which are never shared
+    pub model_bytes: usize,
+    /// Memory used by each resource, indexed by names such as `gazetteers/<name>` or
+    /// `builtin_entity_parser`
+    pub resources: BTreeMap<String, ResourceMemoryUsage>,
+    /// Bytes used by the resources which are not shared with any other engine of the registry
+    pub own_bytes: usize,
+    /// Bytes used by the resources which are shared with other engines of the registry
+    pub shared_bytes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ResourceMemoryUsage {
+    pub bytes: usize,
+    /// Number of engines of the registry using this resource
+    pub nb_engines: usize,
+}
+
+impl EngineRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the engine of an assistant from a directory, replacing the engine previously loaded
+    /// for this assistant if any
+    pub fn load_from_path<P: AsRef<Path>>(&self, assistant_id: &str, path: P) -> Result<()> {
+        self.insert(assistant_id, SnipsNluEngine::from_path(path)?);
+        Ok(())
+    }
+
+    /// Same as `load_from_path`, with an engine loaded from a zip archive
+    pub fn load_from_zip<R: io::Read + io::Seek>(
+        &self,
+        assistant_id: &str,
+        reader: R,
+    ) -> Result<()> {
+        self.insert(assistant_id, SnipsNluEngine::from_zip(reader)?);
+        Ok(())
+    }
+
+    /// Registers the engine of an assistant, where the parsing calls in progress complete with
+    /// the previous engine of this assistant, and where the runtime configuration of the assistant
+    /// is kept. The entity values added at runtime to the previous engine are not.
+    pub fn insert(&self, assistant_id: &str, engine: SnipsNluEngine) {
+        let mut engines = self.engines.write().unwrap();
+        if let Some(handle) = engines.get(assistant_id) {
+            handle.swap(engine);
+            return;
+        }
+        engines.insert(assistant_id.to_string(), EngineHandle::new(engine));
+    }
+
+    /// Removes the engine of an assistant, and returns whether it was registered
+    pub fn unload(&self, assistant_id: &str) -> bool {
+        self.engines.write().unwrap().remove(assistant_id).is_some()
+    }
+
+    pub fn assistant_ids(&self) -> Vec<String> {
+        self.engines.read().unwrap().keys().cloned().collect()
+    }
+
+    pub fn get(&self, assistant_id: &str) -> Result<Arc<SnipsNluEngine>> {
+        self.with_handle(assistant_id, |handle| handle.engine())
+    }
+
+    pub fn config(&self, assistant_id: &str) -> Result<EngineConfig> {
+        self.with_handle(assistant_id, |handle| handle.config())
+    }
+
+    /// Updates the runtime configuration of an assistant, which also applies to the engines
+    /// loaded afterwards for this assistant
+    pub fn set_config(&self, assistant_id: &str, config: EngineConfig) -> Result<()> {
+        self.with_handle(assistant_id, |handle| handle.set_config(config))
+    }
+
+    fn with_handle<T, F: FnOnce(&EngineHandle) -> T>(&self, assistant_id: &str, f: F) -> Result<T> {
+        self.engines
+            .read()
+            .unwrap()
+            .get(assistant_id)
+            .map(f)
+            .ok_or_else(|| SnipsNluError::UnknownAssistant(assistant_id.to_string()).into())
+    }
+
+    /// Parses the input with the engine of the given assistant
+    pub fn parse(
+        &self,
+        assistant_id: &str,
+        input: &str,
+        intents_filter: Option<&[String]>,
+    ) -> Result<IntentParserResult> {
+        self.get(assistant_id)?.parse(input, intents_filter)
+    }
+
+    /// Reports the memory used by the models and the resources of each engine, along with the
+    /// resources shared between engines
+    pub fn memory_usage(&self) -> BTreeMap<String, EngineMemoryUsage> {
+        let engines: Vec<(String, Arc<SnipsNluEngine>)> = self.engines
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(assistant_id, handle)| (assistant_id.clone(), handle.engine()))
+            .collect();
+        let all_resources: Vec<Vec<(String, usize, *const ())>> = engines
+            .iter()
+            .map(|&(_, ref engine)| resources_footprint(&engine.shared_resources()))
+            .collect();
+
+        engines
+            .iter()
+            .zip(all_resources.iter())
+            .map(|(&(ref assistant_id, ref engine), resources)| {
+                let resources: BTreeMap<String, ResourceMemoryUsage> = resources
+                    .iter()
+                    .map(|&(ref name, bytes, address)| {
+                        let nb_engines = all_resources
+                            .iter()
+                            .filter(|other_resources| {
+                                other_resources.iter().any(|&(_, _, other)| other == address)
+                            })
+                            .count();
+                        (name.clone(), ResourceMemoryUsage { bytes, nb_engines })
+                    })
+                    .collect();
+                let (shared, own): (Vec<&ResourceMemoryUsage>, Vec<_>) = resources
+                    .values()
+                    .partition(|resource| resource.nb_engines > 1);
+                let model_bytes = engine
+                    .memory_usage()
+                    .components
+                    .iter()
+                    .filter(|&(component, _)| !component.starts_with("resources/"))
+                    .map(|(_, bytes)| bytes)
+                    .sum();
+                let memory_usage = EngineMemoryUsage {
+                    language: engine.info().language,
+                    model_bytes,
+                    own_bytes: own.iter().map(|resource| resource.bytes).sum(),
+                    shared_bytes: shared.iter().map(|resource| resource.bytes).sum(),
+                    resources,
+                };
+                (assistant_id.clone(), memory_usage)
+            })
+            .collect()
+    }
+}
+
+/// Name, size and address of each resource, where the address identifies shared resources
+fn resources_footprint(resources: &SharedResources) -> Vec<(String, usize, *const ())> {
+    fn footprint<T: HeapSize>(name: String, resource: &Arc<T>) -> (String, usize, *const ()) {
+        (name, resource.heap_size(), &**resource as *const T as *const ())
+    }
+
+    let gazetteers = resources
+        .gazetteers
+        .iter()
+        .map(|(name, gazetteer)| footprint(format!("gazetteers/{}", name), gazetteer));
+    let word_clusterers = resources
+        .word_clusterers
+        .iter()
+        .map(|(name, clusterer)| footprint(format!("word_clusters/{}", name), clusterer));
+    let stemmer = resources
+        .stemmer
+        .iter()
+        .map(|stemmer| footprint("stemmer".to_string(), stemmer));
+    let builtin_entity_parser = Some(footprint(
+        "builtin_entity_parser".to_string(),
+        &resources.builtin_entity_parser,
+    ));
+    gazetteers
+        .chain(word_clusterers)
+        .chain(stemmer)
+        .chain(builtin_entity_parser)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::file_path;
+
+    #[test]
+    fn memory_usage_should_report_shared_resources() {
+        // Given
+        let engine_path = file_path("tests").join("models").join("trained_engine");
+        let registry = EngineRegistry::new();
+        registry.load_from_path("assistant_1", &engine_path).unwrap();
+        registry.load_from_path("assistant_2", &engine_path).unwrap();
+
+        // When
+        let shared_usage = registry.memory_usage();
+        registry.unload("assistant_1");
+        let own_usage = registry.memory_usage();
+
+        // Then
+        let stemmer_usage = shared_usage["assistant_1"].resources["stemmer"];
+        assert!(stemmer_usage.bytes > 0);
+        assert_eq!(2, stemmer_usage.nb_engines);
+        assert_eq!(2, shared_usage["assistant_1"].resources["builtin_entity_parser"].nb_engines);
+        assert!(shared_usage["assistant_1"].model_bytes > 0);
+        assert_eq!(0, shared_usage["assistant_2"].own_bytes);
+        assert_eq!(vec!["assistant_2"], own_usage.keys().collect::<Vec<_>>());
+        assert_eq!(0, own_usage["assistant_2"].shared_bytes);
+        assert_eq!(shared_usage["assistant_2"].shared_bytes, own_usage["assistant_2"].own_bytes);
+    }
+
+    #[test]
+    fn load_should_keep_config_of_assistant() {
+        // Given
+        let engine_path = file_path("tests").join("models").join("trained_engine");
+        let registry = EngineRegistry::new();
+        registry.load_from_path("assistant", &engine_path).unwrap();
+        let config = EngineConfig {
+            intent_probability_threshold: 0.4,
+            fuzzy_matching_threshold: Some(0.9),
+        };
+        registry.set_config("assistant", config.clone()).unwrap();
+
+        // When
+        registry.load_from_path("assistant", &engine_path).unwrap();
+
+        // Then
+        assert_eq!(config, registry.config("assistant").unwrap());
+        assert_eq!(config, registry.get("assistant").unwrap().config());
+    }
+
+    #[test]
+    fn parse_should_fail_for_unknown_assistant() {
+        // Given
+        let registry = EngineRegistry::new();
+
+        // When
+        let error = registry.parse("unknown", "Make me a coffee", None).err().unwrap();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::UnknownAssistant(assistant_id)) =>
+                assert_eq!("unknown", assistant_id),
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+}
@@ -1,9 +1,97 @@
+use dataset::DatasetErrors;
+use failure::{Context, Error};
+
 #[derive(Debug, Fail)]
 pub enum SnipsNluError {
     #[fail(display = "Unable to read file '{}'", _0)]
     ModelLoad(String),
-    #[fail(display = "Expected model version {} but found {}", _1, _0)]
+    #[fail(display = "Model version {} is not compatible with {}", _0, _1)]
     WrongModelVersion(String, &'static str),
+    #[fail(display = "Incompatible {} model '{}': {}", _0, _1, _2)]
+    IncompatibleModel(&'static str, String, String),
+    #[fail(display = "File not found: '{}'", _0)]
+    MissingFile(String),
+    #[fail(display = "Cannot deserialize json file '{}'", _0)]
+    InvalidJson(String),
+    #[fail(display = "Invalid model: {}", _0)]
+    InvalidModel(String),
+    #[fail(display = "Unknown processing unit '{}'", _0)]
+    UnknownProcessingUnit(String),
+    #[fail(display = "{} is not {}", _0, _1)]
+    WrongProcessingUnit(String, &'static str),
+    #[fail(display = "Feature '{}' is not supported", _0)]
+    UnsupportedFeature(String),
+    #[fail(display = "Missing resource: {}", _0)]
+    MissingResource(String),
+    #[fail(display = "Cannot load CRF model '{}'", _0)]
+    CrfModelLoad(String),
+    #[fail(display = "Unknown intent '{}'", _0)]
+    UnknownIntent(String),
+    #[fail(display = "Unknown slot '{}'", _0)]
+    UnknownSlot(String),
+    #[fail(display = "No slot filler can tag the tokens of intent '{}'", _0)]
+    MissingSlotFiller(String),
+    #[fail(display = "Unknown entity '{}'", _0)]
+    UnknownEntity(String),
+    #[fail(display = "Unsupported language '{}'", _0)]
+    UnsupportedLanguage(String),
+    #[fail(display = "Invalid dataset '{}': {}", _0, _1)]
+    InvalidDataset(String, DatasetErrors),
+    #[fail(display = "Unknown assistant '{}'", _0)]
+    UnknownAssistant(String),
+}
+
+impl SnipsNluError {
+    /// Returns the most specific `SnipsNluError` found in the chain of causes of `error`, looking
+    /// through the contexts which may have been added on top of it
+    pub fn find_in(error: &Error) -> Option<&SnipsNluError> {
+        error
+            .iter_chain()
+            .filter_map(|fail| {
+                fail.downcast_ref::<SnipsNluError>().or_else(|| {
+                    fail.downcast_ref::<Context<SnipsNluError>>()
+                        .map(|context| context.get_context())
+                })
+            })
+            .last()
+    }
 }
 
 pub type Result<T> = ::std::result::Result<T, ::failure::Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use failure::ResultExt;
+
+    #[test]
+    fn find_in_should_return_most_specific_error() {
+        // Given
+        let error: Error = Err::<(), _>(SnipsNluError::MissingFile("model.json".to_string()))
+            .with_context(|_| SnipsNluError::ModelLoad("model.json".to_string()))
+            .with_context(|_| "Cannot load engine")
+            .unwrap_err()
+            .into();
+
+        // When
+        let nlu_error = SnipsNluError::find_in(&error);
+
+        // Then
+        match nlu_error {
+            Some(SnipsNluError::MissingFile(path)) => assert_eq!("model.json", path),
+            _ => panic!("Unexpected error: {:?}", nlu_error),
+        }
+    }
+
+    #[test]
+    fn find_in_should_return_none_for_untyped_errors() {
+        // Given
+        let error = format_err!("Something went wrong");
+
+        // When
+        let nlu_error = SnipsNluError::find_in(&error);
+
+        // Then
+        assert!(nlu_error.is_none());
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use itertools::Itertools;
+
+use dataset::{Dataset, Utterance};
+use errors::*;
+use language::{parse_language, FromLanguage};
+use nlu_engine::SnipsNluEngine;
+use nlu_utils::language::Language as NluUtilsLanguage;
+use nlu_utils::token::tokenize;
+use snips_nlu_ontology::IntentParserResult;
+use utils::{IntentName, SlotName};
+
+/// Label of the None intent in the evaluation report
+pub const NONE_INTENT_LABEL: &str = "None";
+
+/// Evaluation of an engine on a labeled test set, in which maps are sorted so that reports of
+/// different model versions can be diffed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvaluationReport {
+    pub nb_utterances: usize,
+    /// Ratio of utterances whose intent and slots are all correct
+    pub exact_match_accuracy: f32,
+    pub intents: BTreeMap<String, IntentMetrics>,
+    pub confusion_matrix: ConfusionMatrix,
+    pub parsing_errors: Vec<ParsingError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IntentMetrics {
+    pub intent: Metrics,
+    pub slots: BTreeMap<SlotName, SlotMetrics>,
+}
+
+/// Slot metrics, where exact match metrics consider a slot correct only when its name and range
+/// are, while token level metrics evaluate the slot name of each token
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlotMetrics {
+    pub exact_match: Metrics,
+    pub token_level: Metrics,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Metrics {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub precision: f32,
+    pub recall: f32,
+    pub f1: f32,
+}
+
+/// Number of utterances of each expected intent, in rows, classified as each intent, in columns
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfusionMatrix {
+    pub intents: Vec<String>,
+    pub matrix: Vec<Vec<usize>>,
+}
+
+/// Utterance whose intent or slots have not been correctly parsed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParsingError {
+    pub input: String,
+    pub expected_intent: String,
+    pub found_intent: String,
+    pub expected_slots: Vec<EvaluatedSlot>,
+    pub found_slots: Vec<EvaluatedSlot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvaluatedSlot {
+    pub slot_name: SlotName,
+    pub value: String,
+    pub range: Option<Range<usize>>,
+}
+
+/// Parses all the utterances of the test set with the engine and evaluates the results
+pub fn evaluate_engine(engine: &SnipsNluEngine, test_set: &Dataset) -> Result<EvaluationReport> {
+    let language = NluUtilsLanguage::from_language(parse_language(&test_set.language)?);
+    let samples = test_set
+        .intent_names()
+        .into_iter()
+        .flat_map(|intent_name| {
+            test_set.intents[&intent_name]
+                .utterances
+                .iter()
+                .map(move |utterance| Sample::new(intent_name.clone(), utterance))
+        })
+        .collect_vec();
+    let inputs = samples.iter().map(|sample| sample.input.as_str()).collect_vec();
+    let results = engine.parse_batch(&inputs, None)?;
+    Ok(compute_report(&samples, &results, language))
+}
+
+/// Expected parsing of a test utterance
+struct Sample {
+    input: String,
+    intent: IntentName,
+    slots: Vec<EvaluatedSlot>,
+}
+
+impl Sample {
+    fn new(intent: IntentName, utterance: &Utterance) -> Self {
+        let mut slots = vec![];
+        let mut chunk_start = 0;
+        for chunk in &utterance.data {
+            let chunk_end = chunk_start + chunk.text.chars().count();
+            if let Some(slot_name) = chunk.slot_name.as_ref() {
+                slots.push(EvaluatedSlot {
+                    slot_name: slot_name.clone(),
+                    value: chunk.text.clone(),
+                    range: Some(chunk_start..chunk_end),
+                });
+            }
+            chunk_start = chunk_end;
+        }
+        Self {
+            input: utterance.text(),
+            intent,
+            slots,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    true_positives: usize,
+    false_positives: usize,
+    false_negatives: usize,
+}
+
+impl Counts {
+    fn metrics(&self) -> Metrics {
+        let ratio = |numerator: usize, denominator: usize| if denominator > 0 {
+            numerator as f32 / denominator as f32
+        } else {
+            0.
+        };
+        let precision = ratio(self.true_positives, self.true_positives + self.false_positives);
+        let recall = ratio(self.true_positives, self.true_positives + self.false_negatives);
+        let f1 = if precision + recall > 0. {
+            2. * precision * recall / (precision + recall)
+        } else {
+            0.
+        };
+        Metrics {
+            true_positives: self.true_positives,
+            false_positives: self.false_positives,
+            false_negatives: self.false_negatives,
+            precision,
+            recall,
+            f1,
+        }
+    }
+}
+
+#[derive(Default)]
+struct IntentCounts {
+    intent: Counts,
+    exact_slots: BTreeMap<SlotName, Counts>,
+    token_slots: BTreeMap<SlotName, Counts>,
+}
+
+fn compute_report(
+    samples: &[Sample],
+    results: &[IntentParserResult],
+    language: NluUtilsLanguage,
+) -> EvaluationReport {
+    let mut counts: BTreeMap<String, IntentCounts> = BTreeMap::new();
+    let mut confusion_counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+    let mut parsing_errors = vec![];
+    let mut nb_exact_matches = 0;
+
+    for (sample, result) in samples.iter().zip(results.iter()) {
+        let expected_intent = sample.intent.clone();
+        let found_intent = result
+            .intent
+            .as_ref()
+            .map(|intent| intent.intent_name.clone())
+            .unwrap_or_else(|| NONE_INTENT_LABEL.to_string());
+        let found_slots = result
+            .slots
+            .as_ref()
+            .map(|slots| slots
+                .iter()
+                .map(|slot| EvaluatedSlot {
+                    slot_name: slot.slot_name.clone(),
+                    value: slot.raw_value.clone(),
+                    range: slot.range.clone(),
+                })
+                .collect_vec())
+            .unwrap_or_else(|| vec![]);
+        *confusion_counts
+            .entry((expected_intent.clone(), found_intent.clone()))
+            .or_insert(0) += 1;
+
+        if expected_intent == found_intent {
+            counts.entry(expected_intent.clone()).or_insert_with(Default::default)
+                .intent.true_positives += 1;
+        } else {
+            counts.entry(expected_intent.clone()).or_insert_with(Default::default)
+                .intent.false_negatives += 1;
+            counts.entry(found_intent.clone()).or_insert_with(Default::default)
+                .intent.false_positives += 1;
+        }
+
+        let mut slots_match = true;
+        for slot in &sample.slots {
+            let intent_counts = counts.get_mut(&expected_intent).unwrap();
+            let slot_counts = intent_counts.exact_slots.entry(slot.slot_name.clone())
+                .or_insert_with(Default::default);
+            if expected_intent == found_intent && contains_slot(&found_slots, slot) {
+                slot_counts.true_positives += 1;
+            } else {
+                slot_counts.false_negatives += 1;
+                slots_match = false;
+            }
+        }
+        for slot in &found_slots {
+            if expected_intent != found_intent || !contains_slot(&sample.slots, slot) {
+                counts.get_mut(&found_intent).unwrap()
+                    .exact_slots.entry(slot.slot_name.clone())
+                    .or_insert_with(Default::default)
+                    .false_positives += 1;
+                slots_match = false;
+            }
+        }
+
+        for token in tokenize(&sample.input, language) {
+            let expected_label = token_label(&sample.slots, &token.char_range)
+                .map(|slot_name| (&expected_intent, slot_name));
+            let found_label = token_label(&found_slots, &token.char_range)
+                .map(|slot_name| (&found_intent, slot_name));
+            if expected_label.is_some() && expected_label == found_label {
+                let (intent, slot_name) = expected_label.unwrap();
+                counts.get_mut(intent).unwrap()
+                    .token_slots.entry(slot_name.clone()).or_insert_with(Default::default)
+                    .true_positives += 1;
+                continue;
+            }
+            if let Some((intent, slot_name)) = expected_label {
+                counts.get_mut(intent).unwrap()
+                    .token_slots.entry(slot_name.clone()).or_insert_with(Default::default)
+                    .false_negatives += 1;
+            }
+            if let Some((intent, slot_name)) = found_label {
+                counts.get_mut(intent).unwrap()
+                    .token_slots.entry(slot_name.clone()).or_insert_with(Default::default)
+                    .false_positives += 1;
+            }
+        }
+
+        if expected_intent == found_intent && slots_match {
+            nb_exact_matches += 1;
+        } else {
+            parsing_errors.push(ParsingError {
+                input: sample.input.clone(),
+                expected_intent,
+                found_intent,
+                expected_slots: sample.slots.clone(),
+                found_slots,
+            });
+        }
+    }
+
+    let intents = counts
+        .into_iter()
+        .map(|(intent, intent_counts)| {
+            let slot_names: BTreeSet<&SlotName> = intent_counts.exact_slots.keys()
+                .chain(intent_counts.token_slots.keys())
+                .collect();
+            let slots = slot_names
+                .into_iter()
+                .map(|slot_name| {
+                    let slot_metrics = SlotMetrics {
+                        exact_match: intent_counts.exact_slots.get(slot_name)
+                            .map(|counts| counts.metrics())
+                            .unwrap_or_else(|| Counts::default().metrics()),
+                        token_level: intent_counts.token_slots.get(slot_name)
+                            .map(|counts| counts.metrics())
+                            .unwrap_or_else(|| Counts::default().metrics()),
+                    };
+                    (slot_name.clone(), slot_metrics)
+                })
+                .collect();
+            let metrics = IntentMetrics {
+                intent: intent_counts.intent.metrics(),
+                slots,
+            };
+            (intent, metrics)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let confusion_intents = intents.keys().cloned().collect_vec();
+    let matrix = confusion_intents
+        .iter()
+        .map(|expected| confusion_intents
+            .iter()
+            .map(|found| *confusion_counts
+                .get(&(expected.clone(), found.clone()))
+                .unwrap_or(&0))
+            .collect())
+        .collect();
+
+    EvaluationReport {
+        nb_utterances: samples.len(),
+        exact_match_accuracy: if samples.is_empty() {
+            0.
+        } else {
+            nb_exact_matches as f32 / samples.len() as f32
+        },
+        intents,
+        confusion_matrix: ConfusionMatrix {
+            intents: confusion_intents,
+            matrix,
+        },
+        parsing_errors,
+    }
+}
+
+fn contains_slot(slots: &[EvaluatedSlot], slot: &EvaluatedSlot) -> bool {
+    slots
+        .iter()
+        .any(|other| other.slot_name == slot.slot_name && other.range == slot.range)
+}
+
+fn token_label<'a>(slots: &'a [EvaluatedSlot], token_range: &Range<usize>) -> Option<&'a SlotName> {
+    slots
+        .iter()
+        .find(|slot| slot.range.as_ref().map_or(false, |range| {
+            range.start <= token_range.start && token_range.end <= range.end
+        }))
+        .map(|slot| &slot.slot_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use snips_nlu_ontology::{IntentClassifierResult, Slot, SlotValue, StringValue};
+
+    fn parsing_result(input: &str, intent: Option<&str>, slots: Vec<(&str, Range<usize>)>)
+                      -> IntentParserResult {
+        IntentParserResult {
+            input: input.to_string(),
+            intent: intent.map(|intent_name| IntentClassifierResult {
+                intent_name: intent_name.to_string(),
+                probability: 0.8,
+            }),
+            slots: Some(slots
+                .into_iter()
+                .map(|(slot_name, range)| Slot {
+                    raw_value: input.chars().skip(range.start).take(range.len()).collect(),
+                    value: SlotValue::Custom(StringValue {
+                        value: input.chars().skip(range.start).take(range.len()).collect(),
+                    }),
+                    range: Some(range),
+                    entity: "entity".to_string(),
+                    slot_name: slot_name.to_string(),
+                })
+                .collect()),
+        }
+    }
+
+    #[test]
+    fn compute_report_works() {
+        // Given
+        let test_set: Dataset = serde_json::from_str(r#"{
+            "language": "en",
+            "intents": {
+                "MakeTea": {
+                    "utterances": [
+                        {
+                            "data": [
+                                {"text": "make me a "},
+                                {"text": "very hot", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " tea"}
+                            ]
+                        },
+                        {
+                            "data": [
+                                {"text": "i want "},
+                                {"text": "iced", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " tea"}
+                            ]
+                        }
+                    ]
+                },
+                "MakeCoffee": {
+                    "utterances": [{"data": [{"text": "make me a coffee"}]}]
+                }
+            },
+            "entities": {}
+        }"#).unwrap();
+        let samples = test_set.intent_names()
+            .into_iter()
+            .flat_map(|intent| test_set.intents[&intent].utterances
+                .iter()
+                .map(|utterance| Sample::new(intent.clone(), utterance))
+                .collect_vec())
+            .collect_vec();
+        let results = vec![
+            parsing_result("make me a coffee", None, vec![]),
+            parsing_result("make me a very hot tea", Some("MakeTea"), vec![("temp", 15..18)]),
+            parsing_result("i want iced tea", Some("MakeTea"), vec![("temp", 7..11)]),
+        ];
+
+        // When
+        let report = compute_report(&samples, &results, NluUtilsLanguage::EN);
+
+        // Then
+        assert_eq!(3, report.nb_utterances);
+        assert_eq!(1. / 3., report.exact_match_accuracy);
+        assert_eq!(vec!["MakeCoffee", "MakeTea", "None"], report.confusion_matrix.intents);
+        assert_eq!(vec![vec![0, 0, 1], vec![0, 2, 0], vec![0, 0, 0]],
+                   report.confusion_matrix.matrix);
+
+        let make_tea = &report.intents["MakeTea"];
+        assert_eq!(1., make_tea.intent.precision);
+        assert_eq!(1., make_tea.intent.recall);
+        let temp = &make_tea.slots["temp"];
+        assert_eq!((1, 1, 1), (temp.exact_match.true_positives,
+                               temp.exact_match.false_positives,
+                               temp.exact_match.false_negatives));
+        assert_eq!(0.5, temp.exact_match.f1);
+        assert_eq!((2, 0, 1), (temp.token_level.true_positives,
+                               temp.token_level.false_positives,
+                               temp.token_level.false_negatives));
+        assert_eq!(0., report.intents["MakeCoffee"].intent.recall);
+        assert_eq!(1, report.intents["None"].intent.false_positives);
+
+        let misparsed_inputs = report.parsing_errors.iter()
+            .map(|error| error.input.as_str())
+            .collect_vec();
+        assert_eq!(vec!["make me a coffee", "make me a very hot tea"], misparsed_inputs);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use nlu_engine::NluEngineResult;
+use slot_utils::InternalSlot;
+use snips_nlu_ontology::IntentClassifierResult;
+use utils::IntentName;
+
+/// Parsing result of the `SnipsNluEngine` along with the steps which led to it, in order to
+/// investigate misparsed queries
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseExplanation {
+    pub result: NluEngineResult,
+    /// Intent parsers which have been tried, in order, where the last one is the parser which
+    /// answered, if any
+    pub intent_parsers: Vec<IntentParserExplanation>,
+    /// Index, within the intent parsers of the engine, of the parser which answered
+    pub answering_parser_index: Option<usize>,
+    /// Slots found by the answering parser which have been dropped because their value could not
+    /// be resolved
+    pub unresolved_slots: Vec<InternalSlot>,
+}
+
+/// Explanation of the parsing of an input by a single intent parser
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntentParserExplanation {
+    pub parser_name: String,
+    pub intent: Option<IntentClassifierResult>,
+    #[serde(flatten)]
+    pub details: ParsingDetails,
+}
+
+/// Details which are specific to the kind of intent parser, and hence are left empty by parsers
+/// which do not rely on the corresponding mechanism
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsingDetails {
+    pub matched_pattern: Option<MatchedPattern>,
+    /// Features which contributed the most to the probability of the parsed intent, sorted by
+    /// decreasing contribution
+    pub top_features: Vec<FeatureContribution>,
+    pub slot_filling: Option<SlotFillingExplanation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchedPattern {
+    pub intent: IntentName,
+    /// Index of the pattern among the patterns of the intent
+    pub pattern_index: usize,
+    /// Whether the pattern matched the input in which builtin entities have been replaced by
+    /// placeholders
+    pub with_builtin_entities: bool,
+}
+
+/// Contribution of a feature to the score of an intent, which is the product of its tf-idf value
+/// and of its weight in the logistic regression
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureContribution {
+    pub feature: String,
+    pub tfidf: f32,
+    pub weight: f32,
+    pub contribution: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotFillingExplanation {
+    pub tokens: Vec<String>,
+    /// Tags decoded by the slot filler, one per token, before their conversion into slots
+    pub tags: Vec<String>,
+    /// Builtin slots which have been found by the builtin entity parser but not by the tagger
+    pub added_builtin_slots: Vec<InternalSlot>,
+    /// Builtin slots which have been tagged but discarded when reconciling them with the builtin
+    /// entities
+    pub dropped_builtin_slots: Vec<InternalSlot>,
+}
+
+/// Token of an input along with the tag decoded by the slot filler of an intent, and the data from
+/// which this tag derives
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaggedToken {
+    pub value: String,
+    pub char_range: Range<usize>,
+    /// Features of the token, as computed by the feature processor of the slot filler
+    pub features: BTreeMap<String, String>,
+    pub tag: String,
+    /// Marginal probability of each tag of the slot filler at the position of the token
+    pub marginals: BTreeMap<String, f64>,
+}
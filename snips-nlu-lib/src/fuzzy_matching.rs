@@ -0,0 +1,354 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use language::FromLanguage;
+use memory::HeapSize;
+use models::Entity;
+use nlu_utils::language::Language as NluUtilsLanguage;
+use nlu_utils::string::normalize;
+use nlu_utils::token::tokenize_light;
+use resources::stemmer::{CompactStemmer, Stemmer};
+use snips_nlu_ontology::Language;
+use utils::EntityName;
+
+/// Maximum number of utterances, among the ones sharing the most character trigrams with a value,
+/// which are compared to this value
+const MAX_TRIGRAM_CANDIDATES: usize = 50;
+
+/// Maximum number of utterances, among the ones sharing the rarest stems with a value, which are
+/// compared to this value
+const MAX_STEM_CANDIDATES: usize = 50;
+
+/// Value of a custom entity whose utterance is the closest to a slot value
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub resolved_value: String,
+    /// Similarity between the slot value and the matched utterance, between 0 and 1
+    pub score: f32,
+}
+
+/// Indexes of the utterances of the custom entities, which resolve the slot values that do not
+/// exactly match any utterance.
+///
+/// The similarity between a value and an utterance is the highest of their edit similarity and of
+/// the overlap of their stems. Only the utterances sharing a character trigram or a stem with the
+/// value are compared to it, so that large entities do not slow down the resolution.
+///
+/// The indexes are shared between the clones of the matcher, so that a matcher can be cheaply
+/// copied in order to reindex one of its entities.
+#[derive(Clone)]
+pub struct FuzzyEntityMatcher {
+    indexes: HashMap<EntityName, Arc<UtterancesIndex>>,
+    stemmer: Option<Arc<CompactStemmer>>,
+    language: NluUtilsLanguage,
+}
+
+impl FuzzyEntityMatcher {
+    pub fn new(
+        entities: &HashMap<EntityName, Entity>,
+        stemmer: Option<Arc<CompactStemmer>>,
+        language: Language,
+    ) -> Self {
+        let mut matcher = Self {
+            indexes: HashMap::new(),
+            stemmer,
+            language: NluUtilsLanguage::from_language(language),
+        };
+        for (entity_name, entity) in entities {
+            matcher.index_entity(entity_name, entity);
+        }
+        matcher
+    }
+
+    /// Indexes the utterances of an entity, replacing the previous index of this entity if any
+    pub fn index_entity(&mut self, entity_name: &str, entity: &Entity) {
+        let utterances = entity
+            .utterances
+            .iter()
+            .sorted()
+            .into_iter()
+            .map(|(utterance, resolved_value)| {
+                let normalized_value = normalize(utterance);
+                IndexedUtterance {
+                    stems: self.stems(&normalized_value),
+                    normalized_value,
+                    resolved_value: resolved_value.clone(),
+                }
+            })
+            .collect();
+        self.indexes.insert(entity_name.to_string(), Arc::new(UtterancesIndex::new(utterances)));
+    }
+
+    /// Returns the value of the entity whose utterance is the most similar to the slot value,
+    /// provided that their similarity reaches the threshold
+    pub fn find_match(&self, entity_name: &str, value: &str, threshold: f32) -> Option<FuzzyMatch> {
+        let index = self.indexes.get(entity_name)?;
+        let normalized_value = normalize(value);
+        let stems = self.stems(&normalized_value);
+        let mut best_match: Option<FuzzyMatch> = None;
+        for candidate in index.candidates(&normalized_value, &stems) {
+            let utterance = &index.utterances[candidate];
+            let score = similarity(&normalized_value, &stems, utterance);
+            let is_better = best_match.as_ref().map_or(true, |best| score > best.score);
+            if score >= threshold && is_better {
+                best_match = Some(FuzzyMatch {
+                    resolved_value: utterance.resolved_value.clone(),
+                    score,
+                });
+            }
+        }
+        best_match
+    }
+
+    fn stems(&self, normalized_value: &str) -> Vec<String> {
+        tokenize_light(normalized_value, self.language)
+            .into_iter()
+            .map(|token| match self.stemmer {
+                Some(ref stemmer) => stemmer.stem(&token).to_string(),
+                None => token,
+            })
+            .unique()
+            .collect()
+    }
+}
+
+impl HeapSize for FuzzyEntityMatcher {
+    fn heap_size(&self) -> usize {
+        self.indexes.capacity()
+            * (mem::size_of::<u64>() + mem::size_of::<(EntityName, Arc<UtterancesIndex>)>())
+            + self.indexes
+                .iter()
+                .map(|(entity_name, index)| {
+                    entity_name.heap_size() + mem::size_of::<UtterancesIndex>() + index.heap_size()
+                })
+                .sum::<usize>()
+    }
+}
+
+struct UtterancesIndex {
+    utterances: Vec<IndexedUtterance>,
+    /// Positions of the utterances containing each character trigram
+    trigrams: HashMap<String, Vec<u32>>,
+    /// Positions of the utterances containing each stem
+    stems: HashMap<String, Vec<u32>>,
+}
+
+struct IndexedUtterance {
+    normalized_value: String,
+    stems: Vec<String>,
+    resolved_value: String,
+}
+
+impl UtterancesIndex {
+    fn new(utterances: Vec<IndexedUtterance>) -> Self {
+        let mut trigrams: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut stems: HashMap<String, Vec<u32>> = HashMap::new();
+        for (position, utterance) in utterances.iter().enumerate() {
+            for trigram in get_trigrams(&utterance.normalized_value) {
+                trigrams.entry(trigram).or_insert_with(Vec::new).push(position as u32);
+            }
+            for stem in &utterance.stems {
+                stems.entry(stem.clone()).or_insert_with(Vec::new).push(position as u32);
+            }
+        }
+        Self {
+            utterances,
+            trigrams,
+            stems,
+        }
+    }
+
+    /// Positions of the utterances which share the most trigrams with the value, along with the
+    /// ones sharing the rarest stems with it
+    fn candidates(&self, normalized_value: &str, stems: &[String]) -> Vec<usize> {
+        let mut trigram_scores: HashMap<u32, f32> = HashMap::new();
+        for trigram in get_trigrams(normalized_value) {
+            for position in self.trigrams.get(&trigram).into_iter().flat_map(|p| p.iter()) {
+                *trigram_scores.entry(*position).or_insert(0.0) += 1.0;
+            }
+        }
+        // Stems are weighted by their rarity, so that common words such as "the" do not make all
+        // the utterances candidates
+        let mut stem_scores: HashMap<u32, f32> = HashMap::new();
+        for positions in stems.iter().filter_map(|stem| self.stems.get(stem)) {
+            let weight = 1.0 / positions.len() as f32;
+            for position in positions {
+                *stem_scores.entry(*position).or_insert(0.0) += weight;
+            }
+        }
+
+        let mut candidates = best_positions(trigram_scores, MAX_TRIGRAM_CANDIDATES);
+        candidates.extend(best_positions(stem_scores, MAX_STEM_CANDIDATES));
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+}
+
+/// Positions having the highest scores, the lowest positions coming first among equal scores
+fn best_positions(scores: HashMap<u32, f32>, max_positions: usize) -> Vec<usize> {
+    let mut scores = scores.into_iter().collect_vec();
+    scores.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(Ordering::Equal)
+            .then(a.0.cmp(&b.0))
+    });
+    scores
+        .into_iter()
+        .take(max_positions)
+        .map(|(position, _)| position as usize)
+        .collect()
+}
+
+impl HeapSize for UtterancesIndex {
+    fn heap_size(&self) -> usize {
+        self.utterances.heap_size() + self.trigrams.heap_size() + self.stems.heap_size()
+    }
+}
+
+impl HeapSize for IndexedUtterance {
+    fn heap_size(&self) -> usize {
+        self.normalized_value.heap_size() + self.stems.heap_size() + self.resolved_value.heap_size()
+    }
+}
+
+/// Distinct character trigrams of the value, which is padded so that short values have trigrams
+fn get_trigrams(value: &str) -> Vec<String> {
+    let chars = format!(" {} ", value).chars().collect_vec();
+    chars
+        .windows(3)
+        .map(|window| window.iter().collect::<String>())
+        .unique()
+        .collect()
+}
+
+fn similarity(normalized_value: &str, stems: &[String], utterance: &IndexedUtterance) -> f32 {
+    let nb_chars = normalized_value
+        .chars()
+        .count()
+        .max(utterance.normalized_value.chars().count());
+    let edit_similarity = if nb_chars == 0 {
+        1.0
+    } else {
+        let distance = edit_distance(normalized_value, &utterance.normalized_value);
+        1.0 - distance as f32 / nb_chars as f32
+    };
+    let nb_shared_stems = stems.iter().filter(|stem| utterance.stems.contains(stem)).count();
+    let nb_stems = stems.len() + utterance.stems.len() - nb_shared_stems;
+    let stems_overlap = if nb_stems == 0 {
+        0.0
+    } else {
+        nb_shared_stems as f32 / nb_stems as f32
+    };
+    edit_similarity.max(stems_overlap)
+}
+
+/// Levenshtein distance between the characters of two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars = b.chars().collect_vec();
+    let mut previous_row = (0..=b_chars.len()).collect_vec();
+    let mut current_row = vec![0; b_chars.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current_row[0] = i + 1;
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution_cost = if a_char == *b_char { 0 } else { 1 };
+            current_row[j + 1] = (previous_row[j] + substitution_cost)
+                .min(previous_row[j + 1] + 1)
+                .min(current_row[j] + 1);
+        }
+        mem::swap(&mut previous_row, &mut current_row);
+    }
+    previous_row[b_chars.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coffee_entities() -> HashMap<EntityName, Entity> {
+        let entity = Entity {
+            automatically_extensible: false,
+            utterances: hashmap! {
+                "cappuccino".to_string() => "cappuccino".to_string(),
+                "espresso".to_string() => "espresso".to_string(),
+                "expresso".to_string() => "espresso".to_string(),
+                "dark roast".to_string() => "dark roast".to_string(),
+            },
+        };
+        hashmap! { "coffee".to_string() => entity }
+    }
+
+    #[test]
+    fn find_match_should_use_edit_distance() {
+        // Given
+        let matcher = FuzzyEntityMatcher::new(&coffee_entities(), None, Language::EN);
+
+        // When
+        let fuzzy_match = matcher.find_match("coffee", "Capuccino", 0.8);
+
+        // Then
+        let expected_match = FuzzyMatch {
+            resolved_value: "cappuccino".to_string(),
+            score: 0.9,
+        };
+        assert_eq!(Some(expected_match), fuzzy_match);
+        assert_eq!(None, matcher.find_match("coffee", "capuccino", 0.95));
+        assert_eq!(None, matcher.find_match("coffee", "latte", 0.5));
+        assert_eq!(None, matcher.find_match("tea", "capuccino", 0.5));
+    }
+
+    #[test]
+    fn find_match_should_use_stems_overlap() {
+        // Given
+        let stemmer = CompactStemmer::from_reader("roast,roasted,roasting".as_bytes()).unwrap();
+        let matcher =
+            FuzzyEntityMatcher::new(&coffee_entities(), Some(Arc::new(stemmer)), Language::EN);
+
+        // When
+        let fuzzy_match = matcher.find_match("coffee", "roasted dark", 0.8);
+
+        // Then
+        let expected_match = FuzzyMatch {
+            resolved_value: "dark roast".to_string(),
+            score: 1.0,
+        };
+        assert_eq!(Some(expected_match), fuzzy_match);
+    }
+
+    #[test]
+    fn candidates_should_be_capped_for_common_stems() {
+        // Given
+        let mut utterances: HashMap<String, String> = (0..200)
+            .map(|i| (format!("the song {}", i), format!("the song {}", i)))
+            .collect();
+        utterances.insert("the dark side".to_string(), "the dark side".to_string());
+        let entity = Entity {
+            automatically_extensible: false,
+            utterances,
+        };
+        let matcher =
+            FuzzyEntityMatcher::new(&hashmap! { "song".to_string() => entity }, None, Language::EN);
+        let index = &matcher.indexes["song"];
+
+        // When
+        let candidates = index.candidates("the dark side of", &matcher.stems("the dark side of"));
+
+        // Then
+        assert!(candidates.len() <= MAX_TRIGRAM_CANDIDATES + MAX_STEM_CANDIDATES);
+        assert!(candidates
+            .iter()
+            .any(|position| index.utterances[*position].normalized_value == "the dark side"));
+    }
+
+    #[test]
+    fn edit_distance_works() {
+        assert_eq!(0, edit_distance("", ""));
+        assert_eq!(3, edit_distance("", "abc"));
+        assert_eq!(1, edit_distance("capuccino", "cappuccino"));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+    }
+}
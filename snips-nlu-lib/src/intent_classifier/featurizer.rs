@@ -1,19 +1,20 @@
 use std::collections::{HashMap, HashSet};
-use std::str::FromStr;
 use std::sync::Arc;
 
 use itertools::Itertools;
 use ndarray::prelude::*;
 
-use builtin_entity_parsing::{BuiltinEntityParserFactory, CachingBuiltinEntityParser};
+use builtin_entity_parsing::CachingBuiltinEntityParser;
 use models::FeaturizerModel;
 use errors::*;
-use language::FromLanguage;
+use language::{parse_language, FromLanguage};
+use memory::HeapSize;
 use nlu_utils::language::Language as NluUtilsLanguage;
 use nlu_utils::string::normalize;
 use nlu_utils::token::{compute_all_ngrams, tokenize_light};
-use resources::stemmer::{get_stemmer, HashMapStemmer, Stemmer};
-use resources::word_clusterer::{get_word_clusterer, HashMapWordClusterer, WordClusterer};
+use resources::SharedResources;
+use resources::stemmer::{CompactStemmer, Stemmer};
+use resources::word_clusterer::{CompactWordClusterer, WordClusterer};
 use snips_nlu_ontology::{BuiltinEntityKind, Language};
 
 pub struct Featurizer {
@@ -21,30 +22,30 @@ pub struct Featurizer {
     vocabulary: HashMap<String, usize>,
     idf_diag: Vec<f32>,
     sublinear: bool,
-    word_clusterer: Option<Arc<HashMapWordClusterer>>,
-    stemmer: Option<Arc<HashMapStemmer>>,
+    word_clusterer: Option<Arc<CompactWordClusterer>>,
+    stemmer: Option<Arc<CompactStemmer>>,
     entity_utterances_to_feature_names: HashMap<String, Vec<String>>,
     builtin_entity_parser: Arc<CachingBuiltinEntityParser>,
     language: Language,
 }
 
 impl Featurizer {
-    pub fn new(config: FeaturizerModel) -> Result<Self> {
+    pub fn new(config: FeaturizerModel, shared_resources: Arc<SharedResources>) -> Result<Self> {
         let best_features = config.best_features;
         let vocabulary = config.tfidf_vectorizer.vocab;
-        let language = Language::from_str(config.language_code.as_ref())?;
+        let language = parse_language(&config.language_code)?;
         let idf_diag = config.tfidf_vectorizer.idf_diag;
-        let builtin_entity_parser = BuiltinEntityParserFactory::get(language);
+        let builtin_entity_parser = shared_resources.builtin_entity_parser.clone();
         let opt_word_clusterer = if let Some(word_clusterer) = config
             .config
             .word_clusters_name
-            .map(|clusters_name| get_word_clusterer(clusters_name, language)) {
+            .map(|clusters_name| shared_resources.get_word_clusterer(&clusters_name)) {
             Some(word_clusterer?)
         } else {
             None
         };
 
-        let stemmer = get_stemmer(language);
+        let stemmer = shared_resources.stemmer.clone();
         let entity_utterances_to_feature_names = config.entity_utterances_to_feature_names;
 
         Ok(Self {
@@ -62,36 +63,33 @@ impl Featurizer {
 
     pub fn transform(&self, input: &str) -> Result<Array1<f32>> {
         let preprocessed_tokens = self.preprocess_query(input);
-        let vocabulary_size = self.vocabulary.values().max().unwrap() + 1;
-
-        let mut tfidf: Vec<f32> = vec![0.; vocabulary_size];
-        let mut match_idx: HashSet<usize> = HashSet::new();
-        for word in preprocessed_tokens {
-            if let Some(word_idx) = self.vocabulary.get(&word) {
-                tfidf[*word_idx] += 1.;
-                match_idx.insert(*word_idx);
-            }
-        }
-
-        for ix in match_idx {
-            if self.sublinear {
-                tfidf[ix] = (tfidf[ix].ln() + 1.) * self.idf_diag[ix]
-            } else {
-                tfidf[ix] *= self.idf_diag[ix]
-            }
-        }
-
-        let l2_norm: f32 = tfidf.iter().fold(0., |norm, v| norm + v * v).sqrt();
-        let safe_l2_norm = if l2_norm > 0. { l2_norm } else { 1. };
-
-        tfidf = tfidf.iter().map(|c| *c / safe_l2_norm).collect_vec();
-
+        let tfidf = compute_tfidf(
+            &preprocessed_tokens,
+            &self.vocabulary,
+            &self.idf_diag,
+            self.sublinear,
+        );
         let selected_features =
             Array::from_iter((0..self.best_features.len()).map(|fi| tfidf[self.best_features[fi]]));
         Ok(selected_features)
     }
 
-    fn preprocess_query(&self, query: &str) -> Vec<String> {
+    /// Names of the features returned by `transform`, in the same order
+    pub fn feature_names(&self) -> Vec<&str> {
+        let vocabulary_size = self.vocabulary.values().max().map(|max| max + 1).unwrap_or(0);
+        let mut vocabulary_names = vec![""; vocabulary_size];
+        for (name, index) in self.vocabulary.iter() {
+            vocabulary_names[*index] = name;
+        }
+        self.best_features
+            .iter()
+            .map(|index| vocabulary_names[*index])
+            .collect()
+    }
+
+    /// Converts the query into the list of features, such as stems and entities, which are
+    /// then weighted by the tf-idf vectorizer
+    pub fn preprocess_query(&self, query: &str) -> Vec<String> {
         let language = NluUtilsLanguage::from_language(self.language);
         let tokens = tokenize_light(query, language);
         let word_cluster_features = self.word_clusterer
@@ -121,6 +119,48 @@ impl Featurizer {
     }
 }
 
+impl HeapSize for Featurizer {
+    fn heap_size(&self) -> usize {
+        self.best_features.heap_size()
+            + self.vocabulary.heap_size()
+            + self.idf_diag.heap_size()
+            + self.entity_utterances_to_feature_names.heap_size()
+    }
+}
+
+/// Computes the l2 normalized tf-idf vector of the preprocessed tokens, ignoring the ones which
+/// are not in the vocabulary
+pub fn compute_tfidf(
+    preprocessed_tokens: &[String],
+    vocabulary: &HashMap<String, usize>,
+    idf_diag: &[f32],
+    sublinear: bool,
+) -> Vec<f32> {
+    let vocabulary_size = vocabulary.values().max().map(|max| max + 1).unwrap_or(0);
+
+    let mut tfidf: Vec<f32> = vec![0.; vocabulary_size];
+    let mut match_idx: HashSet<usize> = HashSet::new();
+    for word in preprocessed_tokens {
+        if let Some(word_idx) = vocabulary.get(word) {
+            tfidf[*word_idx] += 1.;
+            match_idx.insert(*word_idx);
+        }
+    }
+
+    for ix in match_idx {
+        if sublinear {
+            tfidf[ix] = (tfidf[ix].ln() + 1.) * idf_diag[ix]
+        } else {
+            tfidf[ix] *= idf_diag[ix]
+        }
+    }
+
+    let l2_norm: f32 = tfidf.iter().fold(0., |norm, v| norm + v * v).sqrt();
+    let safe_l2_norm = if l2_norm > 0. { l2_norm } else { 1. };
+
+    tfidf.iter().map(|c| *c / safe_l2_norm).collect_vec()
+}
+
 fn get_builtin_entity_feature_name(
     entity_kind: BuiltinEntityKind,
     language: NluUtilsLanguage,
@@ -136,7 +176,11 @@ fn get_word_cluster_features<C: WordClusterer>(
     let tokens_ref = query_tokens.into_iter().map(|t| t.as_ref()).collect_vec();
     compute_all_ngrams(tokens_ref.as_ref(), tokens_ref.len())
         .into_iter()
-        .filter_map(|ngram| word_clusterer.get_cluster(&ngram.0.to_lowercase()))
+        .filter_map(|ngram| {
+            word_clusterer
+                .get_cluster(&ngram.0.to_lowercase())
+                .map(|cluster| cluster.to_string())
+        })
         .sorted()
 }
 
@@ -153,9 +197,14 @@ fn get_dataset_entities_features(
         .sorted()
 }
 
-fn normalize_stem<S: Stemmer>(tokens: &[String], opt_stemmer: Option<&S>) -> Vec<String> {
+pub fn get_entity_feature_name(entity_name: &str, language: NluUtilsLanguage) -> String {
+    let e = tokenize_light(entity_name, language).join("");
+    format!("featureentity{}", e)
+}
+
+pub fn normalize_stem<S: Stemmer>(tokens: &[String], opt_stemmer: Option<&S>) -> Vec<String> {
     opt_stemmer
-        .map(|stemmer| tokens.iter().map(|t| stemmer.stem(&normalize(t))).collect())
+        .map(|stemmer| tokens.iter().map(|t| stemmer.stem(&normalize(t)).to_string()).collect())
         .unwrap_or_else(|| tokens.iter().map(|t| normalize(t)).collect())
 }
 
@@ -169,17 +218,18 @@ mod tests {
     use nlu_utils::token::tokenize_light;
     use resources::stemmer::Stemmer;
     use resources::word_clusterer::WordClusterer;
-    use resources::loading::load_resources;
+    use resources::loading::load_shared_resources;
+    use snips_nlu_ontology::Language as OntologyLanguage;
     use testutils::assert_epsilon_eq_array1;
     use utils::file_path;
 
     struct TestWordClusterer {}
 
     impl WordClusterer for TestWordClusterer {
-        fn get_cluster(&self, word: &str) -> Option<String> {
+        fn get_cluster(&self, word: &str) -> Option<&str> {
             match word {
-                "love" => Some("cluster_love".to_string()),
-                "house" => Some("cluster_house".to_string()),
+                "love" => Some("cluster_love"),
+                "house" => Some("cluster_house"),
                 _ => None,
             }
         }
@@ -188,12 +238,12 @@ mod tests {
     struct TestStemmer {}
 
     impl Stemmer for TestStemmer {
-        fn stem(&self, value: &str) -> String {
+        fn stem<'a>(&'a self, value: &'a str) -> &'a str {
             match value {
-                "bird" => "bir".to_string(),
-                "hello" => "hell".to_string(),
-                "is" => "be".to_string(),
-                _ => value.to_string(),
+                "bird" => "bir",
+                "hello" => "hell",
+                "is" => "be",
+                _ => value,
             }
         }
     }
@@ -205,7 +255,7 @@ mod tests {
             .join("models")
             .join("trained_engine")
             .join("resources");
-        load_resources(resources_path).unwrap();
+        let shared_resources = load_shared_resources(resources_path, OntologyLanguage::EN).unwrap();
         let best_features = vec![0, 1, 2, 3, 6, 7, 8, 9];
         let vocab = hashmap![
             "awful".to_string() => 0,
@@ -251,7 +301,7 @@ mod tests {
             entity_utterances_to_feature_names,
         };
 
-        let featurizer = Featurizer::new(featurizer_config).unwrap();
+        let featurizer = Featurizer::new(featurizer_config, shared_resources).unwrap();
 
         // When
         let input = "Hëllo this bïrd is a beautiful Bïrd";
@@ -0,0 +1,349 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use dataset::Dataset;
+use errors::*;
+use intent_classifier::featurizer::{compute_tfidf, get_entity_feature_name, normalize_stem,
+                                    Featurizer};
+use language::{parse_language, FromLanguage};
+use models::{FeaturizerConfiguration, FeaturizerModel, TfIdfVectorizerModel};
+use nlu_utils::language::Language as NluUtilsLanguage;
+use nlu_utils::token::tokenize_light;
+use resources::SharedResources;
+
+/// Configuration of the featurizer training
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FeaturizerConfig {
+    /// Features whose chi2 p-value is above this threshold are discarded
+    pub pvalue_threshold: f32,
+    pub sublinear_tf: bool,
+    pub word_clusters_name: Option<String>,
+}
+
+impl Default for FeaturizerConfig {
+    fn default() -> Self {
+        Self {
+            pvalue_threshold: 0.4,
+            sublinear_tf: false,
+            word_clusters_name: None,
+        }
+    }
+}
+
+/// Fits the featurizer on the labeled training texts, and returns it along with the vectorized
+/// training texts, restricted to the selected features
+pub fn fit_featurizer(
+    dataset: &Dataset,
+    texts: &[String],
+    labels: &[usize],
+    config: &FeaturizerConfig,
+    shared_resources: Arc<SharedResources>,
+) -> Result<(FeaturizerModel, Vec<Vec<f32>>)> {
+    let language = parse_language(&dataset.language)?;
+    let entity_utterances_to_feature_names = get_entity_utterances_to_feature_names(
+        dataset,
+        NluUtilsLanguage::from_language(language),
+        &shared_resources,
+    );
+    let configuration = FeaturizerConfiguration {
+        sublinear_tf: config.sublinear_tf,
+        word_clusters_name: config.word_clusters_name.clone(),
+    };
+    let preprocessor = Featurizer::new(
+        FeaturizerModel {
+            language_code: dataset.language.clone(),
+            tfidf_vectorizer: TfIdfVectorizerModel {
+                idf_diag: vec![],
+                vocab: HashMap::new(),
+            },
+            config: configuration.clone(),
+            best_features: vec![],
+            entity_utterances_to_feature_names: entity_utterances_to_feature_names.clone(),
+        },
+        shared_resources,
+    )?;
+    let documents = texts
+        .iter()
+        .map(|text| preprocessor.preprocess_query(text))
+        .collect_vec();
+
+    let tfidf_vectorizer = fit_tfidf(&documents);
+    let tfidf_features = documents
+        .iter()
+        .map(|document| compute_tfidf(
+            document,
+            &tfidf_vectorizer.vocab,
+            &tfidf_vectorizer.idf_diag,
+            config.sublinear_tf,
+        ))
+        .collect_vec();
+    let pvalues = chi2_pvalues(&tfidf_features, labels);
+    let best_features = select_best_features(&pvalues, config.pvalue_threshold);
+    let features = tfidf_features
+        .iter()
+        .map(|row| best_features.iter().map(|ix| row[*ix]).collect())
+        .collect();
+
+    let model = FeaturizerModel {
+        language_code: dataset.language.clone(),
+        tfidf_vectorizer,
+        config: configuration,
+        best_features,
+        entity_utterances_to_feature_names,
+    };
+    Ok((model, features))
+}
+
+/// Maps the normalized and stemmed values of the custom entities to the corresponding entity
+/// features
+fn get_entity_utterances_to_feature_names(
+    dataset: &Dataset,
+    language: NluUtilsLanguage,
+    shared_resources: &SharedResources,
+) -> HashMap<String, Vec<String>> {
+    let stemmer = shared_resources.stemmer.as_ref().map(|stemmer| stemmer.as_ref());
+    let mut utterances_to_feature_names: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for (entity_name, entity) in dataset.entities.keys().sorted().into_iter()
+        .filter_map(|name| dataset.custom_entity(name).map(|entity| (name, entity))) {
+        let feature_name = get_entity_feature_name(entity_name, language);
+        for utterance in entity.utterances() {
+            let tokens = tokenize_light(utterance, language);
+            let key = normalize_stem(&tokens, stemmer).join(" ");
+            utterances_to_feature_names
+                .entry(key)
+                .or_insert_with(BTreeSet::new)
+                .insert(feature_name.clone());
+        }
+    }
+    utterances_to_feature_names
+        .into_iter()
+        .map(|(utterance, feature_names)| (utterance, feature_names.into_iter().collect()))
+        .collect()
+}
+
+/// Builds the vocabulary, sorted alphabetically, and the smoothed idf weights of the documents
+pub fn fit_tfidf(documents: &[Vec<String>]) -> TfIdfVectorizerModel {
+    let vocab: HashMap<String, usize> = documents
+        .iter()
+        .flat_map(|document| document.iter())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .enumerate()
+        .map(|(index, word)| (word.clone(), index))
+        .collect();
+    let mut document_frequencies = vec![0; vocab.len()];
+    for document in documents {
+        let word_indexes: HashSet<&usize> =
+            document.iter().filter_map(|word| vocab.get(word)).collect();
+        for word_index in word_indexes {
+            document_frequencies[*word_index] += 1;
+        }
+    }
+    let nb_documents = documents.len() as f32;
+    let idf_diag = document_frequencies
+        .into_iter()
+        .map(|frequency| ((1. + nb_documents) / (1. + frequency as f32)).ln() + 1.)
+        .collect();
+    TfIdfVectorizerModel { idf_diag, vocab }
+}
+
+/// Computes, for each feature, the p-value of the chi-squared test of independence between the
+/// feature and the labels
+pub fn chi2_pvalues(features: &[Vec<f32>], labels: &[usize]) -> Vec<f32> {
+    let nb_features = features.first().map(|row| row.len()).unwrap_or(0);
+    let nb_classes = labels.iter().max().map(|max| max + 1).unwrap_or(0);
+    let mut observed = vec![vec![0.0f64; nb_features]; nb_classes];
+    let mut class_counts = vec![0usize; nb_classes];
+    for (row, label) in features.iter().zip(labels.iter()) {
+        class_counts[*label] += 1;
+        for (j, value) in row.iter().enumerate() {
+            observed[*label][j] += *value as f64;
+        }
+    }
+    let nb_samples = labels.len() as f64;
+    let present_classes = (0..nb_classes).filter(|c| class_counts[*c] > 0).collect_vec();
+    let degrees_of_freedom = present_classes.len().saturating_sub(1);
+    if degrees_of_freedom == 0 {
+        return vec![1.0; nb_features];
+    }
+    (0..nb_features)
+        .map(|j| {
+            let feature_count: f64 = present_classes.iter().map(|c| observed[*c][j]).sum();
+            let chi2: f64 = present_classes
+                .iter()
+                .map(|c| {
+                    let expected = class_counts[*c] as f64 / nb_samples * feature_count;
+                    if expected > 0. {
+                        (observed[*c][j] - expected).powi(2) / expected
+                    } else {
+                        0.
+                    }
+                })
+                .sum();
+            chi2_survival(chi2, degrees_of_freedom as f64) as f32
+        })
+        .collect()
+}
+
+/// Returns the indexes of the features whose p-value is below the threshold, or the ones with
+/// the lowest p-value when none of them is
+pub fn select_best_features(pvalues: &[f32], pvalue_threshold: f32) -> Vec<usize> {
+    let best_features = (0..pvalues.len())
+        .filter(|ix| pvalues[*ix] < pvalue_threshold)
+        .collect_vec();
+    if !best_features.is_empty() {
+        return best_features;
+    }
+    let min_pvalue = pvalues.iter().cloned().fold(::std::f32::INFINITY, f32::min);
+    (0..pvalues.len())
+        .filter(|ix| pvalues[*ix] == min_pvalue)
+        .collect()
+}
+
+const MAX_ITERATIONS: usize = 1000;
+const EPSILON: f64 = 1e-12;
+const TINY: f64 = 1e-300;
+
+/// Survival function of the chi-squared distribution
+fn chi2_survival(x: f64, degrees_of_freedom: f64) -> f64 {
+    if x <= 0. {
+        return 1.;
+    }
+    regularized_upper_gamma(degrees_of_freedom / 2., x / 2.)
+}
+
+fn regularized_upper_gamma(a: f64, x: f64) -> f64 {
+    let log_prefactor = -x + a * x.ln() - ln_gamma(a);
+    if x < a + 1. {
+        // Series representation of the lower incomplete gamma function
+        let mut term = 1. / a;
+        let mut sum = term;
+        let mut ap = a;
+        for _ in 0..MAX_ITERATIONS {
+            ap += 1.;
+            term *= x / ap;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        (1. - sum * log_prefactor.exp()).max(0.)
+    } else {
+        // Continued fraction representation, evaluated with the modified Lentz's method
+        let mut b = x + 1. - a;
+        let mut c = 1. / TINY;
+        let mut d = 1. / b;
+        let mut h = d;
+        for i in 1..MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1. / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.).abs() < EPSILON {
+                break;
+            }
+        }
+        log_prefactor.exp() * h
+    }
+}
+
+/// Lanczos approximation of the logarithm of the gamma function, for positive values
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.99999999999980993,
+        676.5203681218851,
+        -1259.1392167224028,
+        771.32342877765313,
+        -176.61502916214059,
+        12.507343278686905,
+        -0.13857109526572012,
+        9.9843695780195716e-6,
+        1.5056327351493116e-7,
+    ];
+    let x = x - 1.;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, coeff)| acc + coeff / (x + i as f64 + 1.));
+    0.5 * (2. * ::std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testutils::epsilon_eq;
+
+    #[test]
+    fn fit_tfidf_works() {
+        // Given
+        let documents = vec![
+            vec!["make".to_string(), "coffee".to_string()],
+            vec!["make".to_string(), "tea".to_string()],
+        ];
+
+        // When
+        let tfidf_vectorizer = fit_tfidf(&documents);
+
+        // Then
+        let expected_vocab = hashmap![
+            "coffee".to_string() => 0,
+            "make".to_string() => 1,
+            "tea".to_string() => 2
+        ];
+        assert_eq!(expected_vocab, tfidf_vectorizer.vocab);
+        assert!(epsilon_eq(1.4054651, tfidf_vectorizer.idf_diag[0], 1e-6));
+        assert!(epsilon_eq(1.0, tfidf_vectorizer.idf_diag[1], 1e-6));
+        assert!(epsilon_eq(1.4054651, tfidf_vectorizer.idf_diag[2], 1e-6));
+    }
+
+    #[test]
+    fn chi2_pvalues_works() {
+        // Given
+        let features = vec![
+            vec![1., 0., 1.],
+            vec![1., 0., 1.],
+            vec![0., 1., 1.],
+            vec![0., 1., 1.],
+        ];
+        let labels = vec![0, 0, 1, 1];
+
+        // When
+        let pvalues = chi2_pvalues(&features, &labels);
+
+        // Then
+        assert!(epsilon_eq(0.15729921, pvalues[0], 1e-5));
+        assert!(epsilon_eq(0.15729921, pvalues[1], 1e-5));
+        assert!(epsilon_eq(1.0, pvalues[2], 1e-5));
+    }
+
+    #[test]
+    fn chi2_survival_works() {
+        assert!((chi2_survival(3.841459, 1.) - 0.05).abs() < 1e-6);
+        assert!((chi2_survival(5.991465, 2.) - 0.05).abs() < 1e-6);
+        assert!((chi2_survival(30., 10.) - 0.000857).abs() < 1e-5);
+    }
+
+    #[test]
+    fn select_best_features_works() {
+        // When
+        let best_features = select_best_features(&[0.1, 0.5, 0.3], 0.4);
+        let fallback_features = select_best_features(&[0.8, 0.5, 0.5], 0.4);
+
+        // Then
+        assert_eq!(vec![0, 2], best_features);
+        assert_eq!(vec![1, 2], fallback_features);
+    }
+}
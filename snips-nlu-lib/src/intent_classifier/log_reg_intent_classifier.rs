@@ -1,18 +1,22 @@
 use std::collections::HashSet;
-use std::fs::File;
 use std::path::Path;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use ndarray::prelude::*;
-use serde_json;
 
 use models::IntentClassifierModel;
+use models::migration::load_model;
 use errors::*;
+use explanation::FeatureContribution;
 use failure::ResultExt;
 use intent_classifier::logreg::MulticlassLogisticRegression;
-use intent_classifier::{Featurizer, IntentClassifier};
+use intent_classifier::{Featurizer, IntentClassifier, IntentProbability};
+use memory::HeapSize;
+use resources::SharedResources;
 use snips_nlu_ontology::IntentClassifierResult;
 use utils::{FromPath, IntentName};
+use vfs::FileSystem;
 
 pub struct LogRegIntentClassifier {
     intent_list: Vec<Option<IntentName>>,
@@ -21,21 +25,30 @@ pub struct LogRegIntentClassifier {
 }
 
 impl FromPath for LogRegIntentClassifier {
-    fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+    fn from_vfs<P: AsRef<Path>>(
+        file_system: &FileSystem,
+        path: P,
+        shared_resources: Arc<SharedResources>,
+    ) -> Result<Self> {
         let classifier_model_path = path.as_ref().join("intent_classifier.json");
-        let model_file = File::open(&classifier_model_path)
+        let model_file = file_system.open(&classifier_model_path)
             .with_context(|_|
                 format!("Cannot open LogRegIntentClassifier file '{:?}'", &classifier_model_path))?;
-        let model: IntentClassifierModel = serde_json::from_reader(model_file)
-            .with_context(|_| "Cannot deserialize LogRegIntentClassifier json data")?;
-        Self::new(model)
+        let model: IntentClassifierModel =
+            load_model(model_file, &classifier_model_path, &shared_resources.model_version)
+            .with_context(|_|
+                SnipsNluError::InvalidJson(classifier_model_path.to_string_lossy().into_owned()))?;
+        Self::new(model, shared_resources)
     }
 }
 
 impl LogRegIntentClassifier {
-    pub fn new(model: IntentClassifierModel) -> Result<Self> {
+    pub fn new(
+        model: IntentClassifierModel,
+        shared_resources: Arc<SharedResources>,
+    ) -> Result<Self> {
         let featurizer: Option<Featurizer> = if let Some(featurizer_model) = model.featurizer {
-            Some(Featurizer::new(featurizer_model)?)
+            Some(Featurizer::new(featurizer_model, shared_resources)?)
         } else {
             None
         };
@@ -66,17 +79,124 @@ impl IntentClassifier for LogRegIntentClassifier {
         input: &str,
         intents_filter: Option<&HashSet<IntentName>>,
     ) -> Result<Option<IntentClassifierResult>> {
+        Ok(self.get_sorted_intents_probabilities(input, intents_filter)?
+            .into_iter()
+            .next()
+            .and_then(|(opt_intent, probability)| {
+                opt_intent.map(|intent_name| IntentClassifierResult {
+                    intent_name,
+                    probability,
+                })
+            }))
+    }
+
+    fn get_top_intents(
+        &self,
+        input: &str,
+        intents_filter: Option<&HashSet<IntentName>>,
+        top_k: usize,
+    ) -> Result<Vec<IntentClassifierResult>> {
+        Ok(self.get_sorted_intents_probabilities(input, intents_filter)?
+            .into_iter()
+            .filter_map(|(opt_intent, probability)| {
+                opt_intent.map(|intent_name| IntentClassifierResult {
+                    intent_name,
+                    probability,
+                })
+            })
+            .take(top_k)
+            .collect())
+    }
+
+    fn get_intents(&self, input: &str) -> Result<Vec<IntentProbability>> {
+        let has_model = self.featurizer.is_some() && self.logreg.is_some();
+        let intents_probabilities = if self.intent_list.len() > 1 && !input.is_empty() && has_model {
+            // The one-vs-rest probabilities are normalized so that they form a distribution
+            let probabilities = self.get_sorted_intents_probabilities(input, None)?;
+            let total: f32 = probabilities.iter().map(|&(_, probability)| probability).sum();
+            probabilities
+                .into_iter()
+                .map(|(intent, probability)| (intent, probability / total))
+                .collect()
+        } else if self.intent_list.len() == 1 {
+            vec![(self.intent_list[0].clone(), 1.0)]
+        } else {
+            // Nothing can be classified here, hence the None intent gets all the probability mass
+            let mut probabilities = self.intent_list
+                .iter()
+                .map(|opt_intent| {
+                    let probability = if opt_intent.is_none() { 1.0 } else { 0.0 };
+                    (opt_intent.clone(), probability)
+                })
+                .collect_vec();
+            probabilities.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+            probabilities
+        };
+        Ok(intents_probabilities
+            .into_iter()
+            .map(|(intent_name, probability)| IntentProbability {
+                intent_name,
+                probability,
+            })
+            .collect())
+    }
+
+    fn explain_intent(
+        &self,
+        input: &str,
+        intent_name: &str,
+        top_k: usize,
+    ) -> Result<Vec<FeatureContribution>> {
+        let intent_index = self.intent_list
+            .iter()
+            .position(|opt_intent| {
+                opt_intent.as_ref().map_or(false, |intent| intent == intent_name)
+            })
+            .ok_or_else(|| SnipsNluError::UnknownIntent(intent_name.to_string()))?;
+        let (featurizer, logreg) = match (self.featurizer.as_ref(), self.logreg.as_ref()) {
+            (Some(featurizer), Some(logreg)) => (featurizer, logreg),
+            _ => return Ok(vec![]),
+        };
+        let features = featurizer.transform(input)?;
+        let mut contributions = featurizer
+            .feature_names()
+            .into_iter()
+            .zip(features.iter())
+            .zip(logreg.features_weights(intent_index))
+            .filter(|&((_, tfidf), _)| *tfidf != 0.0)
+            .map(|((feature, tfidf), weight)| FeatureContribution {
+                feature: feature.to_string(),
+                tfidf: *tfidf,
+                weight,
+                contribution: tfidf * weight,
+            })
+            .collect_vec();
+        contributions.sort_by(|a, b| b.contribution.partial_cmp(&a.contribution).unwrap());
+        contributions.truncate(top_k);
+        Ok(contributions)
+    }
+}
+
+impl HeapSize for LogRegIntentClassifier {
+    fn heap_size(&self) -> usize {
+        self.intent_list.heap_size() + self.featurizer.heap_size() + self.logreg.heap_size()
+    }
+}
+
+impl LogRegIntentClassifier {
+    /// Returns the intents, including the None intent, which satisfy the intents filter along
+    /// with their probabilities, sorted by decreasing probability
+    fn get_sorted_intents_probabilities(
+        &self,
+        input: &str,
+        intents_filter: Option<&HashSet<IntentName>>,
+    ) -> Result<Vec<(Option<IntentName>, f32)>> {
         if input.is_empty() || self.intent_list.is_empty() {
-            return Ok(None);
+            return Ok(vec![]);
         }
 
         if self.intent_list.len() == 1 {
-            return Ok(self.intent_list[0]
-                .as_ref()
-                .map(|intent_name| IntentClassifierResult {
-                    intent_name: intent_name.clone(),
-                    probability: 1.0,
-                }));
+            return Ok(vec![(self.intent_list[0].clone(), 1.0)]);
         }
 
         if let (Some(featurizer), Some(logreg)) = (self.featurizer.as_ref(), self.logreg.as_ref()) {
@@ -92,29 +212,21 @@ impl IntentClassifier for LogRegIntentClassifier {
             // Sort intents by decreasing probabilities
             intents_proba.sort_by(|a, b| b.1.partial_cmp(a.1).unwrap());
 
-            let mut filtered_intents = intents_proba.into_iter().filter(|&(opt_intent, _)| {
-                if let Some(intent) = opt_intent.as_ref() {
-                    intents_filter
-                        .map(|intents| intents.contains(intent))
-                        .unwrap_or(true)
-                } else {
-                    true
-                }
-            });
-
-            filtered_intents
-                .next()
-                .map(|(opt_intent, proba)| {
-                    Ok(opt_intent
-                        .clone()
-                        .map(|intent_name| IntentClassifierResult {
-                            intent_name: intent_name.clone(),
-                            probability: *proba,
-                        }))
+            Ok(intents_proba
+                .into_iter()
+                .filter(|&(opt_intent, _)| {
+                    if let Some(intent) = opt_intent.as_ref() {
+                        intents_filter
+                            .map(|intents| intents.contains(intent))
+                            .unwrap_or(true)
+                    } else {
+                        true
+                    }
                 })
-                .unwrap_or(Ok(None))
+                .map(|(opt_intent, proba)| (opt_intent.clone(), *proba))
+                .collect())
         } else {
-            Ok(None)
+            Ok(vec![])
         }
     }
 }
@@ -157,7 +269,8 @@ mod tests {
     use utils::file_path;
 
     use models::{FeaturizerConfiguration, FeaturizerModel, TfIdfVectorizerModel};
-    use resources::loading::load_resources;
+    use resources::loading::load_shared_resources;
+    use snips_nlu_ontology::Language;
 
     fn get_sample_log_reg_classifier() -> LogRegIntentClassifier {
         let language_code = "en".to_string();
@@ -419,7 +532,12 @@ mod tests {
             entity_utterances_to_feature_names,
         };
 
-        let featurizer = Featurizer::new(config).unwrap();
+        let resources_path = file_path("tests")
+            .join("models")
+            .join("trained_engine")
+            .join("resources");
+        let shared_resources = load_shared_resources(resources_path, Language::EN).unwrap();
+        let featurizer = Featurizer::new(config, shared_resources).unwrap();
 
         let intercept = array![
             -0.6769558144299883,
@@ -534,9 +652,14 @@ mod tests {
             .join("trained_engine")
             .join("probabilistic_intent_parser")
             .join("intent_classifier");
+        let resources_path = file_path("tests")
+            .join("models")
+            .join("trained_engine")
+            .join("resources");
+        let shared_resources = load_shared_resources(resources_path, Language::EN).unwrap();
 
         // When
-        let intent_classifier = LogRegIntentClassifier::from_path(path).unwrap();
+        let intent_classifier = LogRegIntentClassifier::from_path(path, shared_resources).unwrap();
         let intent_result = intent_classifier
             .get_intent("Make me one cup of tea please", None)
             .unwrap()
@@ -550,11 +673,6 @@ mod tests {
     #[test]
     fn get_intent_works() {
         // Given
-        let resources_path = file_path("tests")
-            .join("models")
-            .join("trained_engine")
-            .join("resources");
-        load_resources(resources_path).unwrap();
         let classifier = get_sample_log_reg_classifier();
 
         // When
@@ -606,6 +724,86 @@ mod tests {
         assert_eq!(None, result3);
     }
 
+    #[test]
+    fn get_top_intents_works() {
+        // Given
+        let classifier = get_sample_log_reg_classifier();
+
+        // When
+        let top_intents = classifier
+            .get_top_intents("Make me two cups of tea", None, 2)
+            .unwrap();
+
+        // Then
+        let intent_names = top_intents
+            .iter()
+            .map(|res| &*res.intent_name)
+            .collect_vec();
+        assert_eq!(vec!["MakeTea", "MakeCoffee"], intent_names);
+        assert_eq!(0.6514961, top_intents[0].probability);
+        assert!(top_intents[0].probability >= top_intents[1].probability);
+    }
+
+    #[test]
+    fn get_top_intents_should_filter_intents() {
+        // Given
+        let classifier = get_sample_log_reg_classifier();
+
+        // When
+        let top_intents = classifier
+            .get_top_intents(
+                "Make me two cups of tea",
+                Some(hashset! {"MakeCoffee".to_string()}).as_ref(),
+                2,
+            )
+            .unwrap();
+
+        // Then
+        let intent_names = top_intents
+            .iter()
+            .map(|res| &*res.intent_name)
+            .collect_vec();
+        assert_eq!(vec!["MakeCoffee"], intent_names);
+    }
+
+    #[test]
+    fn get_intents_works() {
+        // Given
+        let classifier = get_sample_log_reg_classifier();
+
+        // When
+        let intents = classifier.get_intents("Make me two cups of tea").unwrap();
+
+        // Then
+        let intent_names = intents
+            .iter()
+            .map(|res| res.intent_name.as_ref().map(|name| &**name))
+            .collect_vec();
+        assert_eq!(3, intent_names.len());
+        assert_eq!(Some("MakeTea"), intent_names[0]);
+        assert!(intent_names.contains(&Some("MakeCoffee")));
+        assert!(intent_names.contains(&None));
+        let total_probability: f32 = intents.iter().map(|intent| intent.probability).sum();
+        assert!((total_probability - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn get_intents_should_return_none_intent_when_input_is_empty() {
+        // Given
+        let classifier = get_sample_log_reg_classifier();
+
+        // When
+        let intents = classifier.get_intents("").unwrap();
+
+        // Then
+        let expected_intents = vec![
+            IntentProbability { intent_name: None, probability: 1.0 },
+            IntentProbability { intent_name: Some("MakeCoffee".to_string()), probability: 0.0 },
+            IntentProbability { intent_name: Some("MakeTea".to_string()), probability: 0.0 },
+        ];
+        assert_eq!(expected_intents, intents);
+    }
+
     #[test]
     fn should_get_filtered_out_intents_indexes() {
         // Given
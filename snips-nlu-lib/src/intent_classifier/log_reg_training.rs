@@ -0,0 +1,459 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use rand::Rng;
+
+use data_augmentation::augment_utterances;
+use dataset::Dataset;
+use errors::*;
+use failure::ResultExt;
+use intent_classifier::featurizer_training::{fit_featurizer, FeaturizerConfig};
+use models::{IntentClassifierModel, ProcessingUnitMetadata};
+use resources::SharedResources;
+use serde_json;
+use utils::{seeded_rng, IntentName};
+
+/// Word the noise utterances are made of when no noise is available, as in the Python trainer
+const UNKNOWN_WORD: &str = "unknownword";
+
+/// Configuration of the `LogRegIntentClassifier` training
+#[derive(Debug, Clone, PartialEq, Deserialize, Default)]
+#[serde(default)]
+pub struct LogRegIntentClassifierConfig {
+    pub featurizer_config: FeaturizerConfig,
+    pub data_augmentation_config: IntentClassifierDataAugmentationConfig,
+    pub sgd_config: SgdConfig,
+    pub random_seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct IntentClassifierDataAugmentationConfig {
+    /// Minimum number of utterances per intent, which is reached by generating utterances
+    pub min_utterances: usize,
+    /// Number of noise utterances used to train the None intent, relatively to the average
+    /// number of utterances per intent
+    pub noise_factor: usize,
+    /// Words the noise utterances are made of, which default to the noise of the language
+    /// resources, or to a placeholder word when the resources have none
+    pub noise: Vec<String>,
+}
+
+impl Default for IntentClassifierDataAugmentationConfig {
+    fn default() -> Self {
+        Self {
+            min_utterances: 20,
+            noise_factor: 5,
+            noise: vec![],
+        }
+    }
+}
+
+/// Configuration of the stochastic gradient descent which fits the l2 regularized logistic
+/// regressions
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SgdConfig {
+    pub epochs: usize,
+    /// Regularization factor
+    pub alpha: f32,
+    /// Initial learning rate, which then decreases as `1 / (1 + alpha * learning_rate * t)`
+    pub learning_rate: f32,
+}
+
+impl Default for SgdConfig {
+    fn default() -> Self {
+        Self {
+            epochs: 50,
+            alpha: 1e-3,
+            learning_rate: 0.5,
+        }
+    }
+}
+
+/// Trains the intent classifier model which is loaded by `LogRegIntentClassifier`
+pub struct LogRegIntentClassifierTrainer {
+    config: LogRegIntentClassifierConfig,
+}
+
+impl LogRegIntentClassifierTrainer {
+    pub fn new(config: LogRegIntentClassifierConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn fit(
+        &self,
+        dataset: &Dataset,
+        shared_resources: Arc<SharedResources>,
+    ) -> Result<IntentClassifierModel> {
+        let mut rng = seeded_rng(self.config.random_seed);
+        let augmentation_config = &self.config.data_augmentation_config;
+        let intent_names = dataset.intent_names();
+        let mut texts: Vec<String> = vec![];
+        let mut labels: Vec<usize> = vec![];
+        for (label, intent_name) in intent_names.iter().enumerate() {
+            let utterances = augment_utterances(
+                dataset,
+                intent_name,
+                augmentation_config.min_utterances,
+                &mut rng,
+            );
+            for utterance in utterances {
+                texts.push(utterance.text());
+                labels.push(label);
+            }
+        }
+
+        let mut intent_list: Vec<Option<IntentName>> =
+            intent_names.into_iter().map(Some).collect();
+        let nb_noise_utterances = if intent_list.is_empty() {
+            0
+        } else {
+            augmentation_config.noise_factor * texts.len() / intent_list.len()
+        };
+        if nb_noise_utterances > 0 {
+            let placeholder_noise = vec![UNKNOWN_WORD.to_string()];
+            let noise: &[String] = if !augmentation_config.noise.is_empty() {
+                &augmentation_config.noise
+            } else if let Some(noise) = shared_resources.noise.as_ref() {
+                noise
+            } else {
+                &placeholder_noise
+            };
+            let noise_utterances = generate_noise_utterances(
+                noise,
+                nb_noise_utterances,
+                mean_length(&texts),
+                &mut rng,
+            );
+            labels.extend(::std::iter::repeat(intent_list.len()).take(noise_utterances.len()));
+            texts.extend(noise_utterances);
+            intent_list.push(None);
+        }
+
+        if intent_list.len() < 2 {
+            return Ok(IntentClassifierModel {
+                featurizer: None,
+                intercept: None,
+                coeffs: None,
+                intent_list,
+            });
+        }
+
+        let (featurizer, features) = fit_featurizer(
+            dataset,
+            &texts,
+            &labels,
+            &self.config.featurizer_config,
+            shared_resources,
+        )?;
+        let (intercept, coeffs) = fit_logistic_regression(
+            &features,
+            &labels,
+            intent_list.len(),
+            &self.config.sgd_config,
+            &mut rng,
+        );
+
+        Ok(IntentClassifierModel {
+            featurizer: Some(featurizer),
+            intercept: Some(intercept),
+            coeffs: Some(coeffs),
+            intent_list,
+        })
+    }
+}
+
+/// Writes the model in the directory layout expected by `LogRegIntentClassifier::from_path`
+pub fn persist_intent_classifier<P: AsRef<Path>>(
+    model: &IntentClassifierModel,
+    path: P,
+) -> Result<()> {
+    let path = path.as_ref();
+    fs::create_dir_all(path)
+        .with_context(|_| format!("Cannot create intent classifier directory {:?}", path))?;
+    let model_file = fs::File::create(path.join("intent_classifier.json"))?;
+    serde_json::to_writer(model_file, model)?;
+    let metadata_file = fs::File::create(path.join("metadata.json"))?;
+    serde_json::to_writer(metadata_file, &ProcessingUnitMetadata::LogRegIntentClassifier)?;
+    Ok(())
+}
+
+fn mean_length(texts: &[String]) -> usize {
+    if texts.is_empty() {
+        return 0;
+    }
+    let nb_words: usize = texts.iter().map(|text| text.split_whitespace().count()).sum();
+    nb_words / texts.len()
+}
+
+fn generate_noise_utterances<R: Rng>(
+    noise: &[String],
+    nb_utterances: usize,
+    mean_length: usize,
+    rng: &mut R,
+) -> Vec<String> {
+    (0..nb_utterances)
+        .map(|_| {
+            let length = rng.gen_range(1, 2 * mean_length.max(1) + 1);
+            (0..length)
+                .map(|_| rng.choose(noise).unwrap().as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+/// Fits one-vs-rest binary logistic regressions with a stochastic gradient descent, where the
+/// samples are weighted so that all classes are balanced.
+///
+/// The returned intercepts and coefficients have one entry per class, except in the binary case
+/// where a single regression is fitted for the second class, as expected by
+/// `MulticlassLogisticRegression`.
+pub fn fit_logistic_regression<R: Rng>(
+    features: &[Vec<f32>],
+    labels: &[usize],
+    nb_classes: usize,
+    config: &SgdConfig,
+    rng: &mut R,
+) -> (Vec<f32>, Vec<Vec<f32>>) {
+    let mut class_counts = vec![0usize; nb_classes];
+    for label in labels {
+        class_counts[*label] += 1;
+    }
+    let sample_weights: Vec<f32> = labels
+        .iter()
+        .map(|label| labels.len() as f32 / (nb_classes * class_counts[*label]) as f32)
+        .collect();
+    let positive_classes: Vec<usize> = if nb_classes == 2 {
+        vec![1]
+    } else {
+        (0..nb_classes).collect()
+    };
+    positive_classes
+        .into_iter()
+        .map(|class| {
+            let targets: Vec<f32> = labels
+                .iter()
+                .map(|label| if *label == class { 1. } else { 0. })
+                .collect();
+            fit_binary_logistic_regression(features, &targets, &sample_weights, config, rng)
+        })
+        .unzip()
+}
+
+fn fit_binary_logistic_regression<R: Rng>(
+    features: &[Vec<f32>],
+    targets: &[f32],
+    sample_weights: &[f32],
+    config: &SgdConfig,
+    rng: &mut R,
+) -> (f32, Vec<f32>) {
+    let nb_features = features.first().map(|row| row.len()).unwrap_or(0);
+    let mut weights = vec![0.; nb_features];
+    let mut intercept = 0.;
+    let mut samples_order: Vec<usize> = (0..features.len()).collect();
+    let mut step = 0;
+    for _ in 0..config.epochs {
+        rng.shuffle(&mut samples_order);
+        for sample_index in &samples_order {
+            let eta = config.learning_rate
+                / (1. + config.alpha * config.learning_rate * step as f32);
+            let sample = &features[*sample_index];
+            let score = weights
+                .iter()
+                .zip(sample.iter())
+                .fold(intercept, |score, (weight, value)| score + weight * value);
+            let gradient = (sigmoid(score) - targets[*sample_index]) * sample_weights[*sample_index];
+            for (weight, value) in weights.iter_mut().zip(sample.iter()) {
+                *weight -= eta * (gradient * value + config.alpha * *weight);
+            }
+            intercept -= eta * gradient;
+            step += 1;
+        }
+    }
+    (intercept, weights)
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1. / (1. + (-x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intent_classifier::{IntentClassifier, LogRegIntentClassifier};
+    use resources::loading::load_shared_resources;
+    use snips_nlu_ontology::Language;
+    use utils::file_path;
+
+    fn sample_dataset() -> Dataset {
+        serde_json::from_str(r#"{
+            "language": "en",
+            "intents": {
+                "MakeCoffee": {
+                    "utterances": [
+                        {"data": [{"text": "make me a coffee"}]},
+                        {"data": [{"text": "i want some coffee please"}]},
+                        {"data": [{"text": "brew a cup of coffee"}]}
+                    ]
+                },
+                "MakeTea": {
+                    "utterances": [
+                        {"data": [{"text": "make me a tea"}]},
+                        {"data": [{"text": "i want some tea please"}]},
+                        {"data": [{"text": "prepare a cup of tea"}]}
+                    ]
+                }
+            },
+            "entities": {}
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn fit_logistic_regression_works() {
+        // Given
+        let features = vec![
+            vec![1., 0., 0.],
+            vec![0.9, 0.1, 0.],
+            vec![0., 1., 0.],
+            vec![0.1, 0.9, 0.],
+            vec![0., 0., 1.],
+            vec![0., 0.1, 0.9],
+        ];
+        let labels = vec![0, 0, 1, 1, 2, 2];
+        let mut rng = seeded_rng(Some(1));
+
+        // When
+        let (intercept, coeffs) =
+            fit_logistic_regression(&features, &labels, 3, &SgdConfig::default(), &mut rng);
+
+        // Then
+        assert_eq!(3, intercept.len());
+        assert_eq!(3, coeffs.len());
+        for class in 0..3 {
+            let best_feature = (0..3)
+                .max_by(|i, j| coeffs[class][*i].partial_cmp(&coeffs[class][*j]).unwrap())
+                .unwrap();
+            assert_eq!(class, best_feature);
+        }
+    }
+
+    #[test]
+    fn fit_logistic_regression_should_fit_single_regression_for_two_classes() {
+        // Given
+        let features = vec![vec![1., 0.], vec![0., 1.]];
+        let labels = vec![0, 1];
+        let mut rng = seeded_rng(Some(1));
+
+        // When
+        let (intercept, coeffs) =
+            fit_logistic_regression(&features, &labels, 2, &SgdConfig::default(), &mut rng);
+
+        // Then
+        assert_eq!(1, intercept.len());
+        assert!(coeffs[0][1] > coeffs[0][0]);
+    }
+
+    #[test]
+    fn config_should_deserialize_from_python_config() {
+        // Given
+        let data = r#"{
+            "data_augmentation_config": {
+                "add_builtin_entities_examples": true,
+                "min_utterances": 20,
+                "noise_factor": 5,
+                "unknown_word_prob": 0,
+                "unknown_words_replacement_string": null
+            },
+            "featurizer_config": {
+                "pvalue_threshold": 0.4,
+                "sublinear_tf": false,
+                "word_clusters_name": null
+            },
+            "random_seed": null,
+            "unit_name": "log_reg_intent_classifier"
+        }"#;
+
+        // When
+        let config: LogRegIntentClassifierConfig = serde_json::from_str(data).unwrap();
+
+        // Then
+        assert_eq!(LogRegIntentClassifierConfig::default(), config);
+    }
+
+    #[test]
+    fn fit_should_produce_loadable_model() {
+        // Given
+        let resources_path = file_path("tests")
+            .join("models")
+            .join("trained_engine")
+            .join("resources");
+        let shared_resources = load_shared_resources(resources_path, Language::EN).unwrap();
+        let config = LogRegIntentClassifierConfig {
+            random_seed: Some(42),
+            ..Default::default()
+        };
+        let trainer = LogRegIntentClassifierTrainer::new(config);
+
+        // When
+        let model = trainer.fit(&sample_dataset(), shared_resources.clone()).unwrap();
+        let intent_classifier = LogRegIntentClassifier::new(model, shared_resources).unwrap();
+        let coffee_intent = intent_classifier.get_intent("make me a coffee", None).unwrap();
+        let tea_intent = intent_classifier.get_intent("i want some tea", None).unwrap();
+
+        // Then
+        assert_eq!(Some("MakeCoffee".to_string()), coffee_intent.map(|res| res.intent_name));
+        assert_eq!(Some("MakeTea".to_string()), tea_intent.map(|res| res.intent_name));
+    }
+
+    #[test]
+    fn fit_should_predict_none_intent_for_out_of_domain_text() {
+        // Given
+        let resources_path = file_path("tests")
+            .join("models")
+            .join("trained_engine")
+            .join("resources");
+        let shared_resources = load_shared_resources(resources_path, Language::EN).unwrap();
+        let config = LogRegIntentClassifierConfig {
+            random_seed: Some(42),
+            ..Default::default()
+        };
+        let trainer = LogRegIntentClassifierTrainer::new(config);
+
+        // When
+        let model = trainer.fit(&sample_dataset(), shared_resources.clone()).unwrap();
+        let intent_classifier = LogRegIntentClassifier::new(model, shared_resources).unwrap();
+        let intents = intent_classifier
+            .get_intents("what will the weather be like in paris tomorrow")
+            .unwrap();
+
+        // Then
+        assert_eq!(None, intents[0].intent_name);
+    }
+
+    #[test]
+    fn fit_should_not_fit_regression_for_single_class() {
+        // Given
+        let mut dataset = sample_dataset();
+        dataset.intents.remove("MakeTea");
+        let config = LogRegIntentClassifierConfig {
+            data_augmentation_config: IntentClassifierDataAugmentationConfig {
+                noise_factor: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let trainer = LogRegIntentClassifierTrainer::new(config);
+
+        // When
+        let model = trainer
+            .fit(&dataset, Arc::new(SharedResources::new(Language::EN)))
+            .unwrap();
+
+        // Then
+        assert_eq!(vec![Some("MakeCoffee".to_string())], model.intent_list);
+        assert!(model.featurizer.is_none());
+    }
+}
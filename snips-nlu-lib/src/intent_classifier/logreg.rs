@@ -1,4 +1,7 @@
+use std::mem;
+
 use errors::*;
+use memory::HeapSize;
 use ndarray::prelude::*;
 
 /// The multiclass probability estimates are derived from binary (one-vs.-rest)
@@ -37,6 +40,19 @@ impl MulticlassLogisticRegression {
         })
     }
 
+    /// Weights of the features in the score of the class, excluding the intercept
+    pub fn features_weights(&self, class_index: usize) -> Vec<f32> {
+        // Binary models only hold the weights of the second class
+        let sign = if self.is_binary() && class_index == 0 { -1.0 } else { 1.0 };
+        let column = if self.is_binary() { 0 } else { class_index };
+        self.weights
+            .column(column)
+            .iter()
+            .skip(1)
+            .map(|weight| sign * weight)
+            .collect()
+    }
+
     pub fn run(
         &self,
         features: &ArrayView1<f32>,
@@ -64,6 +80,12 @@ impl MulticlassLogisticRegression {
     }
 }
 
+impl HeapSize for MulticlassLogisticRegression {
+    fn heap_size(&self) -> usize {
+        self.weights.len() * mem::size_of::<f32>()
+    }
+}
+
 fn logit(x: f32) -> f32 {
     1. / (1. + (-x).exp())
 }
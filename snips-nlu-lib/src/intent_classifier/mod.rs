@@ -1,38 +1,78 @@
 mod featurizer;
+mod featurizer_training;
 mod log_reg_intent_classifier;
+mod log_reg_training;
 mod logreg;
 
 use std::collections::HashSet;
-use std::fs::File;
 use std::path::Path;
+use std::sync::Arc;
 
 use errors::*;
+use explanation::FeatureContribution;
 use failure::ResultExt;
-use serde_json;
+use memory::HeapSize;
 use snips_nlu_ontology::IntentClassifierResult;
 
 pub use self::featurizer::Featurizer;
+pub use self::featurizer_training::FeaturizerConfig;
 pub use self::log_reg_intent_classifier::LogRegIntentClassifier;
+pub use self::log_reg_training::{persist_intent_classifier,
+                                 IntentClassifierDataAugmentationConfig,
+                                 LogRegIntentClassifierConfig, LogRegIntentClassifierTrainer,
+                                 SgdConfig};
 use models::ProcessingUnitMetadata;
-use utils::FromPath;
+use resources::SharedResources;
+use utils::{FromPath, IntentName};
+use vfs::FileSystem;
 
-pub trait IntentClassifier: FromPath + Send + Sync {
+/// Probability of an intent, where a `None` intent name corresponds to the None intent
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntentProbability {
+    pub intent_name: Option<IntentName>,
+    pub probability: f32,
+}
+
+pub trait IntentClassifier: FromPath + HeapSize + Send + Sync {
     fn get_intent(
         &self,
         input: &str,
         intents_filter: Option<&HashSet<String>>,
     ) -> Result<Option<IntentClassifierResult>>;
+
+    fn get_top_intents(
+        &self,
+        input: &str,
+        intents_filter: Option<&HashSet<String>>,
+        top_k: usize,
+    ) -> Result<Vec<IntentClassifierResult>>;
+
+    fn get_intents(&self, input: &str) -> Result<Vec<IntentProbability>>;
+
+    /// Returns the `top_k` features which contributed the most to the probability of the intent,
+    /// sorted by decreasing contribution
+    fn explain_intent(
+        &self,
+        input: &str,
+        intent_name: &str,
+        top_k: usize,
+    ) -> Result<Vec<FeatureContribution>>;
 }
 
-pub fn build_intent_classifier<P: AsRef<Path>>(path: P) -> Result<Box<IntentClassifier>> {
+pub fn build_intent_classifier<P: AsRef<Path>>(
+    file_system: &FileSystem,
+    path: P,
+    shared_resources: Arc<SharedResources>,
+) -> Result<Box<IntentClassifier>> {
     let metadata_path = path.as_ref().join("metadata.json");
-    let metadata_file = File::open(&metadata_path)
+    let metadata_file = file_system.open(&metadata_path)
         .with_context(|_| format!("Cannot open intent classifier metadata file '{:?}'",
                                   &metadata_path))?;
-    let metadata: ProcessingUnitMetadata = serde_json::from_reader(metadata_file)
-        .with_context(|_| "Cannot deserialize intent classifier json data")?;
+    let metadata = ProcessingUnitMetadata::from_reader(metadata_file)
+        .with_context(|_| SnipsNluError::InvalidJson(metadata_path.to_string_lossy().into_owned()))?;
     match metadata {
-        ProcessingUnitMetadata::LogRegIntentClassifier => Ok(Box::new(LogRegIntentClassifier::from_path(path)?) as _),
-        _ => Err(format_err!("{:?} is not an intent classifier", metadata))
+        ProcessingUnitMetadata::LogRegIntentClassifier => Ok(Box::new(LogRegIntentClassifier::from_vfs(file_system, path, shared_resources)?) as _),
+        _ => Err(SnipsNluError::WrongProcessingUnit(format!("{:?}", metadata), "an intent classifier").into())
     }
 }
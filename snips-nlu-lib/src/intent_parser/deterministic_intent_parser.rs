@@ -1,29 +1,36 @@
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::Range;
 use std::path::Path;
-use std::str::FromStr;
 use std::sync::Arc;
 
-use regex::{Regex, RegexBuilder};
+use arc_swap::ArcSwap;
+use regex::{self, Regex, RegexBuilder};
 use serde_json;
 
-use builtin_entity_parsing::{BuiltinEntityParserFactory, CachingBuiltinEntityParser};
+use builtin_entity_parsing::CachingBuiltinEntityParser;
 use errors::*;
+use explanation::{MatchedPattern, ParsingDetails, TaggedToken};
 use failure::ResultExt;
+use itertools::Itertools;
 use models::DeterministicParserModel;
 use intent_parser::{internal_parsing_result, IntentParser, InternalParsingResult};
-use language::FromLanguage;
+use language::{parse_language, FromLanguage};
+use memory::HeapSize;
 use nlu_utils::language::Language as NluUtilsLanguage;
 use nlu_utils::range::ranges_overlap;
 use nlu_utils::string::{convert_to_char_range, substring_with_char_range, suffix_from_char_index};
 use nlu_utils::token::{tokenize, tokenize_light};
+use resources::SharedResources;
 use slot_utils::*;
 use snips_nlu_ontology::Language;
-use utils::{EntityName, FromPath, IntentName, SlotName};
+use utils::{EntityName, FromPath, IntentName, PreparedUpdate, SlotName, SwapUpdate};
+use vfs::FileSystem;
+
+/// Pattern of the slots of an entity which has no value, and which must not match anything
+const NEVER_MATCHING_PATTERN: &str = r"\b\B";
 
 pub struct DeterministicIntentParser {
-    regexes_per_intent: HashMap<IntentName, Vec<Regex>>,
+    regexes_per_intent: ArcSwap<HashMap<IntentName, Vec<Regex>>>,
     group_names_to_slot_names: HashMap<String, SlotName>,
     slot_names_to_entities: HashMap<IntentName, HashMap<SlotName, EntityName>>,
     builtin_entity_parser: Arc<CachingBuiltinEntityParser>,
@@ -31,24 +38,33 @@ pub struct DeterministicIntentParser {
 }
 
 impl FromPath for DeterministicIntentParser {
-    fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+    fn from_vfs<P: AsRef<Path>>(
+        file_system: &FileSystem,
+        path: P,
+        shared_resources: Arc<SharedResources>,
+    ) -> Result<Self> {
         let parser_model_path = path.as_ref().join("intent_parser.json");
-        let model_file = File::open(&parser_model_path)
+        let model_file = file_system.open(&parser_model_path)
             .with_context(|_|
                 format!("Cannot open DeterministicIntentParser file '{:?}'",
                         &parser_model_path))?;
         let model: DeterministicParserModel = serde_json::from_reader(model_file)
-            .with_context(|_| "Cannot deserialize DeterministicIntentParser json data")?;
-        Self::new(model)
+            .with_context(|_|
+                SnipsNluError::InvalidJson(parser_model_path.to_string_lossy().into_owned()))?;
+        Self::new(model, shared_resources)
     }
 }
 
 impl DeterministicIntentParser {
-    pub fn new(configuration: DeterministicParserModel) -> Result<Self> {
-        let language = Language::from_str(&configuration.language_code)?;
-        let builtin_entity_parser = BuiltinEntityParserFactory::get(language);
+    pub fn new(
+        configuration: DeterministicParserModel,
+        shared_resources: Arc<SharedResources>,
+    ) -> Result<Self> {
+        let language = parse_language(&configuration.language_code)?;
+        let builtin_entity_parser = shared_resources.builtin_entity_parser.clone();
         Ok(DeterministicIntentParser {
-            regexes_per_intent: compile_regexes_per_intent(configuration.patterns)?,
+            regexes_per_intent: ArcSwap::from_pointee(
+                compile_regexes_per_intent(configuration.patterns)?),
             group_names_to_slot_names: configuration.group_names_to_slot_names,
             slot_names_to_entities: configuration.slot_names_to_entities,
             builtin_entity_parser,
@@ -63,6 +79,107 @@ impl IntentParser for DeterministicIntentParser {
         input: &str,
         intents: Option<&HashSet<IntentName>>,
     ) -> Result<Option<InternalParsingResult>> {
+        Ok(self.parse_with_pattern(input, intents).map(|(result, _)| result))
+    }
+
+    fn parse_with_alternatives(
+        &self,
+        input: &str,
+        intents: Option<&HashSet<IntentName>>,
+        top_k: usize,
+    ) -> Result<Vec<InternalParsingResult>> {
+        if top_k == 0 {
+            return Ok(vec![]);
+        }
+        // Patterns matching is deterministic, hence there is no alternative to the matched intent
+        Ok(self.parse(input, intents)?.into_iter().collect())
+    }
+
+    fn parse_explained(
+        &self,
+        input: &str,
+        intents: Option<&HashSet<IntentName>>,
+    ) -> Result<(Option<InternalParsingResult>, ParsingDetails)> {
+        if let Some((result, matched_pattern)) = self.parse_with_pattern(input, intents) {
+            let details = ParsingDetails {
+                matched_pattern: Some(matched_pattern),
+                ..ParsingDetails::default()
+            };
+            Ok((Some(result), details))
+        } else {
+            Ok((None, ParsingDetails::default()))
+        }
+    }
+
+    fn tag_tokens(&self, _input: &str, _intent: &str) -> Result<Option<Vec<TaggedToken>>> {
+        Ok(None)
+    }
+
+    fn prepare_entity_utterances<'a>(
+        &'a self,
+        entity: &str,
+        utterances: &[String],
+    ) -> Result<Box<PreparedUpdate + 'a>> {
+        let language = NluUtilsLanguage::from_language(self.language);
+        let mut entity_pattern =
+            get_custom_entity_pattern(utterances.iter().map(|utterance| &**utterance), language);
+        if entity_pattern.is_empty() {
+            entity_pattern = NEVER_MATCHING_PATTERN.to_string();
+        }
+        // The regexes are compiled on a copy of the current ones, which keep being used to parse
+        // until the update is committed
+        let mut regexes_per_intent = (*self.regexes_per_intent.load()).clone();
+        let mut updated_regexes = vec![];
+        for (intent, regexes) in regexes_per_intent.iter() {
+            let group_names = self.get_entity_group_names(intent, entity);
+            if group_names.is_empty() {
+                continue;
+            }
+            let intent_regexes = regexes
+                .iter()
+                .map(|regex| {
+                    let pattern = group_names.iter().fold(
+                        regex.as_str().to_string(),
+                        |pattern, group_name| {
+                            replace_group_pattern(&pattern, group_name, &entity_pattern)
+                        },
+                    );
+                    compile_regex(&pattern)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            updated_regexes.push((intent.clone(), intent_regexes));
+        }
+        regexes_per_intent.extend(updated_regexes);
+        Ok(Box::new(SwapUpdate::new(&self.regexes_per_intent, regexes_per_intent)))
+    }
+
+    fn memory_usage(&self) -> BTreeMap<String, usize> {
+        // The memory of the compiled regexes is not exposed, hence the size of their patterns
+        // which is a lower bound
+        let patterns_size = self.regexes_per_intent
+            .load()
+            .iter()
+            .map(|(intent, regexes)| {
+                intent.heap_size() + regexes.iter().map(|regex| regex.as_str().len()).sum::<usize>()
+            })
+            .sum();
+        let slot_mappings_size =
+            self.group_names_to_slot_names.heap_size() + self.slot_names_to_entities.heap_size();
+        vec![
+            ("patterns".to_string(), patterns_size),
+            ("slot_mappings".to_string(), slot_mappings_size),
+        ].into_iter()
+            .collect()
+    }
+}
+
+impl DeterministicIntentParser {
+    /// Returns the parsing result along with the pattern which matched the input
+    fn parse_with_pattern(
+        &self,
+        input: &str,
+        intents: Option<&HashSet<IntentName>>,
+    ) -> Option<(InternalParsingResult, MatchedPattern)> {
         let (ranges_mapping, formatted_input) =
             replace_builtin_entities(input, &*self.builtin_entity_parser);
         let language = NluUtilsLanguage::from_language(self.language);
@@ -70,14 +187,14 @@ impl IntentParser for DeterministicIntentParser {
         let cleaned_formatted_input =
             replace_tokenized_out_characters(&*formatted_input, language, ' ');
 
-        for (intent, regexes) in self.regexes_per_intent.iter() {
+        for (intent, regexes) in self.regexes_per_intent.load().iter() {
             if !intents
                 .map(|intent_set| intent_set.contains(intent))
                 .unwrap_or(true)
             {
                 continue;
             }
-            for regex in regexes {
+            for (pattern_index, regex) in regexes.iter().enumerate() {
                 let matching_result_formatted = self.get_matching_result(
                     input,
                     &*cleaned_formatted_input,
@@ -85,21 +202,44 @@ impl IntentParser for DeterministicIntentParser {
                     intent,
                     Some(&ranges_mapping),
                 );
-                if matching_result_formatted.is_some() {
-                    return Ok(matching_result_formatted);
+                if let Some(result) = matching_result_formatted {
+                    let pattern = MatchedPattern {
+                        intent: intent.to_string(),
+                        pattern_index,
+                        with_builtin_entities: true,
+                    };
+                    return Some((result, pattern));
                 }
                 let matching_result =
                     self.get_matching_result(input, &*cleaned_input, regex, intent, None);
-                if matching_result.is_some() {
-                    return Ok(matching_result);
+                if let Some(result) = matching_result {
+                    let pattern = MatchedPattern {
+                        intent: intent.to_string(),
+                        pattern_index,
+                        with_builtin_entities: false,
+                    };
+                    return Some((result, pattern));
                 }
             }
         }
-        Ok(None)
+        None
+    }
+
+    /// Names of the regex groups which capture the slots of the intent filled by the entity
+    fn get_entity_group_names(&self, intent: &str, entity: &str) -> Vec<&str> {
+        let slot_names_to_entities = match self.slot_names_to_entities.get(intent) {
+            Some(slot_names_to_entities) => slot_names_to_entities,
+            None => return vec![],
+        };
+        self.group_names_to_slot_names
+            .iter()
+            .filter(|&(_, slot_name)| {
+                slot_names_to_entities.get(slot_name).map(|e| &**e) == Some(entity)
+            })
+            .map(|(group_name, _)| &**group_name)
+            .collect()
     }
-}
 
-impl DeterministicIntentParser {
     fn get_matching_result(
         &self,
         input: &str,
@@ -149,6 +289,7 @@ impl DeterministicIntentParser {
                         char_range,
                         entity,
                         slot_name,
+                        score_upper_bound: Some(1.0),
                     }
                 })
                 .collect();
@@ -167,15 +308,63 @@ fn compile_regexes_per_intent(
     patterns
         .into_iter()
         .map(|(intent, patterns)| {
-            let regexes: Result<_> = patterns
-                .into_iter()
-                .map(|p| Ok(RegexBuilder::new(&p).case_insensitive(true).build()?))
-                .collect();
+            let regexes: Result<_> = patterns.iter().map(|p| compile_regex(p)).collect();
             Ok((intent, regexes?))
         })
         .collect()
 }
 
+fn compile_regex(pattern: &str) -> Result<Regex> {
+    Ok(RegexBuilder::new(pattern).case_insensitive(true).build()?)
+}
+
+/// Pattern matching any of the utterances of a custom entity, where the longest utterances come
+/// first so that they take precedence over their prefixes
+pub fn get_custom_entity_pattern<'a, I>(utterances: I, language: NluUtilsLanguage) -> String
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    utterances
+        .into_iter()
+        .filter(|utterance| !tokenize_light(utterance, language).is_empty())
+        .map(regex::escape)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .sorted_by(|a, b| b.chars().count().cmp(&a.chars().count()).then(a.cmp(b)))
+        .join("|")
+}
+
+/// Replaces the content of each occurrence of the named group in the pattern
+fn replace_group_pattern(pattern: &str, group_name: &str, group_pattern: &str) -> String {
+    let group_start = format!("(?P<{}>", group_name);
+    let mut replaced_pattern = String::with_capacity(pattern.len());
+    let mut remaining_pattern = pattern;
+    while let Some(start) = remaining_pattern.find(&*group_start) {
+        let content_start = start + group_start.len();
+        let content_end = content_start + find_group_end(&remaining_pattern[content_start..]);
+        replaced_pattern.push_str(&remaining_pattern[..content_start]);
+        replaced_pattern.push_str(group_pattern);
+        remaining_pattern = &remaining_pattern[content_end..];
+    }
+    replaced_pattern.push_str(remaining_pattern);
+    replaced_pattern
+}
+
+/// Byte index of the first closing parenthesis which is not escaped, the content of entity groups
+/// being made of escaped utterances only
+fn find_group_end(group_content: &str) -> usize {
+    let mut is_escaped = false;
+    for (index, c) in group_content.char_indices() {
+        match c {
+            _ if is_escaped => is_escaped = false,
+            '\\' => is_escaped = true,
+            ')' => return index,
+            _ => {}
+        }
+    }
+    group_content.len()
+}
+
 fn deduplicate_overlapping_slots(
     slots: Vec<InternalSlot>,
     language: Language,
@@ -244,7 +433,7 @@ fn replace_builtin_entities(
     (range_mapping, processed_text)
 }
 
-fn get_builtin_entity_name(entity_label: &str) -> String {
+pub fn get_builtin_entity_name(entity_label: &str) -> String {
     // Here we don't need language specific tokenization, we just want to generate a feature name, that's why we use EN
     let normalized_entity_label = tokenize_light(entity_label, NluUtilsLanguage::EN)
         .join("")
@@ -352,9 +541,10 @@ mod tests {
             .join("models")
             .join("trained_engine")
             .join("deterministic_intent_parser");
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
 
         // When
-        let intent_parser = DeterministicIntentParser::from_path(path).unwrap();
+        let intent_parser = DeterministicIntentParser::from_path(path, shared_resources).unwrap();
         let parsing_result = intent_parser.parse("make me two cups of coffee", None).unwrap();
 
         // Then
@@ -364,7 +554,8 @@ mod tests {
                 value: "two".to_string(),
                 char_range: 8..11,
                 entity: "snips/number".to_string(),
-                slot_name: "number_of_cups".to_string()
+                slot_name: "number_of_cups".to_string(),
+                score_upper_bound: Some(1.0),
             }
         ]);
         assert_eq!(expected_intent, parsing_result.as_ref().map(|res| &*res.intent.intent_name));
@@ -374,7 +565,8 @@ mod tests {
     #[test]
     fn should_get_intent() {
         // Given
-        let parser = DeterministicIntentParser::new(test_configuration()).unwrap();
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
+        let parser = DeterministicIntentParser::new(test_configuration(), shared_resources).unwrap();
         let text = "this is a dummy_a query with another dummy_c";
 
         // When
@@ -392,7 +584,8 @@ mod tests {
     #[test]
     fn should_get_intent_with_builtin_entity() {
         // Given
-        let parser = DeterministicIntentParser::new(test_configuration()).unwrap();
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
+        let parser = DeterministicIntentParser::new(test_configuration(), shared_resources).unwrap();
         let text = "Send 10 dollars to John";
 
         // When
@@ -410,7 +603,8 @@ mod tests {
     #[test]
     fn should_get_slots() {
         // Given
-        let parser = DeterministicIntentParser::new(test_configuration()).unwrap();
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
+        let parser = DeterministicIntentParser::new(test_configuration(), shared_resources).unwrap();
         let text = "this is a dummy_a query with another dummy_c";
 
         // When
@@ -423,12 +617,14 @@ mod tests {
                 char_range: 10..17,
                 entity: "dummy_entity_1".to_string(),
                 slot_name: "dummy_slot_name".to_string(),
+                score_upper_bound: Some(1.0),
             },
             InternalSlot {
                 value: "dummy_c".to_string(),
                 char_range: 37..44,
                 entity: "dummy_entity_2".to_string(),
                 slot_name: "dummy_slot_name2".to_string(),
+                score_upper_bound: Some(1.0),
             },
         ]);
         assert_eq!(slots, expected_slots);
@@ -437,7 +633,8 @@ mod tests {
     #[test]
     fn should_get_slots_with_non_ascii_chars() {
         // Given
-        let parser = DeterministicIntentParser::new(test_configuration()).unwrap();
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
+        let parser = DeterministicIntentParser::new(test_configuration(), shared_resources).unwrap();
         let text = "This is another über dummy_cc query!";
 
         // When
@@ -450,6 +647,7 @@ mod tests {
                 char_range: 21..29,
                 entity: "dummy_entity_2".to_string(),
                 slot_name: "dummy_slot_name2".to_string(),
+                score_upper_bound: Some(1.0),
             },
         ]);
         assert_eq!(slots, expected_slots);
@@ -458,7 +656,8 @@ mod tests {
     #[test]
     fn should_get_slots_with_builtin_entity() {
         // Given
-        let parser = DeterministicIntentParser::new(test_configuration()).unwrap();
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
+        let parser = DeterministicIntentParser::new(test_configuration(), shared_resources).unwrap();
         let text = "Send 10 dollars to John at dummy c";
 
         // When
@@ -471,12 +670,14 @@ mod tests {
                 char_range: 5..15,
                 entity: "snips/amountOfMoney".to_string(),
                 slot_name: "dummy_slot_name4".to_string(),
+                score_upper_bound: Some(1.0),
             },
             InternalSlot {
                 value: "dummy c".to_string(),
                 char_range: 27..34,
                 entity: "dummy_entity_2".to_string(),
                 slot_name: "dummy_slot_name2".to_string(),
+                score_upper_bound: Some(1.0),
             },
         ]);
         assert_eq!(slots, expected_slots);
@@ -485,7 +686,8 @@ mod tests {
     #[test]
     fn should_get_slots_with_special_tokenized_out_characters() {
         // Given
-        let parser = DeterministicIntentParser::new(test_configuration()).unwrap();
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
+        let parser = DeterministicIntentParser::new(test_configuration(), shared_resources).unwrap();
         let text = "this is another dummy’c";
 
         // When
@@ -498,6 +700,7 @@ mod tests {
                 char_range: 16..23,
                 entity: "dummy_entity_2".to_string(),
                 slot_name: "dummy_slot_name3".to_string(),
+                score_upper_bound: Some(1.0),
             },
         ]);
         assert_eq!(slots, expected_slots);
@@ -513,30 +716,35 @@ mod tests {
                 char_range: 3..7,
                 entity: "e".to_string(),
                 slot_name: "s1".to_string(),
+                score_upper_bound: None,
             },
             InternalSlot {
                 value: "aaaaaaa".to_string(),
                 char_range: 9..16,
                 entity: "e1".to_string(),
                 slot_name: "s2".to_string(),
+                score_upper_bound: None,
             },
             InternalSlot {
                 value: "bbbbbbbb".to_string(),
                 char_range: 10..18,
                 entity: "e1".to_string(),
                 slot_name: "s3".to_string(),
+                score_upper_bound: None,
             },
             InternalSlot {
                 value: "b cccc".to_string(),
                 char_range: 17..23,
                 entity: "e1".to_string(),
                 slot_name: "s4".to_string(),
+                score_upper_bound: None,
             },
             InternalSlot {
                 value: "non_overlapping2".to_string(),
                 char_range: 50..60,
                 entity: "e".to_string(),
                 slot_name: "s5".to_string(),
+                score_upper_bound: None,
             },
         ];
 
@@ -550,18 +758,21 @@ mod tests {
                 char_range: 3..7,
                 entity: "e".to_string(),
                 slot_name: "s1".to_string(),
+                score_upper_bound: None,
             },
             InternalSlot {
                 value: "b cccc".to_string(),
                 char_range: 17..23,
                 entity: "e1".to_string(),
                 slot_name: "s4".to_string(),
+                score_upper_bound: None,
             },
             InternalSlot {
                 value: "non_overlapping2".to_string(),
                 char_range: 50..60,
                 entity: "e".to_string(),
                 slot_name: "s5".to_string(),
+                score_upper_bound: None,
             },
         ];
         assert_eq!(deduplicated_slots, expected_slots);
@@ -571,10 +782,10 @@ mod tests {
     fn should_replace_builtin_entities() {
         // Given
         let text = "Meeting this evening or tomorrow at 11am !";
-        let parser = BuiltinEntityParserFactory::get(Language::EN);
+        let parser = CachingBuiltinEntityParser::new(Language::EN, 1000);
 
         // When
-        let (range_mapping, formatted_text) = replace_builtin_entities(text, &*parser);
+        let (range_mapping, formatted_text) = replace_builtin_entities(text, &parser);
 
         // Then
         let expected_mapping =
@@ -597,6 +808,58 @@ mod tests {
         assert_eq!("%SNIPSDATETIME%", &formatted_label)
     }
 
+    #[test]
+    fn should_prepare_entity_utterances() {
+        // Given
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
+        let parser = DeterministicIntentParser::new(test_configuration(), shared_resources).unwrap();
+        let utterances = vec!["dummy_z".to_string(), "dummy z".to_string()];
+        let parse_intent = |text| parser.parse(text, None).unwrap().map(|res| res.intent);
+
+        // When
+        let update = parser.prepare_entity_utterances("dummy_entity_1", &utterances).unwrap();
+        let intent_before_commit = parse_intent("this is a dummy_a query from another intent");
+        update.commit();
+
+        // Then
+        assert_eq!(
+            Some("dummy_intent_2"),
+            intent_before_commit.as_ref().map(|res| &*res.intent_name)
+        );
+        let intent = parse_intent("this is a dummy z query from another intent");
+        assert_eq!(Some("dummy_intent_2"), intent.as_ref().map(|res| &*res.intent_name));
+        assert!(parse_intent("this is a dummy_a query from another intent").is_none());
+        let intent = parse_intent("this is a dummy_z query with another dummy_c");
+        assert_eq!(Some("dummy_intent_1"), intent.as_ref().map(|res| &*res.intent_name));
+    }
+
+    #[test]
+    fn should_not_match_entity_without_utterances() {
+        // Given
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
+        let parser = DeterministicIntentParser::new(test_configuration(), shared_resources).unwrap();
+
+        // When
+        parser.prepare_entity_utterances("dummy_entity_1", &[]).unwrap().commit();
+
+        // Then
+        let result = parser.parse("this is a dummy_a query from another intent", None).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn replace_group_pattern_works() {
+        // Given
+        let pattern = r"^(?P<group_1>a\)|b) and (?P<group_10>c) or (?P<group_1>a\)|b)?$";
+
+        // When
+        let replaced_pattern = replace_group_pattern(pattern, "group_1", r"d\(|e");
+
+        // Then
+        let expected_pattern = r"^(?P<group_1>d\(|e) and (?P<group_10>c) or (?P<group_1>d\(|e)?$";
+        assert_eq!(expected_pattern, replaced_pattern);
+    }
+
     #[test]
     fn should_replace_tokenized_out_characters() {
         // Given
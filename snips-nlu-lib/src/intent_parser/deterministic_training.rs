@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use regex;
+use serde_json;
+
+use dataset::{Dataset, Entity, Utterance};
+use errors::*;
+use failure::ResultExt;
+use intent_parser::deterministic_intent_parser::{get_builtin_entity_name,
+                                                 get_custom_entity_pattern};
+use language::{parse_language, FromLanguage};
+use models::{DeterministicParserModel, ProcessingUnitMetadata};
+use nlu_utils::language::Language as NluUtilsLanguage;
+use nlu_utils::token::tokenize_light;
+use utils::{EntityName, SlotName};
+
+const WHITESPACE_PATTERN: &str = r"\s*";
+
+/// Configuration of the `DeterministicIntentParser` training
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct DeterministicIntentParserConfig {
+    /// Maximum number of utterances per intent which are converted to patterns
+    pub max_queries: usize,
+    /// Patterns longer than this are discarded
+    pub max_pattern_length: usize,
+}
+
+impl Default for DeterministicIntentParserConfig {
+    fn default() -> Self {
+        Self {
+            max_queries: 100,
+            max_pattern_length: 1000,
+        }
+    }
+}
+
+/// Builds the patterns of the `DeterministicIntentParser` from the utterances of a dataset
+pub struct DeterministicIntentParserTrainer {
+    config: DeterministicIntentParserConfig,
+}
+
+impl DeterministicIntentParserTrainer {
+    pub fn new(config: DeterministicIntentParserConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn fit(&self, dataset: &Dataset) -> Result<DeterministicParserModel> {
+        let language = NluUtilsLanguage::from_language(parse_language(&dataset.language)?);
+        let entity_patterns = get_entity_patterns(dataset, language);
+        let mut patterns = HashMap::new();
+        let mut group_names_to_slot_names = HashMap::new();
+        let mut slot_names_to_entities = HashMap::new();
+
+        for intent_name in dataset.intent_names() {
+            let utterances = &dataset.intents[&intent_name].utterances;
+            let mut intent_patterns = vec![];
+            for utterance in utterances.iter().take(self.config.max_queries) {
+                let pattern = utterance_to_pattern(
+                    utterance,
+                    &entity_patterns,
+                    &mut group_names_to_slot_names,
+                    language,
+                )?;
+                if pattern.len() <= self.config.max_pattern_length
+                    && !intent_patterns.contains(&pattern)
+                {
+                    intent_patterns.push(pattern);
+                }
+            }
+            let intent_slot_names_to_entities: HashMap<SlotName, EntityName> = utterances
+                .iter()
+                .flat_map(|utterance| utterance.data.iter())
+                .filter_map(|chunk| match (chunk.slot_name.as_ref(), chunk.entity.as_ref()) {
+                    (Some(slot_name), Some(entity)) => Some((slot_name.clone(), entity.clone())),
+                    _ => None,
+                })
+                .collect();
+            patterns.insert(intent_name.clone(), intent_patterns);
+            slot_names_to_entities.insert(intent_name, intent_slot_names_to_entities);
+        }
+
+        Ok(DeterministicParserModel {
+            language_code: dataset.language.clone(),
+            patterns,
+            group_names_to_slot_names,
+            slot_names_to_entities,
+        })
+    }
+}
+
+/// Writes the model in the directory layout expected by `DeterministicIntentParser::from_path`
+pub fn persist_deterministic_intent_parser<P: AsRef<Path>>(
+    model: &DeterministicParserModel,
+    path: P,
+) -> Result<()> {
+    let path = path.as_ref();
+    fs::create_dir_all(path)
+        .with_context(|_| format!("Cannot create intent parser directory {:?}", path))?;
+    let model_file = fs::File::create(path.join("intent_parser.json"))?;
+    serde_json::to_writer(model_file, model)?;
+    let metadata_file = fs::File::create(path.join("metadata.json"))?;
+    serde_json::to_writer(metadata_file, &ProcessingUnitMetadata::DeterministicIntentParser)?;
+    Ok(())
+}
+
+/// Returns, for each entity, the regex pattern matching its values.
+///
+/// Builtin entities are matched through the placeholder which replaces them in the parsed input,
+/// while custom entities are matched by any of their values, longest first.
+fn get_entity_patterns(
+    dataset: &Dataset,
+    language: NluUtilsLanguage,
+) -> HashMap<EntityName, String> {
+    dataset
+        .entities
+        .iter()
+        .map(|(entity_name, entity)| {
+            let pattern = match entity {
+                Entity::Builtin(_) => get_builtin_entity_name(entity_name),
+                Entity::Custom(custom_entity) =>
+                    get_custom_entity_pattern(custom_entity.utterances(), language),
+            };
+            (entity_name.clone(), pattern)
+        })
+        .collect()
+}
+
+fn utterance_to_pattern(
+    utterance: &Utterance,
+    entity_patterns: &HashMap<EntityName, String>,
+    group_names_to_slot_names: &mut HashMap<String, SlotName>,
+    language: NluUtilsLanguage,
+) -> Result<String> {
+    let mut pattern_parts = vec![];
+    for chunk in &utterance.data {
+        match (chunk.slot_name.as_ref(), chunk.entity.as_ref()) {
+            (Some(slot_name), Some(entity)) => {
+                let entity_pattern = entity_patterns
+                    .get(entity)
+                    .ok_or_else(|| SnipsNluError::UnknownEntity(entity.clone()))?;
+                let group_name = format!("group_{}", group_names_to_slot_names.len());
+                pattern_parts.push(format!("(?P<{}>{})", group_name, entity_pattern));
+                group_names_to_slot_names.insert(group_name, slot_name.clone());
+            }
+            _ => pattern_parts.extend(
+                tokenize_light(&chunk.text, language)
+                    .iter()
+                    .map(|token| regex::escape(token)),
+            ),
+        }
+    }
+    Ok(format!(
+        "^{}{}{}$",
+        WHITESPACE_PATTERN,
+        pattern_parts.join(WHITESPACE_PATTERN),
+        WHITESPACE_PATTERN
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intent_parser::{DeterministicIntentParser, IntentParser};
+    use itertools::Itertools;
+    use resources::SharedResources;
+    use snips_nlu_ontology::Language;
+    use std::sync::Arc;
+
+    fn sample_dataset() -> Dataset {
+        serde_json::from_str(r#"{
+            "language": "en",
+            "intents": {
+                "MakeTea": {
+                    "utterances": [
+                        {
+                            "data": [
+                                {"text": "make me "},
+                                {"text": "two", "entity": "snips/number", "slot_name": "cups"},
+                                {"text": " cups of "},
+                                {"text": "hot", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " tea"}
+                            ]
+                        },
+                        {
+                            "data": [
+                                {"text": "make me "},
+                                {"text": "three", "entity": "snips/number", "slot_name": "cups"},
+                                {"text": " cups of "},
+                                {"text": "cold", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " tea"}
+                            ]
+                        },
+                        {
+                            "data": [
+                                {"text": "i want "},
+                                {"text": "hot", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " tea"}
+                            ]
+                        }
+                    ]
+                }
+            },
+            "entities": {
+                "temperature": {
+                    "data": [
+                        {"value": "hot", "synonyms": []},
+                        {"value": "cold", "synonyms": ["iced", "very cold"]}
+                    ],
+                    "use_synonyms": true,
+                    "automatically_extensible": false
+                },
+                "snips/number": {}
+            }
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn fit_should_generate_patterns() {
+        // Given
+        let dataset = sample_dataset();
+        let trainer = DeterministicIntentParserTrainer::new(Default::default());
+
+        // When
+        let model = trainer.fit(&dataset).unwrap();
+
+        // Then
+        let expected_patterns = vec![
+            concat!(r"^\s*make\s*me\s*(?P<group_0>%SNIPSNUMBER%)\s*cups\s*of\s*",
+                    r"(?P<group_1>very cold|cold|iced|hot)\s*tea\s*$").to_string(),
+            concat!(r"^\s*make\s*me\s*(?P<group_2>%SNIPSNUMBER%)\s*cups\s*of\s*",
+                    r"(?P<group_3>very cold|cold|iced|hot)\s*tea\s*$").to_string(),
+            r"^\s*i\s*want\s*(?P<group_4>very cold|cold|iced|hot)\s*tea\s*$".to_string(),
+        ];
+        let expected_group_names_to_slot_names = hashmap![
+            "group_0".to_string() => "cups".to_string(),
+            "group_1".to_string() => "temp".to_string(),
+            "group_2".to_string() => "cups".to_string(),
+            "group_3".to_string() => "temp".to_string(),
+            "group_4".to_string() => "temp".to_string()
+        ];
+        let expected_slot_names_to_entities = hashmap![
+            "MakeTea".to_string() => hashmap![
+                "cups".to_string() => "snips/number".to_string(),
+                "temp".to_string() => "temperature".to_string()
+            ]
+        ];
+        assert_eq!(expected_patterns, model.patterns["MakeTea"]);
+        assert_eq!(expected_group_names_to_slot_names, model.group_names_to_slot_names);
+        assert_eq!(expected_slot_names_to_entities, model.slot_names_to_entities);
+    }
+
+    #[test]
+    fn fit_should_discard_long_patterns() {
+        // Given
+        let dataset = sample_dataset();
+        let config = DeterministicIntentParserConfig {
+            max_queries: 100,
+            max_pattern_length: 20,
+        };
+
+        // When
+        let model = DeterministicIntentParserTrainer::new(config).fit(&dataset).unwrap();
+
+        // Then
+        assert!(model.patterns["MakeTea"].is_empty());
+    }
+
+    #[test]
+    fn fit_should_keep_patterns_of_max_length() {
+        // Given
+        let dataset = sample_dataset();
+        let config = DeterministicIntentParserConfig {
+            max_queries: 100,
+            max_pattern_length: 61,
+        };
+
+        // When
+        let model = DeterministicIntentParserTrainer::new(config).fit(&dataset).unwrap();
+
+        // Then
+        let expected_patterns =
+            vec![r"^\s*i\s*want\s*(?P<group_4>very cold|cold|iced|hot)\s*tea\s*$".to_string()];
+        assert_eq!(expected_patterns, model.patterns["MakeTea"]);
+    }
+
+    #[test]
+    fn fit_should_fail_on_unknown_entity() {
+        // Given
+        let mut dataset = sample_dataset();
+        dataset.entities.remove("temperature");
+        let trainer = DeterministicIntentParserTrainer::new(Default::default());
+
+        // When
+        let error = trainer.fit(&dataset).unwrap_err();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::UnknownEntity(entity)) => assert_eq!("temperature", entity),
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn generated_patterns_should_parse_custom_entities() {
+        // Given
+        let model = DeterministicIntentParserTrainer::new(Default::default())
+            .fit(&sample_dataset())
+            .unwrap();
+        let parser =
+            DeterministicIntentParser::new(model, Arc::new(SharedResources::new(Language::EN)))
+                .unwrap();
+
+        // When
+        let parsing_result = parser.parse("I want very cold tea", None).unwrap().unwrap();
+
+        // Then
+        assert_eq!("MakeTea", &parsing_result.intent.intent_name);
+        let slot_values = parsing_result.slots.into_iter().map(|slot| slot.value).collect_vec();
+        assert_eq!(vec!["very cold".to_string()], slot_values);
+    }
+}
@@ -1,17 +1,26 @@
 pub mod deterministic_intent_parser;
+mod deterministic_training;
 pub mod probabilistic_intent_parser;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::path::Path;
+use std::sync::Arc;
 
 use errors::*;
+use explanation::{ParsingDetails, TaggedToken};
+use intent_classifier::IntentProbability;
 use snips_nlu_ontology::IntentClassifierResult;
 
 use models::ProcessingUnitMetadata;
+use resources::SharedResources;
 pub use self::deterministic_intent_parser::DeterministicIntentParser;
+pub use self::deterministic_training::{persist_deterministic_intent_parser,
+                                       DeterministicIntentParserConfig,
+                                       DeterministicIntentParserTrainer};
 pub use self::probabilistic_intent_parser::ProbabilisticIntentParser;
 pub use slot_utils::InternalSlot;
-use utils::FromPath;
+use utils::{FromPath, PreparedUpdate};
+use vfs::FileSystem;
 
 pub struct InternalParsingResult {
     pub intent: IntentClassifierResult,
@@ -38,12 +47,53 @@ pub trait IntentParser: FromPath + Send + Sync {
         input: &str,
         intents: Option<&HashSet<String>>,
     ) -> Result<Option<InternalParsingResult>>;
+
+    fn parse_with_alternatives(
+        &self,
+        input: &str,
+        intents: Option<&HashSet<String>>,
+        top_k: usize,
+    ) -> Result<Vec<InternalParsingResult>>;
+
+    /// Returns the probabilities of all the intents, including the None intent, as computed by
+    /// the intent classifier of the parser, and `None` when the parser does not rely on one
+    fn get_intents(&self, _input: &str) -> Result<Option<Vec<IntentProbability>>> {
+        Ok(None)
+    }
+
+    /// Parses the input like `parse` does, and additionally reports how the result was obtained
+    fn parse_explained(
+        &self,
+        input: &str,
+        intents: Option<&HashSet<String>>,
+    ) -> Result<(Option<InternalParsingResult>, ParsingDetails)>;
+
+    /// Tags the tokens of the input with the slot filler of the intent, and returns `None` when
+    /// the parser does not rely on a slot filler
+    fn tag_tokens(&self, input: &str, intent: &str) -> Result<Option<Vec<TaggedToken>>>;
+
+    /// Prepares the replacement of the utterances of a custom entity which are matched by the
+    /// parser, without retraining it. The parser is left unchanged until the returned update is
+    /// committed.
+    fn prepare_entity_utterances<'a>(
+        &'a self,
+        entity: &str,
+        utterances: &[String],
+    ) -> Result<Box<PreparedUpdate + 'a>>;
+
+    /// Approximate memory used by each component of the parser, excluding the shared resources
+    fn memory_usage(&self) -> BTreeMap<String, usize>;
 }
 
-pub fn build_intent_parser<P: AsRef<Path>>(metadata: ProcessingUnitMetadata, path: P) -> Result<Box<IntentParser>> {
+pub fn build_intent_parser<P: AsRef<Path>>(
+    metadata: ProcessingUnitMetadata,
+    file_system: &FileSystem,
+    path: P,
+    shared_resources: Arc<SharedResources>,
+) -> Result<Box<IntentParser>> {
     match metadata {
-        ProcessingUnitMetadata::DeterministicIntentParser => Ok(Box::new(DeterministicIntentParser::from_path(path)?) as _),
-        ProcessingUnitMetadata::ProbabilisticIntentParser => Ok(Box::new(ProbabilisticIntentParser::from_path(path)?) as _),
-        _ => Err(format_err!("{:?} is not an intent parser", metadata))
+        ProcessingUnitMetadata::DeterministicIntentParser => Ok(Box::new(DeterministicIntentParser::from_vfs(file_system, path, shared_resources)?) as _),
+        ProcessingUnitMetadata::ProbabilisticIntentParser => Ok(Box::new(ProbabilisticIntentParser::from_vfs(file_system, path, shared_resources)?) as _),
+        _ => Err(SnipsNluError::WrongProcessingUnit(format!("{:?}", metadata), "an intent parser").into())
     }
 }
@@ -1,16 +1,23 @@
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::iter::FromIterator;
 use std::path::Path;
+use std::sync::Arc;
 
 use errors::*;
+use explanation::{ParsingDetails, TaggedToken};
 use failure::ResultExt;
-use intent_classifier::{build_intent_classifier, IntentClassifier};
+use intent_classifier::{build_intent_classifier, IntentClassifier, IntentProbability};
 use intent_parser::{IntentParser, InternalParsingResult};
 use models::ProbabilisticParserModel;
+use resources::SharedResources;
 use serde_json;
 use slot_filler::{build_slot_filler, SlotFiller};
-use utils::{FromPath, IntentName};
+use slot_utils::InternalSlot;
+use utils::{FromPath, IntentName, PreparedUpdate};
+use vfs::FileSystem;
+
+/// Number of features reported when explaining the classification of an intent
+const NB_EXPLAINING_FEATURES: usize = 10;
 
 pub struct ProbabilisticIntentParser {
     intent_classifier: Box<IntentClassifier>,
@@ -18,21 +25,31 @@ pub struct ProbabilisticIntentParser {
 }
 
 impl FromPath for ProbabilisticIntentParser {
-    fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+    fn from_vfs<P: AsRef<Path>>(
+        file_system: &FileSystem,
+        path: P,
+        shared_resources: Arc<SharedResources>,
+    ) -> Result<Self> {
         let parser_model_path = path.as_ref().join("intent_parser.json");
-        let model_file = File::open(&parser_model_path)
+        let model_file = file_system.open(&parser_model_path)
             .with_context(|_|
                 format!("Cannot open ProbabilisticIntentParser file '{:?}'",
                         &parser_model_path))?;
         let model: ProbabilisticParserModel = serde_json::from_reader(model_file)
-            .with_context(|_| "Cannot deserialize ProbabilisticIntentParser json data")?;
+            .with_context(|_|
+                SnipsNluError::InvalidJson(parser_model_path.to_string_lossy().into_owned()))?;
         let intent_classifier_path = path.as_ref().join("intent_classifier");
-        let intent_classifier = build_intent_classifier(intent_classifier_path)?;
+        let intent_classifier = build_intent_classifier(
+            file_system, intent_classifier_path, shared_resources.clone())?;
         let slot_fillers_vec: Result<Vec<_>> = model.slot_fillers.iter()
             .map(|metadata|
                 Ok((
                     metadata.intent.to_string(),
-                    build_slot_filler(path.as_ref().join(&metadata.slot_filler_name))?,
+                    build_slot_filler(
+                        file_system,
+                        path.as_ref().join(&metadata.slot_filler_name),
+                        shared_resources.clone(),
+                    )?,
                 ))
             )
             .collect();
@@ -49,15 +66,7 @@ impl IntentParser for ProbabilisticIntentParser {
     ) -> Result<Option<InternalParsingResult>> {
         let opt_intent_result = self.intent_classifier.get_intent(input, intents)?;
         if let Some(intent_result) = opt_intent_result {
-            let slots = self.slot_fillers
-                .get(&*intent_result.intent_name)
-                .ok_or_else(|| {
-                    format_err!(
-                        "intent {} not found in slot fillers",
-                        intent_result.intent_name
-                    )
-                })?
-                .get_slots(input)?;
+            let slots = self.get_slots(input, &*intent_result.intent_name)?;
             Ok(Some(InternalParsingResult {
                 intent: intent_result,
                 slots,
@@ -66,14 +75,107 @@ impl IntentParser for ProbabilisticIntentParser {
             Ok(None)
         }
     }
+
+    fn parse_with_alternatives(
+        &self,
+        input: &str,
+        intents: Option<&HashSet<IntentName>>,
+        top_k: usize,
+    ) -> Result<Vec<InternalParsingResult>> {
+        self.intent_classifier
+            .get_top_intents(input, intents, top_k)?
+            .into_iter()
+            .map(|intent_result| {
+                let slots = self.get_slots(input, &*intent_result.intent_name)?;
+                Ok(InternalParsingResult {
+                    intent: intent_result,
+                    slots,
+                })
+            })
+            .collect()
+    }
+
+    fn get_intents(&self, input: &str) -> Result<Option<Vec<IntentProbability>>> {
+        self.intent_classifier.get_intents(input).map(Some)
+    }
+
+    fn parse_explained(
+        &self,
+        input: &str,
+        intents: Option<&HashSet<IntentName>>,
+    ) -> Result<(Option<InternalParsingResult>, ParsingDetails)> {
+        let intent_result = match self.intent_classifier.get_intent(input, intents)? {
+            Some(intent_result) => intent_result,
+            None => return Ok((None, ParsingDetails::default())),
+        };
+        let top_features = self.intent_classifier.explain_intent(
+            input,
+            &*intent_result.intent_name,
+            NB_EXPLAINING_FEATURES,
+        )?;
+        let (slots, slot_filling) = self.get_slot_filler(&*intent_result.intent_name)?
+            .get_slots_explained(input)?;
+        let result = InternalParsingResult {
+            intent: intent_result,
+            slots,
+        };
+        let details = ParsingDetails {
+            matched_pattern: None,
+            top_features,
+            slot_filling: Some(slot_filling),
+        };
+        Ok((Some(result), details))
+    }
+
+    fn tag_tokens(&self, input: &str, intent: &str) -> Result<Option<Vec<TaggedToken>>> {
+        self.get_slot_filler(intent)?.tag_tokens(input).map(Some)
+    }
+
+    fn prepare_entity_utterances<'a>(
+        &'a self,
+        entity: &str,
+        utterances: &[String],
+    ) -> Result<Box<PreparedUpdate + 'a>> {
+        let updates = self.slot_fillers
+            .values()
+            .map(|slot_filler| slot_filler.prepare_entity_utterances(entity, utterances))
+            .collect::<Vec<_>>();
+        Ok(Box::new(updates))
+    }
+
+    fn memory_usage(&self) -> BTreeMap<String, usize> {
+        let slot_fillers_usage = self.slot_fillers
+            .iter()
+            .map(|(intent, slot_filler)| {
+                (format!("slot_fillers/{}", intent), slot_filler.heap_size())
+            });
+        Some(("intent_classifier".to_string(), self.intent_classifier.heap_size()))
+            .into_iter()
+            .chain(slot_fillers_usage)
+            .collect()
+    }
+}
+
+impl ProbabilisticIntentParser {
+    fn get_slots(&self, input: &str, intent_name: &str) -> Result<Vec<InternalSlot>> {
+        self.get_slot_filler(intent_name)?.get_slots(input)
+    }
+
+    fn get_slot_filler(&self, intent_name: &str) -> Result<&SlotFiller> {
+        Ok(self.slot_fillers
+            .get(intent_name)
+            .ok_or_else(|| SnipsNluError::UnknownIntent(intent_name.to_string()))?
+            .as_ref())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use utils::file_path;
-    use slot_utils::InternalSlot;
-    use resources::loading::load_resources;
+    use resources::loading::load_shared_resources;
+    use snips_nlu_ontology::Language;
+    use testutils::without_score_upper_bounds;
 
     #[test]
     fn from_path_works() {
@@ -86,10 +188,10 @@ mod tests {
             .join("probabilistic_intent_parser");
 
         let resources_path = trained_engine_path.join("resources");
-        load_resources(resources_path).unwrap();
+        let shared_resources = load_shared_resources(resources_path, Language::EN).unwrap();
 
         // When
-        let intent_parser = ProbabilisticIntentParser::from_path(parser_path).unwrap();
+        let intent_parser = ProbabilisticIntentParser::from_path(parser_path, shared_resources).unwrap();
         let parsing_result = intent_parser.parse("make me two cups of coffee", None).unwrap();
 
         // Then
@@ -100,9 +202,49 @@ mod tests {
                 char_range: 8..11,
                 entity: "snips/number".to_string(),
                 slot_name: "number_of_cups".to_string(),
+                score_upper_bound: None,
             }
         ]);
         assert_eq!(expected_intent, parsing_result.as_ref().map(|res| &*res.intent.intent_name));
-        assert_eq!(expected_slots, parsing_result.map(|res| res.slots));
+        assert_eq!(expected_slots, parsing_result.map(|res| without_score_upper_bounds(res.slots)));
+    }
+
+    #[test]
+    fn parse_with_alternatives_works() {
+        // Given
+        let trained_engine_path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+
+        let parser_path = trained_engine_path
+            .join("probabilistic_intent_parser");
+
+        let resources_path = trained_engine_path.join("resources");
+        let shared_resources = load_shared_resources(resources_path, Language::EN).unwrap();
+
+        let intent_parser = ProbabilisticIntentParser::from_path(parser_path, shared_resources).unwrap();
+
+        // When
+        let parsing_results = intent_parser
+            .parse_with_alternatives("make me two cups of coffee", None, 2)
+            .unwrap();
+
+        // Then
+        let intent_names: Vec<&str> = parsing_results
+            .iter()
+            .map(|res| &*res.intent.intent_name)
+            .collect();
+        assert_eq!(vec!["MakeCoffee", "MakeTea"], intent_names);
+        assert!(parsing_results[0].intent.probability >= parsing_results[1].intent.probability);
+        let expected_slots = vec![
+            InternalSlot {
+                value: "two".to_string(),
+                char_range: 8..11,
+                entity: "snips/number".to_string(),
+                slot_name: "number_of_cups".to_string(),
+                score_upper_bound: None,
+            }
+        ];
+        assert_eq!(expected_slots, without_score_upper_bounds(parsing_results[0].slots.clone()));
     }
 }
@@ -1,6 +1,15 @@
+use std::str::FromStr;
+
+use errors::*;
+use failure::ResultExt;
 use nlu_utils::language::Language as NluUtilsLanguage;
 use snips_nlu_ontology::Language;
 
+pub fn parse_language(language_code: &str) -> Result<Language> {
+    Ok(Language::from_str(language_code)
+        .with_context(|_| SnipsNluError::UnsupportedLanguage(language_code.to_string()))?)
+}
+
 pub trait FromLanguage {
     fn from_language(l: Language) -> Self;
 }
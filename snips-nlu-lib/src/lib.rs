@@ -1,5 +1,7 @@
+extern crate arc_swap;
 extern crate base64;
 extern crate crfsuite;
+extern crate crossbeam_queue;
 extern crate csv;
 #[cfg(test)]
 extern crate dinghy_test;
@@ -11,15 +13,17 @@ extern crate lazy_static;
 extern crate lru_cache;
 #[macro_use]
 extern crate ndarray;
+extern crate rand;
+extern crate rayon;
 extern crate regex;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+extern crate sha2;
 extern crate snips_nlu_ontology;
 extern crate snips_nlu_ontology_parsers;
 extern crate snips_nlu_utils as nlu_utils;
-extern crate tempfile;
 extern crate zip;
 
 #[cfg(test)]
@@ -27,11 +31,19 @@ extern crate zip;
 extern crate maplit;
 
 mod builtin_entity_parsing;
+mod data_augmentation;
+pub mod dataset;
+mod engine_handle;
+mod engine_registry;
+mod explanation;
+mod fuzzy_matching;
 mod models;
 pub mod errors;
+pub mod evaluation;
 mod intent_classifier;
 mod intent_parser;
 mod language;
+mod memory;
 mod nlu_engine;
 mod resources;
 mod slot_filler;
@@ -39,16 +51,36 @@ mod slot_utils;
 #[cfg(test)]
 mod testutils;
 mod utils;
+mod vfs;
 
 pub const MODEL_VERSION: &str = "0.16.0";
+/// Versions of the models which can be loaded, possibly after having been migrated
+pub const COMPATIBLE_MODEL_VERSIONS: &str = ">=0.15.0, <0.17.0";
 
 pub use models::*;
 pub use errors::*;
-pub use builtin_entity_parsing::BuiltinEntityParserFactory; // This is used by benches
-pub use intent_classifier::{IntentClassifier, LogRegIntentClassifier};
-pub use intent_parser::{DeterministicIntentParser, IntentParser, ProbabilisticIntentParser};
-pub use nlu_engine::SnipsNluEngine;
-pub use resources::loading::clear_resources; // This is used by benches
-pub use slot_filler::{CRFSlotFiller, SlotFiller};
+pub use dataset::Dataset;
+pub use engine_handle::EngineHandle;
+pub use engine_registry::{EngineMemoryUsage, EngineRegistry, ResourceMemoryUsage};
+pub use evaluation::{evaluate_engine, EvaluationReport};
+pub use explanation::{FeatureContribution, IntentParserExplanation, MatchedPattern,
+                      ParseExplanation, ParsingDetails, SlotFillingExplanation, TaggedToken};
+pub use intent_classifier::{persist_intent_classifier, FeaturizerConfig, IntentClassifier,
+                            IntentClassifierDataAugmentationConfig, IntentProbability,
+                            LogRegIntentClassifier, LogRegIntentClassifierConfig,
+                            LogRegIntentClassifierTrainer, SgdConfig};
+pub use intent_parser::{persist_deterministic_intent_parser, DeterministicIntentParser,
+                        DeterministicIntentParserConfig, DeterministicIntentParserTrainer,
+                        IntentParser, ProbabilisticIntentParser};
+pub use memory::MemoryUsage;
+pub use nlu_engine::{CustomEntityInfo, EngineConfig, EngineInfo, NluEngineResult, ResourcesInfo,
+                     SnipsNluEngine};
+pub use resources::SharedResources;
+pub use resources::loading::{load_shared_resources, load_shared_resources_from_vfs};
+pub use slot_filler::{CRFSlotFiller, CRFSlotFillerConfig, CRFSlotFillerTrainer, SlotFiller,
+                      SlotFillerDataAugmentationConfig};
+pub use slot_utils::{InternalSlot, ScoredSlot};
+pub use utils::PreparedUpdate;
+pub use vfs::{FileSystem, LocalFileSystem, MemoryFileSystem};
 pub use nlu_utils::token::{compute_all_ngrams, tokenize_light};
 
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::mem;
+
+/// Approximate number of bytes allocated on the heap by a value, which excludes the resources
+/// shared through an `Arc`
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+/// Memory used by each component of an engine, indexed by paths such as
+/// `probabilistic_intent_parser/slot_fillers/MakeTea`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MemoryUsage {
+    pub total_bytes: usize,
+    pub components: BTreeMap<String, usize>,
+}
+
+impl MemoryUsage {
+    pub fn new(components: BTreeMap<String, usize>) -> Self {
+        Self {
+            total_bytes: components.values().sum(),
+            components,
+        }
+    }
+}
+
+macro_rules! impl_heap_size_for_primitives {
+    ($($primitive:ty),*) => {
+        $(
+            impl HeapSize for $primitive {
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_heap_size_for_primitives!(bool, u8, i32, u32, usize, f32, f64);
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map(|value| value.heap_size()).unwrap_or(0)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>()
+            + self.iter().map(|value| value.heap_size()).sum::<usize>()
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+impl<K: HeapSize + Eq + Hash, V: HeapSize> HeapSize for HashMap<K, V> {
+    fn heap_size(&self) -> usize {
+        // Each bucket stores a hash along with the entry
+        self.capacity() * (mem::size_of::<u64>() + mem::size_of::<(K, V)>())
+            + self
+                .iter()
+                .map(|(key, value)| key.heap_size() + value.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl<T: HeapSize + Eq + Hash> HeapSize for HashSet<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * (mem::size_of::<u64>() + mem::size_of::<T>())
+            + self.iter().map(|value| value.heap_size()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heap_size_should_include_nested_values() {
+        // Given
+        let mut values: Vec<String> = Vec::with_capacity(4);
+        values.push(String::with_capacity(10));
+        values.push("abc".to_string());
+
+        // When
+        let heap_size = values.heap_size();
+
+        // Then
+        assert_eq!(4 * mem::size_of::<String>() + 10 + 3, heap_size);
+    }
+}
@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 
+use errors::*;
+use models::migration::{insert_missing, ModelMigration};
+use models::ModelVersion;
+use serde_json::{Map, Value};
 use utils::IntentName;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct IntentClassifierModel {
     pub featurizer: Option<FeaturizerModel>,
     pub intercept: Option<Vec<f32>>,
@@ -10,7 +14,25 @@ pub struct IntentClassifierModel {
     pub intent_list: Vec<Option<IntentName>>,
 }
 
-#[derive(Debug, Deserialize)]
+impl ModelMigration for IntentClassifierModel {
+    const COMPONENT: &'static str = "intent classifier";
+
+    fn migrate(mut model: Value, version: &ModelVersion) -> Result<Value> {
+        // Featurizers prior to 0.16.0 did not map entity utterances to features
+        if *version < ModelVersion::new(0, 16, 0) {
+            if let Some(featurizer) = model.get_mut("featurizer").filter(|f| !f.is_null()) {
+                insert_missing(
+                    featurizer,
+                    "entity_utterances_to_feature_names",
+                    Value::Object(Map::new()),
+                )?;
+            }
+        }
+        Ok(model)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct FeaturizerModel {
     pub language_code: String,
     pub tfidf_vectorizer: TfIdfVectorizerModel,
@@ -19,13 +41,13 @@ pub struct FeaturizerModel {
     pub entity_utterances_to_feature_names: HashMap<String, Vec<String>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FeaturizerConfiguration {
     pub sublinear_tf: bool,
     pub word_clusters_name: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct TfIdfVectorizerModel {
     pub idf_diag: Vec<f32>,
     pub vocab: HashMap<String, usize>,
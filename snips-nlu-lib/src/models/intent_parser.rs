@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use utils::{EntityName, IntentName, SlotName};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct DeterministicParserModel {
     pub language_code: String,
     pub patterns: HashMap<IntentName, Vec<String>>,
@@ -0,0 +1,112 @@
+use std::io::Read;
+use std::path::Path;
+
+use errors::*;
+use failure::ResultExt;
+use models::ModelVersion;
+use serde::de::DeserializeOwned;
+use serde_json;
+use serde_json::Value;
+
+/// Model which can be upgraded from the format of older, but compatible, model versions
+pub trait ModelMigration: DeserializeOwned {
+    /// Name of the component described by the model, used to report incompatibilities
+    const COMPONENT: &'static str;
+
+    /// Upgrades the json model, declared with the provided version, to the current format,
+    /// leaving up-to-date models unchanged
+    fn migrate(model: Value, _version: &ModelVersion) -> Result<Value> {
+        Ok(model)
+    }
+}
+
+/// Deserializes the model located at `path` after having applied its migration shim, and reports
+/// the component along with its path as `SnipsNluError::IncompatibleModel` when the model cannot
+/// be upgraded to the current format
+pub fn load_model<M, R, P>(reader: R, path: P, version: &ModelVersion) -> Result<M>
+where
+    M: ModelMigration,
+    R: Read,
+    P: AsRef<Path>,
+{
+    let incompatible_model = |reason: String| SnipsNluError::IncompatibleModel(
+        M::COMPONENT,
+        path.as_ref().to_string_lossy().into_owned(),
+        reason,
+    );
+    let model: Value = serde_json::from_reader(reader)?;
+    let model = M::migrate(model, version)
+        .with_context(|e| incompatible_model(e.to_string()))?;
+    Ok(serde_json::from_value(model)
+        .with_context(|e| incompatible_model(e.to_string()))?)
+}
+
+/// Inserts `default` under `key` in `model` when the key is missing
+pub fn insert_missing(model: &mut Value, key: &str, default: Value) -> Result<()> {
+    model
+        .as_object_mut()
+        .ok_or_else(|| format_err!("expected a json object"))?
+        .entry(key)
+        .or_insert(default);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::{IntentClassifierModel, SlotFillerModel};
+
+    const SLOT_FILLER_WITHOUT_TAGGING_SCHEME: &str = r#"{
+        "language_code": "en",
+        "intent": "MakeTea",
+        "slot_name_mapping": {},
+        "crf_model_file": null,
+        "config": {"feature_factory_configs": []}
+    }"#;
+
+    #[test]
+    fn load_model_should_migrate_older_slot_filler_models() {
+        // Given
+        let data = SLOT_FILLER_WITHOUT_TAGGING_SCHEME.as_bytes();
+        let version = ModelVersion::new(0, 15, 0);
+
+        // When
+        let model: SlotFillerModel = load_model(data, "slot_filler.json", &version).unwrap();
+
+        // Then
+        assert_eq!(1, model.config.tagging_scheme);
+    }
+
+    #[test]
+    fn load_model_should_not_migrate_up_to_date_models() {
+        // Given
+        let data = SLOT_FILLER_WITHOUT_TAGGING_SCHEME.as_bytes();
+        let version = ModelVersion::new(0, 16, 0);
+
+        // When
+        let result = load_model::<SlotFillerModel, _, _>(data, "slot_filler.json", &version);
+
+        // Then
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn load_model_should_report_incompatible_component() {
+        // Given
+        let data: &[u8] = r#"{"intercept": null, "coeffs": null}"#.as_ref();
+        let path = "probabilistic_intent_parser/intent_classifier.json";
+
+        // When
+        let error = load_model::<IntentClassifierModel, _, _>(data, path, &ModelVersion::current())
+            .unwrap_err();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::IncompatibleModel(component, model_path, _)) => {
+                assert_eq!("intent classifier", *component);
+                assert_eq!(path, model_path);
+            }
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+}
@@ -1,12 +1,12 @@
 use std::collections::HashMap;
 
+use errors::*;
+use memory::HeapSize;
+use models::migration::{insert_missing, ModelMigration};
+use models::ModelVersion;
+use serde_json::Value;
 use utils::{EntityName, IntentName, SlotName};
 
-#[derive(Debug, Deserialize)]
-pub struct ModelVersion {
-    pub model_version: String,
-}
-
 #[derive(Debug, Deserialize)]
 pub struct NluEngineModel {
     pub dataset_metadata: DatasetMetadata,
@@ -15,7 +15,19 @@ pub struct NluEngineModel {
     pub training_package_version: String,
 }
 
-#[derive(Debug, Deserialize)]
+impl ModelMigration for NluEngineModel {
+    const COMPONENT: &'static str = "nlu engine";
+
+    fn migrate(mut model: Value, version: &ModelVersion) -> Result<Value> {
+        // Models prior to 0.16.0 did not record the version of the training package
+        if *version < ModelVersion::new(0, 16, 0) {
+            insert_missing(&mut model, "training_package_version", Value::from("unknown"))?;
+        }
+        Ok(model)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct DatasetMetadata {
     pub language_code: String,
     pub entities: HashMap<String, Entity>,
@@ -27,3 +39,17 @@ pub struct Entity {
     pub automatically_extensible: bool,
     pub utterances: HashMap<String, String>,
 }
+
+impl HeapSize for DatasetMetadata {
+    fn heap_size(&self) -> usize {
+        self.language_code.heap_size()
+            + self.entities.heap_size()
+            + self.slot_name_mappings.heap_size()
+    }
+}
+
+impl HeapSize for Entity {
+    fn heap_size(&self) -> usize {
+        self.utterances.heap_size()
+    }
+}
@@ -1,4 +1,9 @@
-#[derive(Debug, Deserialize, Copy, Clone, PartialEq, Eq)]
+use std::io::Read;
+
+use errors::*;
+use serde_json;
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
 #[serde(tag = "unit_name")]
 #[serde(rename_all = "snake_case")]
 pub enum ProcessingUnitMetadata {
@@ -8,6 +13,22 @@ pub enum ProcessingUnitMetadata {
     LogRegIntentClassifier,
 }
 
+impl ProcessingUnitMetadata {
+    /// Deserializes the metadata of a processing unit, reporting the units which are not known by
+    /// this library as `SnipsNluError::UnknownProcessingUnit`
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        let unit_name = value
+            .get("unit_name")
+            .and_then(|unit_name| unit_name.as_str())
+            .map(|unit_name| unit_name.to_string());
+        serde_json::from_value(value).or_else(|error| match unit_name {
+            Some(unit_name) => Err(SnipsNluError::UnknownProcessingUnit(unit_name).into()),
+            None => Err(error.into()),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -21,4 +42,20 @@ mod tests {
         let metadata: ProcessingUnitMetadata = serde_json::from_str(data).unwrap();
         assert_eq!(ProcessingUnitMetadata::CrfSlotFiller, metadata);
     }
+
+    #[test]
+    fn from_reader_should_report_unknown_unit() {
+        // Given
+        let data: &[u8] = r#"{"unit_name": "lookup_intent_parser"}"#.as_ref();
+
+        // When
+        let error = ProcessingUnitMetadata::from_reader(data).unwrap_err();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::UnknownProcessingUnit(unit_name)) =>
+                assert_eq!("lookup_intent_parser", unit_name),
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
 }
@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 
+use errors::*;
+use models::migration::{insert_missing, ModelMigration};
+use models::ModelVersion;
+use serde_json::Value;
 use utils::{EntityName, IntentName, SlotName};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct SlotFillerModel {
     pub language_code: String,
     pub intent: IntentName,
@@ -11,13 +15,51 @@ pub struct SlotFillerModel {
     pub config: SlotFillerConfiguration,
 }
 
-#[derive(Debug, Deserialize)]
+impl ModelMigration for SlotFillerModel {
+    const COMPONENT: &'static str = "slot filler";
+
+    fn migrate(mut model: Value, version: &ModelVersion) -> Result<Value> {
+        // Models prior to 0.16.0 were always trained with the BIO tagging scheme, which was
+        // therefore not persisted
+        if *version < ModelVersion::new(0, 16, 0) {
+            if let Some(config) = model.get_mut("config") {
+                insert_missing(config, "tagging_scheme", Value::from(1))?;
+            }
+        }
+        Ok(model)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct SlotFillerConfiguration {
     pub tagging_scheme: u8,
     pub feature_factory_configs: Vec<FeatureFactory>,
+    #[serde(default)]
+    pub crf_args: CrfArgs,
+}
+
+/// Arguments of the crfsuite trainer
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CrfArgs {
+    pub algorithm: String,
+    /// Parameters of the training algorithm, such as the `c1` and `c2` regularization factors
+    #[serde(flatten)]
+    pub parameters: HashMap<String, Value>,
+}
+
+impl Default for CrfArgs {
+    fn default() -> Self {
+        Self {
+            algorithm: "lbfgs".to_string(),
+            parameters: vec![
+                ("c1".to_string(), Value::from(0.1)),
+                ("c2".to_string(), Value::from(0.1)),
+            ].into_iter().collect(),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct FeatureFactory {
     pub factory_name: String,
     pub offsets: Vec<i32>,
@@ -0,0 +1,149 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use errors::*;
+
+/// Semver-style version of a trained model, of the form `major.minor.patch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModelVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl ModelVersion {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Version of the models which are loaded without any migration
+    pub fn current() -> Self {
+        ::MODEL_VERSION.parse().unwrap()
+    }
+}
+
+impl FromStr for ModelVersion {
+    type Err = ::failure::Error;
+
+    fn from_str(version: &str) -> Result<Self> {
+        let numbers = version
+            .trim()
+            .split('.')
+            .map(|number| number.parse::<u64>())
+            .collect::<::std::result::Result<Vec<_>, _>>()
+            .map_err(|_| SnipsNluError::InvalidModel(
+                format!("invalid model version '{}'", version)))?;
+        if numbers.len() != 3 {
+            return Err(SnipsNluError::InvalidModel(
+                format!("invalid model version '{}'", version)).into());
+        }
+        Ok(Self::new(numbers[0], numbers[1], numbers[2]))
+    }
+}
+
+impl fmt::Display for ModelVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl PartialOrd for ModelVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ModelVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch).cmp(&(other.major, other.minor, other.patch))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparator {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+}
+
+/// Range of model versions, declared as a comma separated list of requirements such as
+/// `">=0.15.0, <0.17.0"`, which must all be satisfied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelVersionRange {
+    requirements: Vec<(Comparator, ModelVersion)>,
+}
+
+impl ModelVersionRange {
+    pub fn contains(&self, version: &ModelVersion) -> bool {
+        self.requirements
+            .iter()
+            .all(|&(comparator, ref bound)| match comparator {
+                Comparator::Greater => version > bound,
+                Comparator::GreaterOrEqual => version >= bound,
+                Comparator::Less => version < bound,
+                Comparator::LessOrEqual => version <= bound,
+                Comparator::Equal => version == bound,
+            })
+    }
+}
+
+impl FromStr for ModelVersionRange {
+    type Err = ::failure::Error;
+
+    fn from_str(range: &str) -> Result<Self> {
+        let requirements = range
+            .split(',')
+            .map(|requirement| {
+                let requirement = requirement.trim();
+                let (comparator, version) = if requirement.starts_with(">=") {
+                    (Comparator::GreaterOrEqual, &requirement[2..])
+                } else if requirement.starts_with("<=") {
+                    (Comparator::LessOrEqual, &requirement[2..])
+                } else if requirement.starts_with('>') {
+                    (Comparator::Greater, &requirement[1..])
+                } else if requirement.starts_with('<') {
+                    (Comparator::Less, &requirement[1..])
+                } else if requirement.starts_with('=') {
+                    (Comparator::Equal, &requirement[1..])
+                } else {
+                    (Comparator::Equal, requirement)
+                };
+                Ok((comparator, version.parse()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { requirements })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_model_version() {
+        // When
+        let version: ModelVersion = "0.16.2".parse().unwrap();
+
+        // Then
+        assert_eq!(ModelVersion::new(0, 16, 2), version);
+        assert_eq!("0.16.2", &version.to_string());
+        assert!("0.16".parse::<ModelVersion>().is_err());
+        assert!("0.16.x".parse::<ModelVersion>().is_err());
+    }
+
+    #[test]
+    fn range_should_contain_compatible_versions() {
+        // Given
+        let range: ModelVersionRange = ">=0.15.0, <0.17.0".parse().unwrap();
+
+        // When / Then
+        assert!(range.contains(&ModelVersion::new(0, 15, 0)));
+        assert!(range.contains(&ModelVersion::new(0, 16, 3)));
+        assert!(!range.contains(&ModelVersion::new(0, 14, 9)));
+        assert!(!range.contains(&ModelVersion::new(0, 17, 0)));
+        assert!(!range.contains(&ModelVersion::new(1, 16, 0)));
+    }
+}
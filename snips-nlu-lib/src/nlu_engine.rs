@@ -1,50 +1,175 @@
-use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io;
 use std::iter::FromIterator;
-use std::path::{Component, Path};
-use std::str::FromStr;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
+use arc_swap::ArcSwap;
 use itertools::Itertools;
 
-use builtin_entity_parsing::{BuiltinEntityParserFactory, CachingBuiltinEntityParser};
+use builtin_entity_parsing::CachingBuiltinEntityParser;
+use dataset::EntityValue;
 use errors::*;
+use explanation::{IntentParserExplanation, ParseExplanation, TaggedToken};
 use failure::ResultExt;
+use fuzzy_matching::{FuzzyEntityMatcher, FuzzyMatch};
+use intent_classifier::IntentProbability;
 use intent_parser::*;
-use language::FromLanguage;
-use models::{DatasetMetadata, Entity, NluEngineModel, ModelVersion, ProcessingUnitMetadata};
+use language::{parse_language, FromLanguage};
+use memory::{HeapSize, MemoryUsage};
+use models::{DatasetMetadata, Entity, ModelVersion, ModelVersionRange, NluEngineModel,
+             ProcessingUnitMetadata};
+use models::migration::load_model;
 use nlu_utils::language::Language as NluUtilsLanguage;
 use nlu_utils::string::{normalize, substring_with_char_range};
 use nlu_utils::token::{compute_all_ngrams, tokenize};
-use resources::loading::load_resources;
-use serde_json;
-use slot_utils::resolve_slots;
-use snips_nlu_ontology::{BuiltinEntityKind, IntentParserResult, Language, Slot, SlotValue};
-use tempfile;
-use utils::{EntityName, IntentName, SlotName};
-use zip::ZipArchive;
+use rayon::prelude::*;
+use resources::SharedResources;
+use resources::loading::load_engine_resources_from_vfs;
+use slot_utils::{group_coordinated_slots, resolve_slots, split_coordinated_slots, ScoredSlot};
+use snips_nlu_ontology::{BuiltinEntityKind, IntentClassifierResult, IntentParserResult, Language,
+                         Slot, SlotValue};
+use utils::{AtomicF32, EntityName, IntentName, PreparedUpdate, SlotName};
+use vfs::{FileSystem, LocalFileSystem, MemoryFileSystem};
 
 pub struct SnipsNluEngine {
-    dataset_metadata: DatasetMetadata,
+    model_version: String,
+    training_package_version: String,
+    metadata: ArcSwap<EngineMetadata>,
+    /// Held while the values of an entity are updated, so that concurrent updates do not
+    /// overwrite each other. Parsing never takes it.
+    entities_update: Mutex<()>,
+    language: Language,
+    parser_names: Vec<String>,
     parsers: Vec<Box<IntentParser>>,
-    builtin_entity_parser: Arc<CachingBuiltinEntityParser>,
+    shared_resources: Arc<SharedResources>,
+    intent_probability_threshold: AtomicF32,
+    /// NaN when fuzzy matching is disabled
+    fuzzy_matching_threshold: AtomicF32,
+}
+
+/// Dataset metadata of the engine along with the fuzzy matching index of its custom entities,
+/// which are replaced together when entity values are added or removed
+struct EngineMetadata {
+    dataset_metadata: DatasetMetadata,
+    fuzzy_matcher: FuzzyEntityMatcher,
+}
+
+/// Runtime configuration of the `SnipsNluEngine`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineConfig {
+    /// Minimum probability that an intent must reach in order to be returned, below which the
+    /// parsing result is considered as the None intent
+    pub intent_probability_threshold: f32,
+    /// Minimum similarity, between 0 and 1, with which a custom slot value that does not exactly
+    /// match any utterance of its entity is resolved to the closest one. Fuzzy matching is
+    /// disabled when `None`.
+    pub fuzzy_matching_threshold: Option<f32>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            intent_probability_threshold: 0.0,
+            fuzzy_matching_threshold: None,
+        }
+    }
+}
+
+/// Description of the model and resources of a loaded `SnipsNluEngine`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EngineInfo {
+    pub model_version: String,
+    pub training_package_version: String,
+    pub language: String,
+    /// Intent parsers, in the order in which they are tried
+    pub intent_parsers: Vec<String>,
+    /// Slots of each intent, along with their entities
+    pub intents: BTreeMap<IntentName, BTreeMap<SlotName, EntityName>>,
+    pub custom_entities: BTreeMap<EntityName, CustomEntityInfo>,
+    pub builtin_entities: Vec<EntityName>,
+    pub resources: ResourcesInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CustomEntityInfo {
+    pub automatically_extensible: bool,
+    /// Number of utterances, including synonyms and their normalized variants, which resolve to
+    /// a value of the entity
+    pub nb_utterances: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourcesInfo {
+    pub stemmer: bool,
+    pub gazetteers: Vec<String>,
+    pub word_clusters: Vec<String>,
+}
+
+/// Parsing result of the `SnipsNluEngine`, which also reports the intent which has been rejected
+/// because its probability was lower than the configured threshold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NluEngineResult {
+    pub input: String,
+    pub intent: Option<IntentClassifierResult>,
+    /// Slots in which coordinated values, such as "milk" and "eggs" in "add milk and eggs", are
+    /// grouped. This is the only result holding such groups: the `IntentParserResult` it is
+    /// converted to, and hence `parse` and the C structs, return each value as a separate slot.
+    pub slots: Option<Vec<ScoredSlot>>,
+    pub rejected_intent: Option<IntentClassifierResult>,
+}
+
+impl NluEngineResult {
+    fn empty(input: &str) -> Self {
+        NluEngineResult {
+            input: input.to_string(),
+            intent: None,
+            slots: None,
+            rejected_intent: None,
+        }
+    }
+}
+
+impl From<NluEngineResult> for IntentParserResult {
+    fn from(result: NluEngineResult) -> Self {
+        IntentParserResult {
+            input: result.input,
+            intent: result.intent,
+            // The coordinated values of a slot are returned as separate slots
+            slots: result.slots.map(|slots| {
+                slots
+                    .iter()
+                    .flat_map(|slot| slot.individual_values())
+                    .map(|slot| slot.clone().into())
+                    .collect()
+            }),
+        }
+    }
 }
 
 impl SnipsNluEngine {
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_vfs(&LocalFileSystem, path)
+    }
+
+    /// Loads the engine located at `path` within the provided file system
+    pub fn from_vfs<P: AsRef<Path>>(file_system: &FileSystem, path: P) -> Result<Self> {
         let engine_model_path = path.as_ref().join("nlu_engine.json");
-        Self::check_model_version(&engine_model_path)
+        let model_version = Self::check_model_version(file_system, &engine_model_path)
             .with_context(|_|
                 SnipsNluError::ModelLoad(engine_model_path.to_str().unwrap().to_string()))?;
 
-        let resources_path = path.as_ref().join("resources");
-        load_resources(resources_path)?;
-
-        let model_file = fs::File::open(&engine_model_path)
+        let model_file = file_system.open(&engine_model_path)
             .with_context(|_| format!("Could not open nlu engine file {:?}", &engine_model_path))?;
-        let model: NluEngineModel = serde_json::from_reader(model_file)
-            .with_context(|_| "Could not deserialize nlu engine json file")?;
+        let model: NluEngineModel = load_model(model_file, &engine_model_path, &model_version)
+            .with_context(|_|
+                SnipsNluError::InvalidJson(engine_model_path.to_string_lossy().into_owned()))?;
+
+        let language = parse_language(&model.dataset_metadata.language_code)?;
+        let resources_path = path.as_ref().join("resources");
+        let shared_resources =
+            load_engine_resources_from_vfs(file_system, resources_path, language, model_version)?;
 
         let parsers = model
             .intent_parsers
@@ -52,136 +177,502 @@ impl SnipsNluEngine {
             .map(|parser_name| {
                 let parser_path = path.as_ref().join(parser_name);
                 let metadata_path = parser_path.join("metadata.json");
-                let metadata_file = fs::File::open(metadata_path)
+                let metadata_file = file_system.open(&metadata_path)
                     .with_context(|_|
                         format!("Could not open metadata file of parser '{}'", parser_name))?;
-                let metadata: ProcessingUnitMetadata = serde_json::from_reader(metadata_file)
+                let metadata = ProcessingUnitMetadata::from_reader(metadata_file)
                     .with_context(|_|
-                        format!("Could not deserialize json metadata of parser '{}'", parser_name))?;
-                Ok(build_intent_parser(metadata, parser_path)? as _)
+                        SnipsNluError::InvalidJson(metadata_path.to_string_lossy().into_owned()))?;
+                Ok(build_intent_parser(
+                    metadata, file_system, parser_path, shared_resources.clone())? as _)
             })
             .collect::<Result<Vec<_>>>()?;
 
-        let language = Language::from_str(&model.dataset_metadata.language_code)?;
-        let builtin_entity_parser = BuiltinEntityParserFactory::get(language);
+        let fuzzy_matcher = FuzzyEntityMatcher::new(
+            &model.dataset_metadata.entities,
+            shared_resources.stemmer.clone(),
+            language,
+        );
 
         Ok(SnipsNluEngine {
-            dataset_metadata: model.dataset_metadata,
+            model_version: model.model_version,
+            training_package_version: model.training_package_version,
+            metadata: ArcSwap::from_pointee(EngineMetadata {
+                dataset_metadata: model.dataset_metadata,
+                fuzzy_matcher,
+            }),
+            entities_update: Mutex::new(()),
+            language,
+            parser_names: model.intent_parsers,
             parsers,
-            builtin_entity_parser,
+            shared_resources,
+            intent_probability_threshold: AtomicF32::new(
+                EngineConfig::default().intent_probability_threshold),
+            fuzzy_matching_threshold: AtomicF32::new(::std::f32::NAN),
         })
     }
 
-    fn check_model_version<P: AsRef<Path>>(path: P) -> Result<()> {
-        let model_file = fs::File::open(&path)?;
+    /// Returns the version declared by the model when it is compatible
+    fn check_model_version<P: AsRef<Path>>(
+        file_system: &FileSystem,
+        path: P,
+    ) -> Result<ModelVersion> {
+        #[derive(Deserialize)]
+        struct ModelVersionHeader {
+            model_version: String,
+        }
 
-        let model_version: ModelVersion = ::serde_json::from_reader(model_file)?;
-        if model_version.model_version != ::MODEL_VERSION {
-            bail!(SnipsNluError::WrongModelVersion(
-                model_version.model_version,
-                ::MODEL_VERSION
-            ));
+        let model_file = file_system.open(path.as_ref())?;
+        let header: ModelVersionHeader = ::serde_json::from_reader(model_file)?;
+        let model_version: ModelVersion = header.model_version.parse()?;
+        let compatible_versions: ModelVersionRange = ::COMPATIBLE_MODEL_VERSIONS.parse()?;
+        if !compatible_versions.contains(&model_version) {
+            return Err(SnipsNluError::WrongModelVersion(
+                header.model_version,
+                ::COMPATIBLE_MODEL_VERSIONS
+            ).into());
         }
-        Ok(())
+        Ok(model_version)
     }
 }
 
 impl SnipsNluEngine {
+    /// Loads the engine from a zip archive, whose content is read in memory without being
+    /// extracted on disk
     pub fn from_zip<R: io::Read + io::Seek>(reader: R) -> Result<Self> {
-        let mut archive = ZipArchive::new(reader)
+        let file_system = MemoryFileSystem::from_zip(reader)
             .with_context(|_| "Could not read nlu engine zip data")?;
-        let temp_dir = tempfile::Builder::new()
-            .prefix("temp_dir_nlu_")
-            .tempdir()?;
-        let temp_dir_path = temp_dir.path();
+        let engine_dir_path = file_system
+            .root_entries()
+            .into_iter()
+            .find(|entry| file_system.exists(&entry.join("nlu_engine.json")))
+            .ok_or_else(|| SnipsNluError::MissingFile("nlu_engine.json".to_string()))?;
 
-        for file_index in 0..archive.len() {
-            let mut file = archive.by_index(file_index)?;
-            let outpath = temp_dir_path.join(file.sanitized_name());
+        Self::from_vfs(&file_system, engine_dir_path)
+    }
+}
 
-            if (&*file.name()).ends_with('/') || (&*file.name()).ends_with('\\') {
-                fs::create_dir_all(&outpath)?;
+impl SnipsNluEngine {
+    pub fn info(&self) -> EngineInfo {
+        let metadata = self.metadata.load();
+        let dataset_metadata = &metadata.dataset_metadata;
+        let intents: BTreeMap<IntentName, BTreeMap<SlotName, EntityName>> = dataset_metadata
+            .slot_name_mappings
+            .iter()
+            .map(|(intent, mapping)| (intent.clone(), mapping.clone().into_iter().collect()))
+            .collect();
+        let custom_entities = dataset_metadata
+            .entities
+            .iter()
+            .map(|(entity_name, entity)| {
+                let entity_info = CustomEntityInfo {
+                    automatically_extensible: entity.automatically_extensible,
+                    nb_utterances: entity.utterances.len(),
+                };
+                (entity_name.clone(), entity_info)
+            })
+            .collect();
+        let builtin_entities = intents
+            .values()
+            .flat_map(|mapping| mapping.values())
+            .filter(|entity| BuiltinEntityKind::from_identifier(entity).is_ok())
+            .unique()
+            .sorted()
+            .into_iter()
+            .cloned()
+            .collect();
+        let resources = ResourcesInfo {
+            stemmer: self.shared_resources.stemmer.is_some(),
+            gazetteers: self.shared_resources.gazetteers.keys().cloned().sorted(),
+            word_clusters: self.shared_resources.word_clusterers.keys().cloned().sorted(),
+        };
+        EngineInfo {
+            model_version: self.model_version.clone(),
+            training_package_version: self.training_package_version.clone(),
+            language: dataset_metadata.language_code.clone(),
+            intent_parsers: self.parser_names.clone(),
+            intents,
+            custom_entities,
+            builtin_entities,
+            resources,
+        }
+    }
+
+    /// Reports the approximate memory used by each component of the engine, including the
+    /// resources which may be shared with other engines
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut components = BTreeMap::new();
+        let metadata = self.metadata.load();
+        components.insert("dataset_metadata".to_string(), metadata.dataset_metadata.heap_size());
+        components.insert("fuzzy_matcher".to_string(), metadata.fuzzy_matcher.heap_size());
+        for (parser_name, parser) in self.parser_names.iter().zip(self.parsers.iter()) {
+            for (component, bytes) in parser.memory_usage() {
+                components.insert(format!("{}/{}", parser_name, component), bytes);
+            }
+        }
+        for (resource, bytes) in self.shared_resources.memory_usage() {
+            components.insert(format!("resources/{}", resource), bytes);
+        }
+        MemoryUsage::new(components)
+    }
+
+    /// Resources of the engine, which are shared with the other engines having loaded identical
+    /// resources
+    pub fn shared_resources(&self) -> Arc<SharedResources> {
+        self.shared_resources.clone()
+    }
+
+    pub fn config(&self) -> EngineConfig {
+        let fuzzy_matching_threshold = self.fuzzy_matching_threshold.load();
+        EngineConfig {
+            intent_probability_threshold: self.intent_probability_threshold.load(),
+            fuzzy_matching_threshold: if fuzzy_matching_threshold.is_nan() {
+                None
             } else {
-                if let Some(p) = outpath.parent() {
-                    if !p.exists() {
-                        fs::create_dir_all(&p)?;
-                    }
+                Some(fuzzy_matching_threshold)
+            },
+        }
+    }
+
+    /// Updates the configuration, which can be done while other threads are parsing
+    pub fn set_config(&self, config: EngineConfig) {
+        self.intent_probability_threshold.store(config.intent_probability_threshold);
+        self.fuzzy_matching_threshold
+            .store(config.fuzzy_matching_threshold.unwrap_or(::std::f32::NAN));
+    }
+}
+
+impl SnipsNluEngine {
+    /// Adds values to a custom entity, which are then matched and resolved without retraining the
+    /// engine.
+    ///
+    /// The values only live in this engine: they are not persisted, and an engine loaded from the
+    /// same model, for instance by `EngineHandle::reload_from_path`, does not have them.
+    pub fn add_entity_values(&self, entity: &str, values: &[EntityValue]) -> Result<()> {
+        self.update_entity(entity, |custom_entity| {
+            for entity_value in values {
+                let utterances =
+                    Some(&entity_value.value).into_iter().chain(&entity_value.synonyms);
+                for utterance in utterances {
+                    custom_entity
+                        .utterances
+                        .insert(utterance.to_string(), entity_value.value.to_string());
+                    custom_entity
+                        .utterances
+                        .insert(normalize(utterance), entity_value.value.to_string());
                 }
-                let mut outfile = fs::File::create(&outpath).unwrap();
-                io::copy(&mut file, &mut outfile)?;
             }
-        }
+        })
+    }
 
-        let first_archive_file = archive
-            .by_index(0)?
-            .sanitized_name();
+    /// Removes values from a custom entity, along with their synonyms, with the same lifetime as
+    /// the values added by `add_entity_values`
+    pub fn remove_entity_values(&self, entity: &str, values: &[String]) -> Result<()> {
+        self.update_entity(entity, |custom_entity| {
+            custom_entity
+                .utterances
+                .retain(|_, resolved_value| !values.contains(resolved_value))
+        })
+    }
 
-        let engine_dir_path = first_archive_file
-            .components()
-            .find(|component| if let Component::Normal(_) = component { true } else { false })
-            .ok_or_else(|| format_err!("Trained engine archive is incorrect"))?
-            .as_os_str();
+    /// Builds the updated metadata, fuzzy matching index and parsers of the entity while parsing
+    /// goes on with the current ones, and then publishes them, so that a failure leaves the engine
+    /// unchanged
+    fn update_entity<F>(&self, entity: &str, update: F) -> Result<()>
+    where
+        F: FnOnce(&mut Entity),
+    {
+        let _update_guard = self.entities_update.lock().unwrap();
+        let current_metadata = self.metadata.load();
+        let mut custom_entity = current_metadata
+            .dataset_metadata
+            .entities
+            .get(entity)
+            .cloned()
+            .ok_or_else(|| SnipsNluError::UnknownEntity(entity.to_string()))?;
+        update(&mut custom_entity);
 
-        let engine_dir_name = engine_dir_path
-            .to_str()
-            .ok_or_else(|| format_err!("Engine directory name is empty"))?;
+        let utterances = custom_entity.utterances.keys().cloned().sorted();
+        let parser_updates = self.parsers
+            .iter()
+            .map(|parser| parser.prepare_entity_utterances(entity, &utterances))
+            .collect::<Result<Vec<_>>>()?;
+        let mut fuzzy_matcher = current_metadata.fuzzy_matcher.clone();
+        fuzzy_matcher.index_entity(entity, &custom_entity);
+        let mut dataset_metadata = current_metadata.dataset_metadata.clone();
+        dataset_metadata.entities.insert(entity.to_string(), custom_entity);
 
-        Ok(SnipsNluEngine::from_path(temp_dir_path.join(engine_dir_name))?)
+        Box::new(parser_updates).commit();
+        self.metadata.store(Arc::new(EngineMetadata {
+            dataset_metadata,
+            fuzzy_matcher,
+        }));
+        Ok(())
     }
 }
 
 impl SnipsNluEngine {
+    /// Parses the input into an intent and its slots
+    ///
+    /// The score upper bounds and fuzzy matching scores of the slots are only reported by
+    /// `parse_detailed`, as the slots of an `IntentParserResult` have no room for them.
     pub fn parse(
         &self,
         input: &str,
         intents_filter: Option<&[IntentName]>,
     ) -> Result<IntentParserResult> {
-        if self.parsers.is_empty() {
-            return Ok(IntentParserResult {
-                input: input.to_string(),
-                intent: None,
-                slots: None,
-            });
+        Ok(self.parse_detailed(input, intents_filter)?.into())
+    }
+
+    /// Parses several inputs in parallel on the rayon thread pool, and returns the results in
+    /// the same order as the inputs
+    pub fn parse_batch(
+        &self,
+        inputs: &[&str],
+        intents_filter: Option<&[IntentName]>,
+    ) -> Result<Vec<IntentParserResult>> {
+        inputs
+            .par_iter()
+            .map(|input| self.parse(input, intents_filter))
+            .collect()
+    }
+
+    /// Same as `parse_batch`, but returns the results of `parse_detailed`
+    pub fn parse_detailed_batch(
+        &self,
+        inputs: &[&str],
+        intents_filter: Option<&[IntentName]>,
+    ) -> Result<Vec<NluEngineResult>> {
+        inputs
+            .par_iter()
+            .map(|input| self.parse_detailed(input, intents_filter))
+            .collect()
+    }
+
+    /// Parses the input like `parse` does, and additionally reports the intent which has been
+    /// rejected when its probability is lower than the configured intent probability threshold,
+    /// along with the score upper bound of each slot and the match score of the slots which have
+    /// been resolved by fuzzy matching
+    pub fn parse_detailed(
+        &self,
+        input: &str,
+        intents_filter: Option<&[IntentName]>,
+    ) -> Result<NluEngineResult> {
+        let (result, _) = self.parse_with(input, intents_filter, |_, parser, intents| {
+            parser.parse(input, intents)
+        })?;
+        Ok(result)
+    }
+
+    /// Parses the input like `parse_detailed` does, and additionally reports which intent parsers
+    /// have been tried and how the answering one came up with its result
+    pub fn parse_explained(
+        &self,
+        input: &str,
+        intents_filter: Option<&[IntentName]>,
+    ) -> Result<ParseExplanation> {
+        let mut intent_parsers = Vec::with_capacity(self.parsers.len());
+        let mut parsed_slots = vec![];
+        let (result, answering_parser_index) =
+            self.parse_with(input, intents_filter, |index, parser, intents| {
+                let (opt_internal_parsing_result, details) =
+                    parser.parse_explained(input, intents)?;
+                intent_parsers.push(IntentParserExplanation {
+                    parser_name: self.parser_names[index].clone(),
+                    intent: opt_internal_parsing_result
+                        .as_ref()
+                        .map(|result| result.intent.clone()),
+                    details,
+                });
+                if let Some(ref internal_parsing_result) = opt_internal_parsing_result {
+                    parsed_slots = internal_parsing_result.slots.clone();
+                }
+                Ok(opt_internal_parsing_result)
+            })?;
+        let unresolved_slots = if result.rejected_intent.is_some() {
+            vec![]
+        } else {
+            get_unresolved_slots(parsed_slots, &result)
+        };
+        Ok(ParseExplanation {
+            result,
+            intent_parsers,
+            answering_parser_index,
+            unresolved_slots,
+        })
+    }
+
+    /// Tries the intent parsers in order, with the given parsing function, until one of them
+    /// finds an intent, and then either rejects this intent or resolves the slots found along
+    /// with it. The index of the answering parser is returned along with the result.
+    fn parse_with<F>(
+        &self,
+        input: &str,
+        intents_filter: Option<&[IntentName]>,
+        mut parse: F,
+    ) -> Result<(NluEngineResult, Option<usize>)>
+    where
+        F: FnMut(usize, &IntentParser, Option<&HashSet<IntentName>>)
+            -> Result<Option<InternalParsingResult>>,
+    {
+        let set_intents = get_intents_set(intents_filter);
+
+        for (index, parser) in self.parsers.iter().enumerate() {
+            if let Some(internal_parsing_result) = parse(index, &**parser, set_intents.as_ref())? {
+                if self.is_rejected(&internal_parsing_result.intent) {
+                    let result = NluEngineResult {
+                        rejected_intent: Some(internal_parsing_result.intent),
+                        ..NluEngineResult::empty(input)
+                    };
+                    return Ok((result, Some(index)));
+                }
+                let result = self.resolve_parsing_result(input, internal_parsing_result);
+                return Ok((result, Some(index)));
+            }
         }
-        let set_intents: Option<HashSet<IntentName>> = intents_filter
-            .map(|intent_list| HashSet::from_iter(intent_list.iter().map(|name| name.to_string())));
+        Ok((NluEngineResult::empty(input), None))
+    }
 
+    /// Tags the tokens of the input with the slot filler of the intent, regardless of the intent
+    /// which would be found when parsing the input, in order to debug the slot filling
+    pub fn tag_tokens(&self, input: &str, intent: &str) -> Result<Vec<TaggedToken>> {
+        if !self.metadata.load().dataset_metadata.slot_name_mappings.contains_key(intent) {
+            return Err(SnipsNluError::UnknownIntent(intent.to_string()).into());
+        }
         for parser in &self.parsers {
-            let opt_internal_parsing_result = parser.parse(input, set_intents.as_ref())?;
-            if let Some(internal_parsing_result) = opt_internal_parsing_result {
-                let filter_entity_kinds = self.dataset_metadata
-                    .slot_name_mappings
-                    .values()
-                    .flat_map::<Vec<_>, _>(|intent_mapping: &HashMap<SlotName, EntityName>| {
-                        intent_mapping.values().collect()
-                    })
-                    .flat_map(|entity_name| BuiltinEntityKind::from_identifier(entity_name).ok())
-                    .unique()
-                    .collect::<Vec<_>>();
-
-                let resolved_slots = resolve_slots(
-                    input,
-                    internal_parsing_result.slots,
-                    &self.dataset_metadata,
-                    &*self.builtin_entity_parser,
-                    Some(&*filter_entity_kinds),
-                );
-
-                return Ok(IntentParserResult {
-                    input: input.to_string(),
-                    intent: Some(internal_parsing_result.intent),
-                    slots: Some(resolved_slots),
-                });
+            if let Some(tagged_tokens) = parser.tag_tokens(input, intent)? {
+                return Ok(tagged_tokens);
+            }
+        }
+        Err(SnipsNluError::MissingSlotFiller(intent.to_string()).into())
+    }
+
+    /// Returns the `top_k` most likely intents along with their slots, sorted by decreasing
+    /// probability
+    ///
+    /// The None intent is not part of the returned alternatives, which means that intents are
+    /// returned here even when `parse` would not find any intent. Intents whose probability is
+    /// lower than the configured threshold are however not returned.
+    pub fn parse_with_alternatives(
+        &self,
+        input: &str,
+        intents_filter: Option<&[IntentName]>,
+        top_k: usize,
+    ) -> Result<Vec<IntentParserResult>> {
+        let set_intents = get_intents_set(intents_filter);
+        let mut results: Vec<IntentParserResult> = Vec::with_capacity(top_k);
+
+        for parser in &self.parsers {
+            let internal_parsing_results =
+                parser.parse_with_alternatives(input, set_intents.as_ref(), top_k)?;
+            for internal_parsing_result in internal_parsing_results {
+                if results.len() >= top_k {
+                    return Ok(results);
+                }
+                // An intent found by a previous parser takes precedence
+                let intent_name = &internal_parsing_result.intent.intent_name;
+                let already_found = results
+                    .iter()
+                    .flat_map(|result| result.intent.as_ref())
+                    .any(|intent| intent.intent_name == *intent_name);
+                if !already_found && !self.is_rejected(&internal_parsing_result.intent) {
+                    results.push(
+                        self.resolve_parsing_result(input, internal_parsing_result).into());
+                }
             }
         }
-        Ok(IntentParserResult {
+        Ok(results)
+    }
+
+    /// Returns the probabilities of all the intents, including the None intent, sorted by
+    /// decreasing probability
+    ///
+    /// The distribution is the one of the first intent classifier found among the parsers, as
+    /// pattern matching does not provide any. An empty list is returned when none of the parsers
+    /// relies on an intent classifier.
+    pub fn get_intents(&self, input: &str) -> Result<Vec<IntentProbability>> {
+        for parser in &self.parsers {
+            if let Some(intents_probabilities) = parser.get_intents(input)? {
+                return Ok(intents_probabilities);
+            }
+        }
+        Ok(vec![])
+    }
+
+    fn is_rejected(&self, intent: &IntentClassifierResult) -> bool {
+        intent.probability < self.intent_probability_threshold.load()
+    }
+
+    fn resolve_parsing_result(
+        &self,
+        input: &str,
+        internal_parsing_result: InternalParsingResult,
+    ) -> NluEngineResult {
+        let metadata = self.metadata.load();
+        let dataset_metadata = &metadata.dataset_metadata;
+        let filter_entity_kinds = dataset_metadata
+            .slot_name_mappings
+            .values()
+            .flat_map::<Vec<_>, _>(|intent_mapping: &HashMap<SlotName, EntityName>| {
+                intent_mapping.values().collect()
+            })
+            .flat_map(|entity_name| BuiltinEntityKind::from_identifier(entity_name).ok())
+            .unique()
+            .collect::<Vec<_>>();
+
+        let slots = split_coordinated_slots(
+            internal_parsing_result.slots,
+            dataset_metadata,
+            self.language,
+        );
+        let resolved_slots = resolve_slots(
+            input,
+            slots,
+            dataset_metadata,
+            &*self.shared_resources.builtin_entity_parser,
+            Some(&*filter_entity_kinds),
+            self.config()
+                .fuzzy_matching_threshold
+                .map(|threshold| (&metadata.fuzzy_matcher, threshold)),
+        );
+        let grouped_slots = group_coordinated_slots(input, resolved_slots, self.language);
+
+        NluEngineResult {
             input: input.to_string(),
-            intent: None,
-            slots: None,
-        })
+            intent: Some(internal_parsing_result.intent),
+            slots: Some(grouped_slots),
+            rejected_intent: None,
+        }
     }
 }
 
+/// Slots which have been dropped when resolving the parsing result, where a slot split into
+/// coordinated values is resolved as long as one of its values is
+fn get_unresolved_slots(slots: Vec<InternalSlot>, result: &NluEngineResult) -> Vec<InternalSlot> {
+    let resolved_slots = result
+        .slots
+        .iter()
+        .flat_map(|slots| slots.iter())
+        .flat_map(|resolved_slot| resolved_slot.individual_values())
+        .collect_vec();
+    slots
+        .into_iter()
+        .filter(|slot| {
+            !resolved_slots.iter().any(|resolved| {
+                resolved.slot.slot_name == slot.slot_name
+                    && resolved.slot.range.as_ref().map_or(false, |range| {
+                        range.start >= slot.char_range.start && range.end <= slot.char_range.end
+                    })
+            })
+        })
+        .collect()
+}
+
+fn get_intents_set(intents_filter: Option<&[IntentName]>) -> Option<HashSet<IntentName>> {
+    intents_filter
+        .map(|intent_list| HashSet::from_iter(intent_list.iter().map(|name| name.to_string())))
+}
+
 impl SnipsNluEngine {
     pub fn extract_slot(
         &self,
@@ -189,57 +680,82 @@ impl SnipsNluEngine {
         intent_name: &str,
         slot_name: &str,
     ) -> Result<Option<Slot>> {
-        let entity_name = self.dataset_metadata
+        let metadata = self.metadata.load();
+        let dataset_metadata = &metadata.dataset_metadata;
+        let entity_name = dataset_metadata
             .slot_name_mappings
             .get(intent_name)
-            .ok_or_else(|| format_err!("Unknown intent: {}", intent_name))?
+            .ok_or_else(|| SnipsNluError::UnknownIntent(intent_name.to_string()))?
             .get(slot_name)
-            .ok_or_else(|| format_err!("Unknown slot: {}", &slot_name))?;
+            .ok_or_else(|| SnipsNluError::UnknownSlot(slot_name.to_string()))?;
 
-        let slot = if let Some(custom_entity) = self.dataset_metadata.entities.get(entity_name) {
-            let language = Language::from_str(&self.dataset_metadata.language_code)?;
+        let slot = if let Some(custom_entity) = dataset_metadata.entities.get(entity_name) {
             extract_custom_slot(
                 input,
                 entity_name.to_string(),
                 slot_name.to_string(),
                 custom_entity,
-                language,
+                self.language,
+                self.config()
+                    .fuzzy_matching_threshold
+                    .map(|threshold| (&metadata.fuzzy_matcher, threshold)),
             )
         } else {
             extract_builtin_slot(
                 input,
                 entity_name.to_string(),
                 slot_name.to_string(),
-                &self.builtin_entity_parser,
+                &self.shared_resources.builtin_entity_parser,
             )?
         };
         Ok(slot)
     }
 }
 
+/// Extracts the longest ngram of the input which is an utterance of the entity, or else, when
+/// fuzzy matching is enabled, the ngram which is the most similar to an utterance of the entity
 fn extract_custom_slot(
     input: String,
     entity_name: EntityName,
     slot_name: SlotName,
     custom_entity: &Entity,
     language: Language,
+    fuzzy_matching: Option<(&FuzzyEntityMatcher, f32)>,
 ) -> Option<Slot> {
     let tokens = tokenize(&input, NluUtilsLanguage::from_language(language));
     let token_values_ref = tokens.iter().map(|v| &*v.value).collect_vec();
     let mut ngrams = compute_all_ngrams(&*token_values_ref, tokens.len());
     ngrams.sort_by_key(|&(_, ref indexes)| -(indexes.len() as i16));
 
-    ngrams
-        .into_iter()
-        .find(|&(ref ngram, _)| custom_entity.utterances.contains_key(&normalize(ngram)))
-        .map(|(ngram, _)| {
+    let exact_match = ngrams
+        .iter()
+        .find(|&&(ref ngram, _)| custom_entity.utterances.contains_key(&normalize(ngram)))
+        .map(|&(ref ngram, _)| {
+            (ngram.clone(), custom_entity.utterances[&normalize(ngram)].to_string())
+        });
+    let fuzzy_match = || {
+        let (matcher, threshold) = fuzzy_matching?;
+        ngrams
+            .iter()
+            .filter_map(|&(ref ngram, _)| {
+                matcher
+                    .find_match(&entity_name, ngram, threshold)
+                    .map(|fuzzy_match| (ngram, fuzzy_match))
+            })
+            // The longest ngram is kept among the ones having the best score
+            .fold(None, |best: Option<(&String, FuzzyMatch)>, (ngram, fuzzy_match)| match best {
+                Some(ref best) if best.1.score >= fuzzy_match.score => Some(best.clone()),
+                _ => Some((ngram, fuzzy_match)),
+            })
+            .map(|(ngram, fuzzy_match)| (ngram.clone(), fuzzy_match.resolved_value))
+    };
+
+    exact_match
+        .or_else(fuzzy_match)
+        .map(|(raw_value, resolved_value)| {
             Some(Slot {
-                raw_value: ngram.clone(),
-                value: SlotValue::Custom(
-                    custom_entity.utterances[&normalize(&ngram)]
-                        .to_string()
-                        .into(),
-                ),
+                raw_value,
+                value: SlotValue::Custom(resolved_value.into()),
                 range: None,
                 entity: entity_name.clone(),
                 slot_name: slot_name.clone(),
@@ -280,8 +796,11 @@ fn extract_builtin_slot(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
+    use std::io::Cursor;
     use snips_nlu_ontology::NumberValue;
-    use utils::file_path;
+    use utils::{file_path, FromPath};
+    use explanation::{MatchedPattern, ParsingDetails};
 
     #[test]
     fn from_path_works() {
@@ -295,6 +814,63 @@ mod tests {
         assert!(nlu_engine.is_ok());
     }
 
+    #[test]
+    fn info_works() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+
+        // When
+        let info = nlu_engine.info();
+
+        // Then
+        assert_eq!("en", &info.language);
+        assert_eq!(vec!["MakeCoffee", "MakeTea"], info.intents.keys().collect_vec());
+        assert_eq!(Some(&"Temperature".to_string()),
+                   info.intents["MakeTea"].get("beverage_temperature"));
+        assert_eq!(vec!["Temperature"], info.custom_entities.keys().collect_vec());
+        assert_eq!(vec!["snips/number"], info.builtin_entities);
+    }
+
+    #[test]
+    fn from_path_should_report_missing_file() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("unknown_engine");
+
+        // When
+        let error = SnipsNluEngine::from_path(path).err().unwrap();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::MissingFile(file_path)) =>
+                assert!(file_path.ends_with("nlu_engine.json")),
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn from_vfs_should_reject_incompatible_model_version() {
+        // Given
+        let mut file_system = MemoryFileSystem::new();
+        file_system.insert(
+            "engine/nlu_engine.json",
+            br#"{"model_version": "0.14.0"}"#.to_vec(),
+        );
+
+        // When
+        let error = SnipsNluEngine::from_vfs(&file_system, "engine").err().unwrap();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::WrongModelVersion(version, _)) => assert_eq!("0.14.0", version),
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
     #[test]
     fn from_zip_works() {
         // Given
@@ -331,6 +907,26 @@ mod tests {
         assert_eq!(expected_slots, result.slots);
     }
 
+    #[test]
+    fn from_vfs_works() {
+        // Given
+        let zip_path = file_path("tests")
+            .join("models")
+            .join("trained_engine.zip");
+        let zip_bytes = fs::read(zip_path).unwrap();
+        let file_system = MemoryFileSystem::from_zip(Cursor::new(zip_bytes)).unwrap();
+
+        // When
+        let nlu_engine = SnipsNluEngine::from_vfs(&file_system, "trained_engine").unwrap();
+        let result = nlu_engine
+            .parse("Make me two cups of coffee please", None)
+            .unwrap();
+
+        // Then
+        let expected_intent = Some("MakeCoffee".to_string());
+        assert_eq!(expected_intent, result.intent.map(|intent| intent.intent_name));
+    }
+
     #[test]
     fn parse_works() {
         // Given
@@ -361,6 +957,492 @@ mod tests {
         assert_eq!(expected_slots, result.slots);
     }
 
+    #[test]
+    fn parse_with_alternatives_works() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+
+        // When
+        let results = nlu_engine
+            .parse_with_alternatives("Make me two cups of coffee please", None, 2)
+            .unwrap();
+
+        // Then
+        let expected_slots = Some(vec![
+            Slot {
+                raw_value: "two".to_string(),
+                value: SlotValue::Number(NumberValue { value: 2.0 }),
+                range: Some(8..11),
+                entity: "snips/number".to_string(),
+                slot_name: "number_of_cups".to_string(),
+            },
+        ]);
+        let intent_names = results
+            .iter()
+            .map(|result| result.intent.as_ref().map(|intent| &*intent.intent_name))
+            .collect_vec();
+
+        assert_eq!(vec![Some("MakeCoffee"), Some("MakeTea")], intent_names);
+        assert_eq!(expected_slots, results[0].slots);
+    }
+
+    #[test]
+    fn parse_with_alternatives_should_filter_intents() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+        let intents_filter = vec!["MakeTea".to_string()];
+
+        // When
+        let results = nlu_engine
+            .parse_with_alternatives("Make me two cups of coffee please", Some(&intents_filter), 2)
+            .unwrap();
+
+        // Then
+        let intent_names = results
+            .iter()
+            .map(|result| result.intent.as_ref().map(|intent| &*intent.intent_name))
+            .collect_vec();
+
+        assert_eq!(vec![Some("MakeTea")], intent_names);
+    }
+
+    #[test]
+    fn parse_batch_works() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+        let inputs = vec![
+            "Make me two cups of coffee please",
+            "I want a cup of tea",
+            "",
+            "Make me three cups of tea",
+        ];
+
+        // When
+        let results = nlu_engine.parse_batch(&inputs, None).unwrap();
+
+        // Then
+        let expected_results = inputs
+            .iter()
+            .map(|input| nlu_engine.parse(input, None).unwrap())
+            .collect_vec();
+        assert_eq!(expected_results, results);
+    }
+
+    #[test]
+    fn parse_detailed_should_return_slots_score_upper_bounds() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+
+        // When
+        let result = nlu_engine
+            .parse_detailed("Make me two cups of coffee please", None)
+            .unwrap();
+
+        // Then
+        let slots = result.slots.unwrap();
+        assert_eq!(1, slots.len());
+        assert_eq!("number_of_cups", slots[0].slot.slot_name);
+        let score_upper_bound = slots[0].score_upper_bound.unwrap();
+        assert!(score_upper_bound > 0.0 && score_upper_bound <= 1.0);
+    }
+
+    #[test]
+    fn parse_explained_should_report_matched_pattern() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+
+        // When
+        let explanation = nlu_engine
+            .parse_explained("Make me two cups of coffee", None)
+            .unwrap();
+
+        // Then
+        let expected_pattern = MatchedPattern {
+            intent: "MakeCoffee".to_string(),
+            pattern_index: 2,
+            with_builtin_entities: true,
+        };
+        assert_eq!(Some(0), explanation.answering_parser_index);
+        assert_eq!(1, explanation.intent_parsers.len());
+        assert_eq!(
+            Some(expected_pattern),
+            explanation.intent_parsers[0].details.matched_pattern
+        );
+        assert!(explanation.unresolved_slots.is_empty());
+    }
+
+    #[test]
+    fn parse_explained_should_report_features_and_tags() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+        let input = "Make me two cups of coffee please";
+
+        // When
+        let explanation = nlu_engine.parse_explained(input, None).unwrap();
+
+        // Then
+        assert_eq!(nlu_engine.parse_detailed(input, None).unwrap(), explanation.result);
+        assert_eq!(Some(1), explanation.answering_parser_index);
+        assert_eq!(None, explanation.intent_parsers[0].intent);
+        let details = &explanation.intent_parsers[1].details;
+        assert_eq!(None, details.matched_pattern);
+        assert!(!details.top_features.is_empty());
+        assert!(details.top_features[0].contribution > 0.0);
+        let slot_filling = details.slot_filling.as_ref().unwrap();
+        assert_eq!(slot_filling.tokens.len(), slot_filling.tags.len());
+    }
+
+    #[test]
+    fn tag_tokens_works() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+
+        // When
+        let tagged_tokens = nlu_engine
+            .tag_tokens("Make me two cups of coffee", "MakeCoffee")
+            .unwrap();
+
+        // Then
+        let values = tagged_tokens.iter().map(|token| &*token.value).collect_vec();
+        assert_eq!(vec!["Make", "me", "two", "cups", "of", "coffee"], values);
+        assert_eq!(8..11, tagged_tokens[2].char_range);
+        assert!(!tagged_tokens[2].features.is_empty());
+        assert!(tagged_tokens[2].marginals.contains_key(&tagged_tokens[2].tag));
+        let marginals_sum: f64 = tagged_tokens[2].marginals.values().sum();
+        assert!((marginals_sum - 1.0).abs() < 1e-6);
+    }
+
+    /// Trained engine whose only intent parser is the deterministic one
+    fn deterministic_engine() -> SnipsNluEngine {
+        let zip_path = file_path("tests")
+            .join("models")
+            .join("trained_engine.zip");
+        let zip_bytes = fs::read(zip_path).unwrap();
+        let mut file_system = MemoryFileSystem::from_zip(Cursor::new(zip_bytes)).unwrap();
+        let model_path = Path::new("trained_engine").join("nlu_engine.json");
+        let mut model: serde_json::Value =
+            serde_json::from_slice(&file_system.read(&model_path).unwrap()).unwrap();
+        model["intent_parsers"] = serde_json::from_str(r#"["deterministic_intent_parser"]"#)
+            .unwrap();
+        file_system.insert(&model_path, serde_json::to_vec(&model).unwrap());
+        SnipsNluEngine::from_vfs(&file_system, "trained_engine").unwrap()
+    }
+
+    #[test]
+    fn tag_tokens_should_fail_without_slot_filler() {
+        // Given
+        let nlu_engine = deterministic_engine();
+
+        // When
+        let error = nlu_engine.tag_tokens("Make me two cups of coffee", "MakeCoffee").unwrap_err();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::MissingSlotFiller(intent)) => assert_eq!("MakeCoffee", intent),
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn tag_tokens_should_fail_for_unknown_intent() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+
+        // When
+        let error = nlu_engine.tag_tokens("Make me two cups of coffee", "Unknown").err().unwrap();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::UnknownIntent(intent)) => assert_eq!("Unknown", intent),
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn add_and_remove_entity_values_works() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(&path).unwrap();
+        let entity_value = EntityValue {
+            value: "warm".to_string(),
+            synonyms: vec!["lukewarm".to_string()],
+        };
+        let temperature_value = |result: IntentParserResult| {
+            result
+                .slots
+                .into_iter()
+                .flat_map(|slots| slots)
+                .find(|slot| slot.slot_name == "beverage_temperature")
+                .map(|slot| slot.value)
+        };
+
+        // When
+        nlu_engine.add_entity_values("Temperature", &[entity_value]).unwrap();
+        let result_after_add = nlu_engine.parse("Make 3 lukewarm teas", None).unwrap();
+        nlu_engine.remove_entity_values("Temperature", &["warm".to_string()]).unwrap();
+        let result_after_remove = nlu_engine.parse("Make 3 lukewarm teas", None).unwrap();
+
+        // Then
+        let expected_value = SlotValue::Custom("warm".to_string().into());
+        let expected_result_after_remove = SnipsNluEngine::from_path(path)
+            .unwrap()
+            .parse("Make 3 lukewarm teas", None)
+            .unwrap();
+        assert_eq!(
+            Some("MakeTea"),
+            result_after_add.intent.as_ref().map(|intent| &*intent.intent_name)
+        );
+        assert_eq!(Some(expected_value), temperature_value(result_after_add));
+        assert_eq!(expected_result_after_remove, result_after_remove);
+    }
+
+    /// Parser whose entity updates always fail
+    struct FailingParser;
+
+    impl FromPath for FailingParser {
+        fn from_vfs<P: AsRef<Path>>(
+            _file_system: &FileSystem,
+            _path: P,
+            _shared_resources: Arc<SharedResources>,
+        ) -> Result<Self> {
+            unimplemented!()
+        }
+    }
+
+    impl IntentParser for FailingParser {
+        fn parse(
+            &self,
+            _input: &str,
+            _intents: Option<&HashSet<String>>,
+        ) -> Result<Option<InternalParsingResult>> {
+            unimplemented!()
+        }
+
+        fn parse_with_alternatives(
+            &self,
+            _input: &str,
+            _intents: Option<&HashSet<String>>,
+            _top_k: usize,
+        ) -> Result<Vec<InternalParsingResult>> {
+            unimplemented!()
+        }
+
+        fn parse_explained(
+            &self,
+            _input: &str,
+            _intents: Option<&HashSet<String>>,
+        ) -> Result<(Option<InternalParsingResult>, ParsingDetails)> {
+            unimplemented!()
+        }
+
+        fn tag_tokens(&self, _input: &str, _intent: &str) -> Result<Option<Vec<TaggedToken>>> {
+            unimplemented!()
+        }
+
+        fn prepare_entity_utterances<'a>(
+            &'a self,
+            entity: &str,
+            _utterances: &[String],
+        ) -> Result<Box<PreparedUpdate + 'a>> {
+            bail!("Cannot update entity '{}'", entity)
+        }
+
+        fn memory_usage(&self) -> BTreeMap<String, usize> {
+            BTreeMap::new()
+        }
+    }
+
+    #[test]
+    fn failed_entity_update_should_leave_engine_unchanged() {
+        // Given
+        let mut nlu_engine = deterministic_engine();
+        nlu_engine.parsers.push(Box::new(FailingParser));
+        nlu_engine.parser_names.push("failing_parser".to_string());
+        let entity_value = EntityValue {
+            value: "warm".to_string(),
+            synonyms: vec!["lukewarm".to_string()],
+        };
+
+        // When
+        let result = nlu_engine.add_entity_values("Temperature", &[entity_value]);
+
+        // Then
+        assert!(result.is_err());
+        let metadata = nlu_engine.metadata.load();
+        let utterances = &metadata.dataset_metadata.entities["Temperature"].utterances;
+        assert!(!utterances.contains_key("lukewarm"));
+        let deterministic_result = nlu_engine.parsers[0]
+            .parse("Make 3 lukewarm teas", None)
+            .unwrap();
+        assert!(deterministic_result.is_none());
+    }
+
+    #[test]
+    fn deterministic_parser_should_match_added_and_removed_entity_values() {
+        // Given
+        let nlu_engine = deterministic_engine();
+        let entity_value = EntityValue {
+            value: "warm".to_string(),
+            synonyms: vec!["lukewarm".to_string()],
+        };
+
+        // When
+        nlu_engine.add_entity_values("Temperature", &[entity_value]).unwrap();
+        nlu_engine.remove_entity_values("Temperature", &["hot".to_string()]).unwrap();
+        let added_value_result = nlu_engine.parse("Make 3 lukewarm teas", None).unwrap();
+        let removed_value_result = nlu_engine.parse("Make 3 boiling teas", None).unwrap();
+        let kept_value_result = nlu_engine.parse("Make 3 iced teas", None).unwrap();
+
+        // Then
+        let slot_values = |result: &IntentParserResult| {
+            result
+                .slots
+                .iter()
+                .flat_map(|slots| slots)
+                .filter(|slot| slot.slot_name == "beverage_temperature")
+                .map(|slot| (slot.raw_value.clone(), slot.value.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![(
+                "lukewarm".to_string(),
+                SlotValue::Custom("warm".to_string().into()),
+            )],
+            slot_values(&added_value_result)
+        );
+        assert_eq!(None, removed_value_result.intent);
+        assert_eq!(None, removed_value_result.slots);
+        assert_eq!(
+            vec![(
+                "iced".to_string(),
+                SlotValue::Custom("cold".to_string().into()),
+            )],
+            slot_values(&kept_value_result)
+        );
+    }
+
+    #[test]
+    fn add_entity_values_should_fail_for_unknown_entity() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+
+        // When
+        let error = nlu_engine.add_entity_values("Unknown", &[]).err().unwrap();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::UnknownEntity(entity)) => assert_eq!("Unknown", entity),
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn parse_should_reject_intent_below_threshold() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+        let input = "Make me two cups of coffee please";
+        let intent = nlu_engine.parse(input, None).unwrap().intent.unwrap();
+        nlu_engine.set_config(EngineConfig {
+            intent_probability_threshold: intent.probability + 0.1,
+            ..EngineConfig::default()
+        });
+
+        // When
+        let result = nlu_engine.parse_detailed(input, None).unwrap();
+
+        // Then
+        let expected_result = NluEngineResult {
+            input: input.to_string(),
+            intent: None,
+            slots: None,
+            rejected_intent: Some(intent),
+        };
+        assert_eq!(expected_result, result);
+    }
+
+    #[test]
+    fn parse_should_keep_intent_above_threshold() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+        let input = "Make me two cups of coffee please";
+        let expected_result = nlu_engine.parse(input, None).unwrap();
+        let probability = expected_result.intent.as_ref().unwrap().probability;
+        nlu_engine.set_config(EngineConfig {
+            intent_probability_threshold: probability,
+            ..EngineConfig::default()
+        });
+
+        // When
+        let result = nlu_engine.parse_detailed(input, None).unwrap();
+
+        // Then
+        assert_eq!(None, result.rejected_intent);
+        assert_eq!(expected_result, IntentParserResult::from(result));
+    }
+
+    #[test]
+    fn get_intents_works() {
+        // Given
+        let path = file_path("tests")
+            .join("models")
+            .join("trained_engine");
+        let nlu_engine = SnipsNluEngine::from_path(path).unwrap();
+
+        // When
+        let intents = nlu_engine
+            .get_intents("Make me two cups of coffee please")
+            .unwrap();
+
+        // Then
+        let intent_names = intents
+            .iter()
+            .map(|intent| intent.intent_name.as_ref().map(|name| &**name))
+            .sorted();
+        assert_eq!(vec![None, Some("MakeCoffee"), Some("MakeTea")], intent_names);
+        assert_eq!(Some("MakeCoffee".to_string()), intents[0].intent_name);
+        assert!(intents[0].probability < 1.0);
+        assert!(intents
+            .windows(2)
+            .all(|pair| pair[0].probability >= pair[1].probability));
+        let total_probability: f32 = intents.iter().map(|intent| intent.probability).sum();
+        assert!((total_probability - 1.0).abs() < 1e-5);
+    }
+
     #[test]
     fn should_extract_custom_slot_when_tagged() {
         // Given
@@ -379,7 +1461,7 @@ mod tests {
 
         // When
         let extracted_slot =
-            extract_custom_slot(input, entity_name, slot_name, &custom_entity, language);
+            extract_custom_slot(input, entity_name, slot_name, &custom_entity, language, None);
 
         // Then
         let expected_slot = Some(Slot {
@@ -392,6 +1474,37 @@ mod tests {
         assert_eq!(expected_slot, extracted_slot);
     }
 
+    #[test]
+    fn should_extract_custom_slot_with_fuzzy_matching() {
+        // Given
+        let language = Language::EN;
+        let input = "a capuccino please".to_string();
+        let entity_name = "coffee".to_string();
+        let slot_name = "slot".to_string();
+        let custom_entity = Entity {
+            automatically_extensible: false,
+            utterances: hashmap! {
+                "cappuccino".to_string() => "Cappuccino".to_string(),
+            },
+        };
+        let entities = hashmap! { entity_name.clone() => custom_entity.clone() };
+        let matcher = FuzzyEntityMatcher::new(&entities, None, language);
+
+        // When
+        let extracted_slot = extract_custom_slot(
+            input, entity_name, slot_name, &custom_entity, language, Some((&matcher, 0.8)));
+
+        // Then
+        let expected_slot = Some(Slot {
+            raw_value: "capuccino".to_string(),
+            value: SlotValue::Custom("Cappuccino".to_string().into()),
+            range: None,
+            entity: "coffee".to_string(),
+            slot_name: "slot".to_string(),
+        });
+        assert_eq!(expected_slot, extracted_slot);
+    }
+
     #[test]
     fn should_extract_custom_slot_when_not_tagged() {
         // Given
@@ -406,7 +1519,7 @@ mod tests {
 
         // When
         let extracted_slot =
-            extract_custom_slot(input, entity_name, slot_name, &custom_entity, language);
+            extract_custom_slot(input, entity_name, slot_name, &custom_entity, language, None);
 
         // Then
         let expected_slot = Some(Slot {
@@ -433,7 +1546,7 @@ mod tests {
 
         // When
         let extracted_slot =
-            extract_custom_slot(input, entity_name, slot_name, &custom_entity, language);
+            extract_custom_slot(input, entity_name, slot_name, &custom_entity, language, None);
 
         // Then
         let expected_slot = None;
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use sha2::{Digest, Sha256};
+
+use errors::*;
+
+/// Keeps track of the resources which are currently used by some engines, so that a resource
+/// loaded with the exact same content is shared instead of being loaded twice in memory.
+///
+/// Resources are identified by the SHA-256 digest and the length of their content, so that the
+/// raw content does not have to be kept around. The cache only holds weak references: resources
+/// are owned by the engines and get released as soon as the last engine using them is dropped.
+pub struct ResourcesCache<T> {
+    resources: Mutex<HashMap<ContentKey, Weak<T>>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct ContentKey {
+    digest: [u8; 32],
+    length: usize,
+}
+
+impl ContentKey {
+    fn new(content: &[u8]) -> Self {
+        let mut digest = [0; 32];
+        digest.copy_from_slice(&Sha256::digest(content));
+        Self {
+            digest,
+            length: content.len(),
+        }
+    }
+}
+
+impl<T> ResourcesCache<T> {
+    pub fn new() -> Self {
+        Self { resources: Mutex::new(HashMap::new()) }
+    }
+
+    /// Loads the resource with the provided content, or returns the already loaded one when
+    /// its content is identical
+    pub fn get_or_load<F>(&self, content: &[u8], load: F) -> Result<Arc<T>>
+        where F: FnOnce(&[u8]) -> Result<T>
+    {
+        let content_key = ContentKey::new(content);
+        if let Some(shared_resource) = self.get(&content_key) {
+            return Ok(shared_resource);
+        }
+        // The lock is released while loading, so that loading a resource does not block the
+        // engines loading other resources
+        let resource = Arc::new(load(content)?);
+        let mut resources = self.resources.lock().unwrap();
+        // The same content may have been loaded meanwhile by another engine
+        if let Some(shared_resource) = resources.get(&content_key).and_then(Weak::upgrade) {
+            return Ok(shared_resource);
+        }
+        resources.insert(content_key, Arc::downgrade(&resource));
+        Ok(resource)
+    }
+
+    fn get(&self, content_key: &ContentKey) -> Option<Arc<T>> {
+        let mut resources = self.resources.lock().unwrap();
+        resources.retain(|_, resource| resource.upgrade().is_some());
+        resources.get(content_key).and_then(Weak::upgrade)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &[u8]) -> Result<String> {
+        Ok(String::from_utf8(content.to_vec())?)
+    }
+
+    #[test]
+    fn should_share_resources_with_identical_content() {
+        // Given
+        let cache = ResourcesCache::new();
+
+        // When
+        let resource_1 = cache.get_or_load(b"dog\ncat", parse).unwrap();
+        let resource_2 = cache.get_or_load(b"dog\ncat", parse).unwrap();
+        let resource_3 = cache.get_or_load(b"dog\nbird", parse).unwrap();
+
+        // Then
+        assert!(Arc::ptr_eq(&resource_1, &resource_2));
+        assert!(!Arc::ptr_eq(&resource_1, &resource_3));
+        assert_eq!("dog\nbird", &*resource_3);
+    }
+
+    #[test]
+    fn should_not_load_shared_resources_again() {
+        // Given
+        let cache = ResourcesCache::new();
+        let _resource = cache.get_or_load(b"dog\ncat", parse).unwrap();
+
+        // When
+        let shared_resource = cache.get_or_load(b"dog\ncat", |_| -> Result<String> {
+            panic!("The resource must not be loaded again")
+        });
+
+        // Then
+        assert_eq!("dog\ncat", &*shared_resource.unwrap());
+    }
+
+    #[test]
+    fn should_release_unused_resources() {
+        // Given
+        let cache = ResourcesCache::new();
+        let resource = cache.get_or_load(b"dog\ncat", parse).unwrap();
+        let weak_resource = Arc::downgrade(&resource);
+
+        // When
+        drop(resource);
+        let _other_resource = cache.get_or_load(b"dog\nbird", parse).unwrap();
+
+        // Then
+        assert!(weak_resource.upgrade().is_none());
+        assert_eq!(1, cache.resources.lock().unwrap().len());
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::mem;
+
+use memory::HeapSize;
+
+/// Location of a string within the buffer of a compact collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    start: u32,
+    end: u32,
+}
+
+fn push_string(buffer: &mut String, value: &str) -> Span {
+    let start = buffer.len() as u32;
+    buffer.push_str(value);
+    Span {
+        start,
+        end: buffer.len() as u32,
+    }
+}
+
+fn get_string(buffer: &str, span: Span) -> &str {
+    &buffer[span.start as usize..span.end as usize]
+}
+
+/// Immutable set of strings which are stored contiguously in a single buffer.
+///
+/// The strings are sorted, so that a lookup is a binary search which neither hashes nor
+/// allocates.
+#[derive(Debug, Default, PartialEq)]
+pub struct CompactStringSet {
+    buffer: String,
+    values: Vec<Span>,
+}
+
+impl CompactStringSet {
+    pub fn contains(&self, value: &str) -> bool {
+        self.values
+            .binary_search_by(|span| get_string(&self.buffer, *span).cmp(value))
+            .is_ok()
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for CompactStringSet {
+    fn from_iter<I: IntoIterator<Item = S>>(values: I) -> Self {
+        let mut values: Vec<S> = values.into_iter().collect();
+        values.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        values.dedup_by(|a, b| a.as_ref() == b.as_ref());
+        let mut buffer = String::with_capacity(values.iter().map(|v| v.as_ref().len()).sum());
+        let values = values
+            .iter()
+            .map(|value| push_string(&mut buffer, value.as_ref()))
+            .collect();
+        Self { buffer, values }
+    }
+}
+
+impl HeapSize for CompactStringSet {
+    fn heap_size(&self) -> usize {
+        self.buffer.capacity() + self.values.capacity() * mem::size_of::<Span>()
+    }
+}
+
+/// Immutable map between strings which are stored contiguously in a single buffer, where
+/// identical values are only stored once.
+///
+/// The entries are sorted by key, so that a lookup is a binary search which neither hashes nor
+/// allocates.
+#[derive(Debug, Default, PartialEq)]
+pub struct CompactStringMap {
+    buffer: String,
+    entries: Vec<(Span, Span)>,
+}
+
+impl CompactStringMap {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .binary_search_by(|&(key_span, _)| get_string(&self.buffer, key_span).cmp(key))
+            .ok()
+            .map(|index| get_string(&self.buffer, self.entries[index].1))
+    }
+}
+
+/// When a key appears several times, the last value is kept as with a `HashMap`
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for CompactStringMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(entries: I) -> Self {
+        let mut entries: Vec<(K, V)> = entries.into_iter().collect();
+        // The sort is stable, hence the reversal which puts the last value of a key first
+        entries.reverse();
+        entries.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+        entries.dedup_by(|a, b| a.0.as_ref() == b.0.as_ref());
+
+        let mut buffer = String::new();
+        let mut value_spans: HashMap<&str, Span> = HashMap::new();
+        let entries = entries
+            .iter()
+            .map(|&(ref key, ref value)| {
+                let key_span = push_string(&mut buffer, key.as_ref());
+                let value_span = *value_spans
+                    .entry(value.as_ref())
+                    .or_insert_with(|| push_string(&mut buffer, value.as_ref()));
+                (key_span, value_span)
+            })
+            .collect();
+        buffer.shrink_to_fit();
+        Self { buffer, entries }
+    }
+}
+
+impl HeapSize for CompactStringMap {
+    fn heap_size(&self) -> usize {
+        self.buffer.capacity() + self.entries.capacity() * mem::size_of::<(Span, Span)>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_string_set_works() {
+        // When
+        let set: CompactStringSet = vec!["dog", "cat", "bear", "cat"].into_iter().collect();
+
+        // Then
+        assert_eq!(3, set.values.len());
+        assert!(set.contains("cat"));
+        assert!(set.contains("bear"));
+        assert!(!set.contains("bird"));
+        assert!(!set.contains(""));
+    }
+
+    #[test]
+    fn compact_string_map_should_store_identical_values_once() {
+        // When
+        let map: CompactStringMap = vec![
+            ("does", "do"),
+            ("did", "do"),
+            ("done", "do"),
+            ("went", "go"),
+            ("did", "do"),
+        ].into_iter()
+            .collect();
+
+        // Then
+        assert_eq!(4, map.entries.len());
+        assert_eq!(Some("do"), map.get("did"));
+        assert_eq!(Some("go"), map.get("went"));
+        assert_eq!(None, map.get("do"));
+        assert_eq!("doesdodiddonewentgo".len(), map.buffer.len());
+    }
+
+    #[test]
+    fn compact_string_map_should_keep_last_value_of_duplicate_keys() {
+        // When
+        let map: CompactStringMap = vec![("a", "1"), ("b", "2"), ("a", "3")].into_iter().collect();
+
+        // Then
+        assert_eq!(Some("3"), map.get("a"));
+    }
+}
@@ -1,109 +1,75 @@
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
 use std::io::{BufRead, BufReader, Read};
-use std::iter::FromIterator;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use errors::*;
 use failure::ResultExt;
-use snips_nlu_ontology::Language;
+use memory::HeapSize;
+use resources::cache::ResourcesCache;
+use resources::compact::CompactStringSet;
+use vfs::FileSystem;
 
 pub trait Gazetteer {
     fn contains(&self, value: &str) -> bool;
 }
 
-pub struct HashSetGazetteer {
-    values: HashSet<String>,
+pub struct CompactGazetteer {
+    values: CompactStringSet,
 }
 
-impl HashSetGazetteer {
+impl CompactGazetteer {
     fn from_reader<R: Read>(reader: R) -> Result<Self> {
         let reader = BufReader::new(reader);
-        let mut values = HashSet::<String>::new();
+        let mut values = vec![];
         for line in reader.lines() {
             let word = line?;
             if !word.is_empty() {
-                values.insert(word);
+                values.push(word);
             }
         }
-        Ok(Self { values })
+        Ok(Self { values: values.into_iter().collect() })
     }
 }
 
-impl<I> From<I> for HashSetGazetteer where I: Iterator<Item=String> {
+impl<I> From<I> for CompactGazetteer where I: Iterator<Item=String> {
     fn from(values_it: I) -> Self {
         Self {
-            values: HashSet::from_iter(values_it),
+            values: values_it.collect(),
         }
     }
 }
 
-impl Gazetteer for HashSetGazetteer {
+impl Gazetteer for CompactGazetteer {
     fn contains(&self, value: &str) -> bool {
         self.values.contains(value)
     }
 }
 
-lazy_static! {
-    static ref GAZETTEERS: Mutex<HashMap<GazetteerConfiguration, Arc<HashSetGazetteer>>> =
-        Mutex::new(HashMap::new());
+impl HeapSize for CompactGazetteer {
+    fn heap_size(&self) -> usize {
+        self.values.heap_size()
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct GazetteerConfiguration {
-    pub name: String,
-    pub language: Language,
+lazy_static! {
+    static ref GAZETTEERS: ResourcesCache<CompactGazetteer> = ResourcesCache::new();
 }
 
 pub fn load_gazetteer<P: AsRef<Path>>(
-    name: String,
-    language: Language,
+    file_system: &FileSystem,
     path: P,
-) -> Result<()> {
-    let configuration = GazetteerConfiguration { name, language };
-
-    if GAZETTEERS.lock().unwrap().contains_key(&configuration) {
-        return Ok(());
-    }
-    let file = File::open(&path)
+) -> Result<Arc<CompactGazetteer>> {
+    let content = file_system.read(path.as_ref())
         .with_context(|_| format!("Cannot open gazetteer file '{:?}'", path.as_ref()))?;
-    let gazetteer = HashSetGazetteer::from_reader(file)?;
-    GAZETTEERS
-        .lock()
-        .unwrap()
-        .entry(configuration)
-        .or_insert_with(|| Arc::new(gazetteer));
-    Ok(())
-}
-
-pub fn get_gazetteer(
-    name: String,
-    language: Language,
-) -> Result<Arc<HashSetGazetteer>> {
-    let configuration = GazetteerConfiguration { name, language };
-    GAZETTEERS
-        .lock()
-        .unwrap()
-        .get(&configuration)
-        .cloned()
-        .ok_or_else(||
-            format_err!("Cannot find gazetteer with configuration {:?}", configuration))
-}
-
-pub fn clear_gazetteers() {
-    GAZETTEERS
-        .lock()
-        .unwrap()
-        .clear();
+    GAZETTEERS.get_or_load(&content, |content| CompactGazetteer::from_reader(content))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Gazetteer, HashSetGazetteer};
+    use super::{Gazetteer, CompactGazetteer};
 
     #[test]
-    fn hashset_gazetteer_works() {
+    fn compact_gazetteer_works() {
         // Given
         let gazetteer: &[u8] = r#"
 dog
@@ -112,7 +78,7 @@ bear
 crocodile"#.as_ref();
 
         // When
-        let gazetteer = HashSetGazetteer::from_reader(gazetteer);
+        let gazetteer = CompactGazetteer::from_reader(gazetteer);
 
         // Then
         assert!(gazetteer.is_ok());
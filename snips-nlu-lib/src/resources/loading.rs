@@ -1,61 +1,121 @@
+use std::collections::HashMap;
 use std::path::Path;
-use std::str::FromStr;
+use std::sync::Arc;
 
 use errors::*;
 use failure::ResultExt;
-use resources::gazetteer::{clear_gazetteers, load_gazetteer};
-use resources::stemmer::{clear_stemmers, load_stemmer};
-use resources::word_clusterer::{clear_word_clusterers, load_word_clusterer};
+use language::parse_language;
+use models::ModelVersion;
+use resources::SharedResources;
+use resources::gazetteer::load_gazetteer;
+use resources::noise::load_noise;
+use resources::stemmer::load_stemmer;
+use resources::word_clusterer::load_word_clusterer;
 use snips_nlu_ontology::Language;
 use serde_json;
-use std::fs::File;
+use vfs::{FileSystem, LocalFileSystem};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ResourcesMetadata {
     language: String,
     gazetteers: Option<Vec<String>>,
     word_clusters: Option<Vec<String>>,
-    stems: Option<String>
+    stems: Option<String>,
+    noise: Option<String>,
 }
 
-pub fn load_resources<P: AsRef<Path>>(resources_dir: P) -> Result<()> {
-    for dir_entry in resources_dir.as_ref().read_dir()? {
-        let language_resources_path = dir_entry?.path();
+pub fn load_shared_resources<P: AsRef<Path>>(
+    resources_dir: P,
+    language: Language,
+) -> Result<Arc<SharedResources>> {
+    load_shared_resources_from_vfs(&LocalFileSystem, resources_dir, language)
+}
+
+/// Loads the resources of the provided language which are located in `resources_dir`
+pub fn load_shared_resources_from_vfs<P: AsRef<Path>>(
+    file_system: &FileSystem,
+    resources_dir: P,
+    language: Language,
+) -> Result<Arc<SharedResources>> {
+    load_resources(file_system, resources_dir, language).map(Arc::new)
+}
+
+/// Same as `load_shared_resources_from_vfs`, for the processing units of an engine whose model
+/// has the provided version
+pub fn load_engine_resources_from_vfs<P: AsRef<Path>>(
+    file_system: &FileSystem,
+    resources_dir: P,
+    language: Language,
+    model_version: ModelVersion,
+) -> Result<Arc<SharedResources>> {
+    let mut resources = load_resources(file_system, resources_dir, language)?;
+    resources.model_version = model_version;
+    Ok(Arc::new(resources))
+}
+
+fn load_resources<P: AsRef<Path>>(
+    file_system: &FileSystem,
+    resources_dir: P,
+    language: Language,
+) -> Result<SharedResources> {
+    if !file_system.exists(resources_dir.as_ref()) {
+        return Ok(SharedResources::new(language));
+    }
+    for language_resources_path in file_system.read_dir(resources_dir.as_ref())? {
         let metadata_file_path = language_resources_path.join("metadata.json");
-        if metadata_file_path.exists() {
-            load_language_resources(language_resources_path)?;
+        if !file_system.exists(&metadata_file_path) {
+            continue;
+        }
+        let metadata = load_resources_metadata(file_system, &metadata_file_path)?;
+        if parse_language(&metadata.language)? == language {
+            return load_language_resources(
+                file_system, language_resources_path, metadata, language);
         }
     }
-    Ok(())
+    Ok(SharedResources::new(language))
 }
 
-pub fn load_language_resources<P: AsRef<Path>>(
-    language_resources_dir: P,
-) -> Result<()> {
-    let metadata_file_path = language_resources_dir.as_ref().join("metadata.json");
-    let metadata_file = File::open(&metadata_file_path)?;
-    let metadata: ResourcesMetadata = serde_json::from_reader(metadata_file)
+fn load_resources_metadata(
+    file_system: &FileSystem,
+    metadata_file_path: &Path,
+) -> Result<ResourcesMetadata> {
+    let metadata_file = file_system.open(metadata_file_path)?;
+    Ok(serde_json::from_reader(metadata_file)
         .with_context(|_|
-            format!("Cannot deserialize resources metadata file '{:?}'", metadata_file_path))?;
-    let language = Language::from_str(&metadata.language)?;
+            SnipsNluError::InvalidJson(metadata_file_path.to_string_lossy().into_owned()))?)
+}
+
+fn load_language_resources<P: AsRef<Path>>(
+    file_system: &FileSystem,
+    language_resources_dir: P,
+    metadata: ResourcesMetadata,
+    language: Language,
+) -> Result<SharedResources> {
+    let mut resources = SharedResources::new(language);
     if let Some(gazetteer_names) = metadata.gazetteers {
         let gazetteers_directory = language_resources_dir.as_ref().join("gazetteers");
+        let mut gazetteers = HashMap::new();
         for gazetteer_name in gazetteer_names {
             let gazetteer_path = gazetteers_directory
                 .join(gazetteer_name.clone())
                 .with_extension("txt");
-            load_gazetteer(gazetteer_name, language, gazetteer_path)?;
+            let gazetteer = load_gazetteer(file_system, gazetteer_path)?;
+            gazetteers.insert(gazetteer_name, gazetteer);
         }
+        resources.gazetteers = gazetteers;
     }
 
     if let Some(word_clusters) = metadata.word_clusters {
         let word_clusters_directory = language_resources_dir.as_ref().join("word_clusters");
+        let mut word_clusterers = HashMap::new();
         for clusters_name in word_clusters {
             let clusters_path = word_clusters_directory
                 .join(clusters_name.clone())
-                .with_extension("txt");;
-            load_word_clusterer(clusters_name, language, clusters_path)?;
+                .with_extension("txt");
+            let word_clusterer = load_word_clusterer(file_system, clusters_path)?;
+            word_clusterers.insert(clusters_name, word_clusterer);
         }
+        resources.word_clusterers = word_clusterers;
     }
 
     if let Some(stems) = metadata.stems {
@@ -63,14 +123,62 @@ pub fn load_language_resources<P: AsRef<Path>>(
         let stems_path = stemming_directory
             .join(stems)
             .with_extension("txt");
-        load_stemmer(language, stems_path)?;
+        resources.stemmer = Some(load_stemmer(file_system, stems_path)?);
+    }
+
+    if let Some(noise) = metadata.noise {
+        let noise_path = language_resources_dir.as_ref()
+            .join(noise)
+            .with_extension("txt");
+        resources.noise = Some(load_noise(file_system, noise_path)?);
     }
 
-    Ok(())
+    Ok(resources)
 }
 
-pub fn clear_resources() {
-    clear_gazetteers();
-    clear_stemmers();
-    clear_word_clusterers();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::file_path;
+
+    #[test]
+    fn load_shared_resources_works() {
+        // Given
+        let resources_path = file_path("tests")
+            .join("models")
+            .join("trained_engine")
+            .join("resources");
+
+        // When
+        let resources = load_shared_resources(resources_path, Language::EN).unwrap();
+
+        // Then
+        assert!(resources.stemmer.is_some());
+        assert!(resources.get_gazetteer("top_10000_words_stemmed").is_ok());
+        assert!(resources.word_clusterers.is_empty());
+        assert!(!resources.noise.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_share_identical_resources_between_loadings() {
+        // Given
+        let resources_path = file_path("tests")
+            .join("models")
+            .join("trained_engine")
+            .join("resources");
+
+        // When
+        let resources_1 = load_shared_resources(&resources_path, Language::EN).unwrap();
+        let resources_2 = load_shared_resources(&resources_path, Language::EN).unwrap();
+
+        // Then
+        assert!(Arc::ptr_eq(
+            resources_1.stemmer.as_ref().unwrap(),
+            resources_2.stemmer.as_ref().unwrap()
+        ));
+        assert!(Arc::ptr_eq(
+            &resources_1.builtin_entity_parser,
+            &resources_2.builtin_entity_parser
+        ));
+    }
 }
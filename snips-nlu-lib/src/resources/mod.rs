@@ -1,4 +1,94 @@
+pub mod cache;
+pub mod compact;
 pub mod loading;
 pub mod gazetteer;
+pub mod noise;
 pub mod stemmer;
 pub mod word_clusterer;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use builtin_entity_parsing::{BuiltinEntityParserFactory, CachingBuiltinEntityParser};
+use errors::*;
+use memory::HeapSize;
+use models::ModelVersion;
+use resources::gazetteer::CompactGazetteer;
+use resources::stemmer::CompactStemmer;
+use resources::word_clusterer::CompactWordClusterer;
+use snips_nlu_ontology::Language;
+
+/// Resources owned by an engine and shared among its processing units
+pub struct SharedResources {
+    pub builtin_entity_parser: Arc<CachingBuiltinEntityParser>,
+    pub gazetteers: HashMap<String, Arc<CompactGazetteer>>,
+    pub stemmer: Option<Arc<CompactStemmer>>,
+    pub word_clusterers: HashMap<String, Arc<CompactWordClusterer>>,
+    /// Words used to generate the utterances of the None intent when training
+    pub noise: Option<Arc<Vec<String>>>,
+    /// Version declared by the engine model, which tells the processing units how their own
+    /// models must be migrated
+    pub model_version: ModelVersion,
+}
+
+impl SharedResources {
+    /// Creates resources which only contain a builtin entity parser
+    pub fn new(language: Language) -> Self {
+        Self {
+            builtin_entity_parser: BuiltinEntityParserFactory::get(language),
+            gazetteers: HashMap::new(),
+            stemmer: None,
+            word_clusterers: HashMap::new(),
+            noise: None,
+            model_version: ModelVersion::current(),
+        }
+    }
+
+    pub fn get_gazetteer(&self, name: &str) -> Result<Arc<CompactGazetteer>> {
+        self.gazetteers
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SnipsNluError::MissingResource(format!("gazetteer '{}'", name)).into())
+    }
+
+    pub fn get_stemmer(&self) -> Result<Arc<CompactStemmer>> {
+        self.stemmer
+            .clone()
+            .ok_or_else(|| SnipsNluError::MissingResource("stemmer".to_string()).into())
+    }
+
+    /// Approximate memory used by each resource, indexed by names such as `gazetteers/<name>`
+    pub fn memory_usage(&self) -> BTreeMap<String, usize> {
+        let gazetteers = self.gazetteers
+            .iter()
+            .map(|(name, gazetteer)| (format!("gazetteers/{}", name), gazetteer.heap_size()));
+        let word_clusterers = self.word_clusterers
+            .iter()
+            .map(|(name, clusterer)| (format!("word_clusters/{}", name), clusterer.heap_size()));
+        let stemmer = self.stemmer
+            .iter()
+            .map(|stemmer| ("stemmer".to_string(), stemmer.heap_size()));
+        let noise = self.noise
+            .iter()
+            .map(|noise| ("noise".to_string(), noise.heap_size()));
+        let builtin_entity_parser = Some((
+            "builtin_entity_parser".to_string(),
+            self.builtin_entity_parser.heap_size(),
+        ));
+        gazetteers
+            .chain(word_clusterers)
+            .chain(stemmer)
+            .chain(noise)
+            .chain(builtin_entity_parser)
+            .collect()
+    }
+
+    pub fn get_word_clusterer(&self, name: &str) -> Result<Arc<CompactWordClusterer>> {
+        self.word_clusterers
+            .get(name)
+            .cloned()
+            .ok_or_else(|| {
+                SnipsNluError::MissingResource(format!("word clusterer '{}'", name)).into()
+            })
+    }
+}
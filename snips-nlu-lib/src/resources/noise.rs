@@ -0,0 +1,24 @@
+use std::path::Path;
+use std::str;
+use std::sync::Arc;
+
+use errors::*;
+use failure::ResultExt;
+use resources::cache::ResourcesCache;
+use vfs::FileSystem;
+
+lazy_static! {
+    static ref NOISES: ResourcesCache<Vec<String>> = ResourcesCache::new();
+}
+
+/// Loads the words of the noise file of a language, which are used to train the None intent
+pub fn load_noise<P: AsRef<Path>>(file_system: &FileSystem, path: P) -> Result<Arc<Vec<String>>> {
+    let content = file_system.read(path.as_ref())
+        .with_context(|_| format!("Cannot open noise file '{:?}'", path.as_ref()))?;
+    NOISES.get_or_load(&content, |content| {
+        Ok(str::from_utf8(content)?
+            .split_whitespace()
+            .map(|word| word.to_string())
+            .collect())
+    })
+}
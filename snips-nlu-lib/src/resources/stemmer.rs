@@ -1,25 +1,27 @@
-use std::collections::HashMap;
-use std::fs::File;
 use std::io::Read;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use csv;
 use errors::*;
 use failure::ResultExt;
-use snips_nlu_ontology::Language;
+use memory::HeapSize;
+use resources::cache::ResourcesCache;
+use resources::compact::CompactStringMap;
+use vfs::FileSystem;
 
 pub trait Stemmer: Send + Sync {
-    fn stem(&self, value: &str) -> String;
+    /// Returns the stem of the value, or the value itself when its stem is unknown
+    fn stem<'a>(&'a self, value: &'a str) -> &'a str;
 }
 
-pub struct HashMapStemmer {
-    values: HashMap<String, String>
+pub struct CompactStemmer {
+    values: CompactStringMap,
 }
 
-impl HashMapStemmer {
+impl CompactStemmer {
     pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
-        let mut values = HashMap::<String, String>::new();
+        let mut values = vec![];
         let mut csv_reader = csv::ReaderBuilder::new()
             .delimiter(b',')
             .quoting(false)
@@ -31,59 +33,37 @@ impl HashMapStemmer {
             let elements = record?;
             let stem = &elements[0];
             for value in elements.iter().skip(1) {
-                values.insert(value.to_string(), stem.to_string());
+                values.push((value.to_string(), stem.to_string()));
             }
         }
-        Ok(Self { values })
+        Ok(Self { values: values.into_iter().collect() })
     }
 }
 
-impl Stemmer for HashMapStemmer {
-    fn stem(&self, value: &str) -> String {
-        self.values
-            .get(value)
-            .map(|v| v.to_string())
-            .unwrap_or_else(|| value.to_string())
+impl Stemmer for CompactStemmer {
+    fn stem<'a>(&'a self, value: &'a str) -> &'a str {
+        self.values.get(value).unwrap_or(value)
+    }
+}
+
+impl HeapSize for CompactStemmer {
+    fn heap_size(&self) -> usize {
+        self.values.heap_size()
     }
 }
 
 lazy_static! {
-    static ref STEMMERS: Mutex<HashMap<Language, Arc<HashMapStemmer>>> =
-        Mutex::new(HashMap::new());
+    static ref STEMMERS: ResourcesCache<CompactStemmer> = ResourcesCache::new();
 }
 
 pub fn load_stemmer<P: AsRef<Path>>(
-    language: Language,
+    file_system: &FileSystem,
     stems_path: P,
-) -> Result<()> {
-    if STEMMERS.lock().unwrap().contains_key(&language) {
-        return Ok(());
-    }
-    let stems_reader = File::open(stems_path.as_ref())
+) -> Result<Arc<CompactStemmer>> {
+    let content = file_system.read(stems_path.as_ref())
         .with_context(|_|
             format!("Cannot open stems file '{:?}'", stems_path.as_ref()))?;
-    let stemmer = HashMapStemmer::from_reader(stems_reader)?;
-    STEMMERS
-        .lock()
-        .unwrap()
-        .entry(language)
-        .or_insert_with(|| Arc::new(stemmer));
-    Ok(())
-}
-
-pub fn get_stemmer(language: Language) -> Option<Arc<HashMapStemmer>> {
-    STEMMERS
-        .lock()
-        .unwrap()
-        .get(&language)
-        .cloned()
-}
-
-pub fn clear_stemmers() {
-    STEMMERS
-        .lock()
-        .unwrap()
-        .clear();
+    STEMMERS.get_or_load(&content, |content| CompactStemmer::from_reader(content))
 }
 
 #[cfg(test)]
@@ -91,21 +71,21 @@ mod tests {
     use super::*;
 
     #[test]
-    fn hashmap_stemmer_works() {
+    fn compact_stemmer_works() {
         // Given
         let stems: &[u8] = r#"
 investigate,investigated,investigation,"investigate
 do,done,don't,doing,did,does"#.as_ref();
 
         // When
-        let stemmer = HashMapStemmer::from_reader(stems);
+        let stemmer = CompactStemmer::from_reader(stems);
 
         // Then
         assert!(stemmer.is_ok());
         let stemmer = stemmer.unwrap();
-        assert_eq!(stemmer.stem("don't"), "do".to_string());
-        assert_eq!(stemmer.stem("does"), "do".to_string());
-        assert_eq!(stemmer.stem("\"investigate"), "investigate".to_string());
-        assert_eq!(stemmer.stem("unknown"), "unknown".to_string());
+        assert_eq!(stemmer.stem("don't"), "do");
+        assert_eq!(stemmer.stem("does"), "do");
+        assert_eq!(stemmer.stem("\"investigate"), "investigate");
+        assert_eq!(stemmer.stem("unknown"), "unknown");
     }
 }
@@ -1,66 +1,79 @@
-use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::iter::FromIterator;
 use std::ops::Range;
 use std::path::Path;
-use std::str::FromStr;
 use std::sync;
 
-use crfsuite::Tagger as CRFSuiteTagger;
 use itertools::Itertools;
 
-use builtin_entity_parsing::{BuiltinEntityParserFactory, CachingBuiltinEntityParser};
+use builtin_entity_parsing::CachingBuiltinEntityParser;
 use errors::*;
+use explanation::{SlotFillingExplanation, TaggedToken};
 use failure::ResultExt;
-use language::FromLanguage;
+use language::{parse_language, FromLanguage};
+use memory::HeapSize;
 use models::SlotFillerModel;
+use models::migration::load_model;
 use nlu_utils::language::Language as NluUtilsLanguage;
 use nlu_utils::range::ranges_overlap;
 use nlu_utils::string::substring_with_char_range;
-use nlu_utils::token::{tokenize, Token};
-use serde_json;
+use nlu_utils::token::{tokenize, tokenize_light, Token};
+use resources::SharedResources;
+use crfsuite::Tagger as CRFSuiteTagger;
+use slot_filler::crf_tagger_pool::TaggerPool;
 use slot_filler::crf_utils::*;
 use slot_filler::feature_processor::ProbabilisticFeatureProcessor;
 use slot_filler::SlotFiller;
 use slot_utils::*;
 use snips_nlu_ontology::{BuiltinEntity, BuiltinEntityKind, Language};
-use utils::FromPath;
+use utils::{FromPath, PreparedUpdate};
+use vfs::FileSystem;
 
 use utils::{EntityName, SlotName};
 
 pub struct CRFSlotFiller {
     language: Language,
     tagging_scheme: TaggingScheme,
-    tagger: Option<sync::Mutex<CRFSuiteTagger>>,
+    tagger: Option<TaggerPool>,
     feature_processor: ProbabilisticFeatureProcessor,
     slot_name_mapping: HashMap<SlotName, EntityName>,
     builtin_entity_parser: sync::Arc<CachingBuiltinEntityParser>,
 }
 
 impl FromPath for CRFSlotFiller {
-    fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+    fn from_vfs<P: AsRef<Path>>(
+        file_system: &FileSystem,
+        path: P,
+        shared_resources: sync::Arc<SharedResources>,
+    ) -> Result<Self> {
         let slot_filler_model_path = path.as_ref().join("slot_filler.json");
-        let model_file = fs::File::open(&slot_filler_model_path)
+        let model_file = file_system.open(&slot_filler_model_path)
             .with_context(|_| format!("Cannot open CRFSlotFiller file '{:?}'",
                                       &slot_filler_model_path))?;
-        let model: SlotFillerModel = serde_json::from_reader(model_file)
-            .with_context(|_| "Cannot deserialize CRFSlotFiller json data")?;
+        let model: SlotFillerModel =
+            load_model(model_file, &slot_filler_model_path, &shared_resources.model_version)
+            .with_context(|_|
+                SnipsNluError::InvalidJson(slot_filler_model_path.to_string_lossy().into_owned()))?;
 
         let tagging_scheme = TaggingScheme::from_u8(model.config.tagging_scheme)?;
         let slot_name_mapping = model.slot_name_mapping;
-        let feature_processor =
-            ProbabilisticFeatureProcessor::new(&model.config.feature_factory_configs)?;
+        let feature_processor = ProbabilisticFeatureProcessor::new(
+            &model.config.feature_factory_configs,
+            shared_resources.clone(),
+        )?;
         let tagger = if let Some(crf_model_file) = model.crf_model_file.as_ref() {
             let crf_path = path.as_ref().join(crf_model_file);
-            let tagger = CRFSuiteTagger::create_from_file(&crf_path)
-                .with_context(|_| format!("Cannot create CRFSuiteTagger from file '{:?}'",
-                                          &crf_path))?;
-            Some(sync::Mutex::new(tagger))
+            let crf_model_data = file_system.read(&crf_path)
+                .with_context(|_| format!("Cannot read CRF model file '{:?}'", &crf_path))?;
+            let tagger = TaggerPool::new(crf_model_data)
+                .with_context(|_|
+                    SnipsNluError::CrfModelLoad(crf_path.to_string_lossy().into_owned()))?;
+            Some(tagger)
         } else {
             None
         };
-        let language = Language::from_str(&model.language_code)?;
-        let builtin_entity_parser = BuiltinEntityParserFactory::get(language);
+        let language = parse_language(&model.language_code)?;
+        let builtin_entity_parser = shared_resources.builtin_entity_parser.clone();
 
         Ok(Self {
             language,
@@ -79,73 +92,134 @@ impl SlotFiller for CRFSlotFiller {
     }
 
     fn get_slots(&self, text: &str) -> Result<Vec<InternalSlot>> {
-        if let Some(ref tagger) = self.tagger {
-            let tokens = tokenize(text, NluUtilsLanguage::from_language(self.language));
-            if tokens.is_empty() {
-                return Ok(vec![]);
-            }
-            let features = self.feature_processor.compute_features(&&*tokens);
+        Ok(self.get_slots_explained(text)?.0)
+    }
+
+    fn get_slots_explained(
+        &self,
+        text: &str,
+    ) -> Result<(Vec<InternalSlot>, SlotFillingExplanation)> {
+        let tagger = match self.tagger {
+            Some(ref tagger) => tagger,
+            None => return Ok((vec![], SlotFillingExplanation::default())),
+        };
+        let tokens = tokenize(text, NluUtilsLanguage::from_language(self.language));
+        if tokens.is_empty() {
+            return Ok((vec![], SlotFillingExplanation::default()));
+        }
+        let features = self.feature_processor.compute_features(&&*tokens);
+        // The same tagger is kept until the slots are scored, as tagging sets the features from
+        // which the marginals are computed
+        let tagger = tagger.get()?;
+        let tags = tagger
+            .tag(&features)?
+            .into_iter()
+            .map(|tag| decode_tag(&*tag))
+            .collect::<Result<Vec<String>>>()?;
+
+        let builtin_slot_names_iter = self.slot_name_mapping.iter().filter_map(
+            |(slot_name, entity)| {
+                BuiltinEntityKind::from_identifier(entity)
+                    .ok()
+                    .map(|_| slot_name.to_string())
+            },
+        );
+        let slots = tags_to_slots(
+            text,
+            &tokens,
+            &tags,
+            self.tagging_scheme,
+            &self.slot_name_mapping,
+        )?;
+        let mut explanation = SlotFillingExplanation {
+            tokens: tokens.iter().map(|token| token.value.clone()).collect(),
+            tags: tags.clone(),
+            ..SlotFillingExplanation::default()
+        };
+
+        let builtin_slot_names = HashSet::from_iter(builtin_slot_names_iter);
+
+        if builtin_slot_names.is_empty() {
+            let slots = add_score_upper_bounds(&*tagger, &tokens, &tags, slots)?;
+            return Ok((slots, explanation));
+        }
+
+        let updated_tags = replace_builtin_tags(tags, &builtin_slot_names);
+
+        let builtin_slots = self.slot_name_mapping
+            .iter()
+            .filter_map(|(slot_name, entity)| {
+                BuiltinEntityKind::from_identifier(entity)
+                    .ok()
+                    .map(|kind| (slot_name.clone(), kind))
+            })
+            .collect_vec();
+
+        let (augmented_slots, augmented_tags) = augment_slots(
+            text,
+            &tokens,
+            &updated_tags,
+            self,
+            &self.slot_name_mapping,
+            &self.builtin_entity_parser,
+            &builtin_slots,
+        )?;
+        explanation.added_builtin_slots = builtin_slots_difference(&augmented_slots, &slots);
+        explanation.dropped_builtin_slots = builtin_slots_difference(&slots, &augmented_slots);
+        let augmented_slots =
+            add_score_upper_bounds(&*tagger, &tokens, &augmented_tags, augmented_slots)?;
+        Ok((augmented_slots, explanation))
+    }
+
+    fn tag_tokens(&self, text: &str) -> Result<Vec<TaggedToken>> {
+        let tokens = tokenize(text, NluUtilsLanguage::from_language(self.language));
+        if tokens.is_empty() {
+            return Ok(vec![]);
+        }
+        let features = self.feature_processor.compute_features(&&*tokens);
+        let (tags, marginals) = if let Some(ref tagger) = self.tagger {
+            let tagger = tagger.get()?;
             let tags = tagger
-                .lock()
-                .map_err(|e| format_err!("Poisonous mutex: {}", e))?
                 .tag(&features)?
                 .into_iter()
                 .map(|tag| decode_tag(&*tag))
                 .collect::<Result<Vec<String>>>()?;
-
-            let builtin_slot_names_iter = self.slot_name_mapping.iter().filter_map(
-                |(slot_name, entity)| {
-                    BuiltinEntityKind::from_identifier(entity)
-                        .ok()
-                        .map(|_| slot_name.to_string())
-                },
-            );
-            let slots = tags_to_slots(
-                text,
-                &tokens,
-                &tags,
-                self.tagging_scheme,
-                &self.slot_name_mapping,
-            )?;
-
-            let builtin_slot_names = HashSet::from_iter(builtin_slot_names_iter);
-
-            if builtin_slot_names.is_empty() {
-                return Ok(slots);
-            }
-
-            let updated_tags = replace_builtin_tags(tags, &builtin_slot_names);
-
-            let builtin_slots = self.slot_name_mapping
-                .iter()
-                .filter_map(|(slot_name, entity)| {
-                    BuiltinEntityKind::from_identifier(entity)
-                        .ok()
-                        .map(|kind| (slot_name.clone(), kind))
+            // Tagging sets the instance from which the marginals are computed
+            let labels = tagger.labels()?;
+            let marginals = (0..tokens.len())
+                .map(|index| {
+                    labels
+                        .iter()
+                        .map(|label| Ok((decode_tag(label)?, tagger.marginal(label, index)?)))
+                        .collect::<Result<BTreeMap<String, f64>>>()
                 })
-                .collect_vec();
-
-            let augmented_slots = augment_slots(
-                text,
-                &tokens,
-                &updated_tags,
-                self,
-                &self.slot_name_mapping,
-                &self.builtin_entity_parser,
-                &builtin_slots,
-            )?;
-            Ok(augmented_slots)
+                .collect::<Result<Vec<_>>>()?;
+            (tags, marginals)
         } else {
-            Ok(vec![])
-        }
+            // No tagger defined corresponds to an intent without slots
+            let marginals: BTreeMap<String, f64> = Some((OUTSIDE.to_string(), 1.0))
+                .into_iter()
+                .collect();
+            (vec![OUTSIDE.to_string(); tokens.len()], vec![marginals; tokens.len()])
+        };
+        Ok(tokens
+            .into_iter()
+            .zip(features.into_iter())
+            .zip(tags.into_iter().zip(marginals.into_iter()))
+            .map(|((token, features), (tag, marginals))| TaggedToken {
+                value: token.value,
+                char_range: token.char_range,
+                features: features.into_iter().collect(),
+                tag,
+                marginals,
+            })
+            .collect())
     }
 
     fn get_sequence_probability(&self, tokens: &[Token], tags: Vec<String>) -> Result<f64> {
         if let Some(ref tagger) = self.tagger {
             let features = self.feature_processor.compute_features(&tokens);
-            let tagger = tagger
-                .lock()
-                .map_err(|e| format_err!("poisonous mutex: {}", e))?;
+            let tagger = tagger.get()?;
             let tagger_labels = tagger
                 .labels()?
                 .into_iter()
@@ -173,6 +247,27 @@ impl SlotFiller for CRFSlotFiller {
                 .unwrap_or(1.0))
         }
     }
+
+    fn prepare_entity_utterances<'a>(
+        &'a self,
+        entity: &str,
+        utterances: &[String],
+    ) -> Box<PreparedUpdate + 'a> {
+        let language = NluUtilsLanguage::from_language(self.language);
+        let tokenized_utterances = utterances
+            .iter()
+            .map(|utterance| tokenize_light(utterance, language))
+            .collect_vec();
+        self.feature_processor.prepare_entity_values(entity, &tokenized_utterances)
+    }
+}
+
+impl HeapSize for CRFSlotFiller {
+    fn heap_size(&self) -> usize {
+        self.tagger.as_ref().map(|tagger| tagger.heap_size()).unwrap_or(0)
+            + self.feature_processor.heap_size()
+            + self.slot_name_mapping.heap_size()
+    }
 }
 
 impl CRFSlotFiller {
@@ -185,16 +280,62 @@ impl CRFSlotFiller {
     }
 }
 
-// We need to use base64 encoding to ensure ascii encoding because of encoding issues in
-// python-crfsuite
-
-fn decode_tag(tag: &str) -> Result<String> {
-    let bytes = ::base64::decode(tag)?;
-    Ok(String::from_utf8(bytes)?)
+/// Sets the score upper bound of each slot to the lowest marginal probability among the tags of its
+/// tokens, where the tags are the ones from which the slots have been built. The tagger must have
+/// tagged the tokens beforehand, so that the marginals correspond to this tagging.
+fn add_score_upper_bounds(
+    tagger: &CRFSuiteTagger,
+    tokens: &[Token],
+    tags: &[String],
+    slots: Vec<InternalSlot>,
+) -> Result<Vec<InternalSlot>> {
+    if slots.is_empty() {
+        return Ok(slots);
+    }
+    let tagger_labels = tagger.labels()?;
+    score_slots(tokens, tags, slots, |tag, index| {
+        let label = encode_tag(tag);
+        // Tags which were not seen during training cannot be scored by the CRF
+        if tagger_labels.contains(&label) {
+            Ok(Some(tagger.marginal(&label, index)?))
+        } else {
+            Ok(None)
+        }
+    })
 }
 
-fn encode_tag(tag: &str) -> String {
-    ::base64::encode(tag)
+fn score_slots<F>(
+    tokens: &[Token],
+    tags: &[String],
+    slots: Vec<InternalSlot>,
+    marginal: F,
+) -> Result<Vec<InternalSlot>>
+where
+    F: Fn(&str, usize) -> Result<Option<f64>>,
+{
+    let slots_ranges = slots.iter().map(|slot| slot.char_range.clone()).collect_vec();
+    let tokens_indexes = spans_to_tokens_indexes(&slots_ranges, tokens);
+    slots
+        .into_iter()
+        .zip(tokens_indexes.into_iter())
+        .map(|(slot, indexes)| {
+            let mut score_upper_bound: Option<f64> = None;
+            for index in indexes {
+                match marginal(&tags[index], index)? {
+                    Some(tag_marginal) => {
+                        score_upper_bound = Some(
+                            score_upper_bound.map_or(tag_marginal, |score| score.min(tag_marginal)),
+                        )
+                    }
+                    None => return Ok(InternalSlot { score_upper_bound: None, ..slot }),
+                }
+            }
+            Ok(InternalSlot {
+                score_upper_bound: score_upper_bound.map(|score| score as f32),
+                ..slot
+            })
+        })
+        .collect()
 }
 
 fn filter_overlapping_builtins(
@@ -222,7 +363,7 @@ fn augment_slots(
     intent_slots_mapping: &HashMap<SlotName, EntityName>,
     builtin_entity_parser: &sync::Arc<CachingBuiltinEntityParser>,
     missing_slots: &[(String, BuiltinEntityKind)],
-) -> Result<Vec<InternalSlot>> {
+) -> Result<(Vec<InternalSlot>, Vec<String>)> {
     let builtin_entities = missing_slots
         .iter()
         .map(|&(_, kind)| kind)
@@ -284,11 +425,8 @@ fn augment_slots(
         .into_iter()
         .flat_map(|entities| entities)
         .collect();
-    Ok(reconciliate_builtin_slots(
-        text,
-        slots,
-        filtered_builtin_entities,
-    ))
+    let reconciliated_slots = reconciliate_builtin_slots(text, slots, filtered_builtin_entities);
+    Ok((reconciliated_slots, best_updated_tags))
 }
 
 fn reconciliate_builtin_slots(
@@ -321,6 +459,7 @@ fn reconciliate_builtin_slots(
                             char_range: be.range.clone(),
                             entity: slot.entity.clone(),
                             slot_name: slot.slot_name.clone(),
+                            score_upper_bound: slot.score_upper_bound,
                         })
                         .unwrap_or_else(|| slot.clone())
                 })
@@ -329,6 +468,23 @@ fn reconciliate_builtin_slots(
         .collect()
 }
 
+/// Builtin slots of `slots` which are not part of `other_slots`
+fn builtin_slots_difference(
+    slots: &[InternalSlot],
+    other_slots: &[InternalSlot],
+) -> Vec<InternalSlot> {
+    slots
+        .iter()
+        .filter(|slot| BuiltinEntityKind::from_identifier(&slot.entity).is_ok())
+        .filter(|slot| {
+            !other_slots.iter().any(|other| {
+                other.slot_name == slot.slot_name && other.char_range == slot.char_range
+            })
+        })
+        .cloned()
+        .collect()
+}
+
 fn disambiguate_builtin_entities(builtin_entities: Vec<BuiltinEntity>) -> Vec<BuiltinEntity> {
     if builtin_entities.is_empty() {
         return builtin_entities;
@@ -388,7 +544,8 @@ mod tests {
     use nlu_utils::language::Language as NluUtilsLanguage;
     use snips_nlu_ontology::{Grain, InstantTimeValue, Language, NumberValue, Precision, SlotValue};
     use utils::file_path;
-    use resources::loading::load_resources;
+    use resources::loading::load_shared_resources;
+    use testutils::without_score_upper_bounds;
 
     #[derive(Debug, Fail)]
     pub enum TestError {
@@ -414,6 +571,12 @@ mod tests {
         }
     }
 
+    impl HeapSize for TestSlotFiller {
+        fn heap_size(&self) -> usize {
+            0
+        }
+    }
+
     impl SlotFiller for TestSlotFiller {
         fn get_tagging_scheme(&self) -> TaggingScheme {
             TaggingScheme::BIO
@@ -423,6 +586,17 @@ mod tests {
             Ok(vec![])
         }
 
+        fn get_slots_explained(
+            &self,
+            _text: &str,
+        ) -> Result<(Vec<InternalSlot>, SlotFillingExplanation)> {
+            Ok((vec![], SlotFillingExplanation::default()))
+        }
+
+        fn tag_tokens(&self, _text: &str) -> Result<Vec<TaggedToken>> {
+            Ok(vec![])
+        }
+
         fn get_sequence_probability(&self, _: &[Token], tags: Vec<String>) -> Result<f64> {
             self.tags_list
                 .iter()
@@ -430,10 +604,22 @@ mod tests {
                 .map(|(i, _)| self.tags_probabilities[i])
                 .ok_or(TestError::UnknownTags(tags).into())
         }
+
+        fn prepare_entity_utterances<'a>(
+            &'a self,
+            _entity: &str,
+            _utterances: &[String],
+        ) -> Box<PreparedUpdate + 'a> {
+            Box::new(Vec::<Box<PreparedUpdate>>::new())
+        }
     }
 
     impl FromPath for TestSlotFiller {
-        fn from_path<P: AsRef<Path>>(_path: P) -> Result<Self> {
+        fn from_vfs<P: AsRef<Path>>(
+            _file_system: &FileSystem,
+            _path: P,
+            _shared_resources: sync::Arc<SharedResources>,
+        ) -> Result<Self> {
             unimplemented!()
         }
     }
@@ -450,10 +636,10 @@ mod tests {
             .join("slot_filler_MakeCoffee");
 
         let resources_path = trained_engine_path.join("resources");
-        load_resources(resources_path).unwrap();
+        let shared_resources = load_shared_resources(resources_path, Language::EN).unwrap();
 
         // When
-        let slot_filler = CRFSlotFiller::from_path(slot_filler_path).unwrap();
+        let slot_filler = CRFSlotFiller::from_path(slot_filler_path, shared_resources).unwrap();
         let slots = slot_filler.get_slots("make me two cups of coffee").unwrap();
 
         // Then
@@ -462,10 +648,13 @@ mod tests {
                 value: "two".to_string(),
                 char_range: 8..11,
                 entity: "snips/number".to_string(),
-                slot_name: "number_of_cups".to_string()
+                slot_name: "number_of_cups".to_string(),
+                score_upper_bound: None,
             }
         ];
-        assert_eq!(expected_slots, slots);
+        let score_upper_bound = slots[0].score_upper_bound.unwrap();
+        assert!(score_upper_bound > 0.0 && score_upper_bound <= 1.0);
+        assert_eq!(expected_slots, without_score_upper_bounds(slots));
     }
 
     #[test]
@@ -573,14 +762,15 @@ mod tests {
             "start_date".to_string() => "snips/datetime".to_string(),
             "end_date".to_string() => "snips/datetime".to_string(),
         };
-        let builtin_entity_parser = BuiltinEntityParserFactory::get(Language::EN);
+        let builtin_entity_parser =
+            sync::Arc::new(CachingBuiltinEntityParser::new(Language::EN, 1000));
         let missing_slots = vec![
             ("start_date".to_string(), BuiltinEntityKind::Time),
             ("end_date".to_string(), BuiltinEntityKind::Time),
         ];
 
         // When
-        let augmented_slots = augment_slots(
+        let (augmented_slots, augmented_tags) = augment_slots(
             text,
             &*tokens,
             &tags,
@@ -597,9 +787,48 @@ mod tests {
                 char_range: 33..42,
                 entity: "snips/datetime".to_string(),
                 slot_name: "end_date".to_string(),
+                score_upper_bound: None,
             },
         ];
+        let expected_tags = tags_list[4].iter().map(|tag| tag.to_string()).collect_vec();
         assert_eq!(expected_slots, augmented_slots);
+        assert_eq!(expected_tags, augmented_tags);
+    }
+
+    #[test]
+    fn score_slots_works() {
+        // Given
+        let language = NluUtilsLanguage::EN;
+        let tokens = tokenize("book a table in paris tomorrow", language);
+        let tags = vec!["O", "O", "O", "O", "B-city", "B-date"]
+            .into_iter()
+            .map(|tag| tag.to_string())
+            .collect_vec();
+        let slot = |value: &str, char_range: Range<usize>, slot_name: &str| InternalSlot {
+            value: value.to_string(),
+            char_range,
+            entity: slot_name.to_string(),
+            slot_name: slot_name.to_string(),
+            score_upper_bound: None,
+        };
+        let slots = vec![
+            slot("in paris", 13..21, "city"),
+            slot("tomorrow", 22..30, "date"),
+            slot("", 30..30, "date"),
+        ];
+        let marginals = [0.9, 0.9, 0.9, 0.7, 0.6, 0.8];
+
+        // When
+        let scored_slots = score_slots(&tokens, &tags, slots, |tag, index| {
+            Ok(if tag == "B-date" { None } else { Some(marginals[index]) })
+        }).unwrap();
+
+        // Then
+        let score_upper_bounds = scored_slots
+            .into_iter()
+            .map(|slot| slot.score_upper_bound)
+            .collect_vec();
+        assert_eq!(vec![Some(0.6), None, None], score_upper_bounds);
     }
 
     #[test]
@@ -630,6 +859,7 @@ mod tests {
                 char_range: 0..16,
                 entity: BuiltinEntityKind::Time.identifier().to_string(),
                 slot_name: "datetime".to_string(),
+                score_upper_bound: None,
             },
         ];
         let builtin_entities = vec![
@@ -655,6 +885,7 @@ mod tests {
                 char_range: 0..17,
                 entity: BuiltinEntityKind::Time.identifier().to_string(),
                 slot_name: "datetime".to_string(),
+                score_upper_bound: None,
             },
         ];
         assert_eq!(expected_slots, reconciliated_slots);
@@ -0,0 +1,102 @@
+use std::ops::Deref;
+
+use crfsuite::Tagger as CRFSuiteTagger;
+use crossbeam_queue::SegQueue;
+
+use errors::*;
+use memory::HeapSize;
+
+/// Pool of CRFSuite taggers built from the same CRF model
+///
+/// A tagger cannot be used by several threads at the same time, hence each caller checks out its
+/// own tagger from the pool. Taggers are created on demand and put back into the pool once
+/// released, so that the pool ends up holding one tagger per concurrent worker. The pool relies
+/// on a lock-free queue, hence concurrent callers never wait for each other.
+pub struct TaggerPool {
+    // Taggers may reference the model data, which is why they must be dropped first
+    taggers: SegQueue<CRFSuiteTagger>,
+    model_data: Vec<u8>,
+}
+
+impl TaggerPool {
+    pub fn new(model_data: Vec<u8>) -> Result<Self> {
+        // The first tagger is created eagerly so that an invalid model is detected when loading
+        let tagger = CRFSuiteTagger::create_from_memory(&model_data)?;
+        let taggers = SegQueue::new();
+        taggers.push(tagger);
+        Ok(Self {
+            taggers,
+            model_data,
+        })
+    }
+
+    pub fn get(&self) -> Result<PooledTagger> {
+        let tagger = if let Ok(tagger) = self.taggers.pop() {
+            tagger
+        } else {
+            CRFSuiteTagger::create_from_memory(&self.model_data)?
+        };
+        Ok(PooledTagger {
+            pool: self,
+            tagger: Some(tagger),
+        })
+    }
+}
+
+/// Tagger checked out from a `TaggerPool`, which goes back to the pool when dropped
+pub struct PooledTagger<'a> {
+    pool: &'a TaggerPool,
+    tagger: Option<CRFSuiteTagger>,
+}
+
+/// Only the model data is accounted for, the memory of the taggers being allocated by CRFSuite
+impl HeapSize for TaggerPool {
+    fn heap_size(&self) -> usize {
+        self.model_data.capacity()
+    }
+}
+
+impl<'a> Deref for PooledTagger<'a> {
+    type Target = CRFSuiteTagger;
+
+    fn deref(&self) -> &CRFSuiteTagger {
+        self.tagger.as_ref().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledTagger<'a> {
+    fn drop(&mut self) {
+        if let Some(tagger) = self.tagger.take() {
+            self.pool.taggers.push(tagger);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use utils::file_path;
+
+    #[test]
+    fn should_put_released_taggers_back_into_pool() {
+        // Given
+        let model_path = file_path("tests")
+            .join("models")
+            .join("trained_engine")
+            .join("probabilistic_intent_parser")
+            .join("slot_filler_MakeCoffee")
+            .join("modelqno05sgl.crfsuite");
+        let tagger_pool = TaggerPool::new(fs::read(model_path).unwrap()).unwrap();
+
+        // When
+        {
+            let _first_tagger = tagger_pool.get().unwrap();
+            let _second_tagger = tagger_pool.get().unwrap();
+            assert_eq!(0, tagger_pool.taggers.len());
+        }
+
+        // Then
+        assert_eq!(2, tagger_pool.taggers.len());
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crfsuite::Trainer as CRFSuiteTrainer;
+use itertools::Itertools;
+use serde_json;
+use serde_json::Value;
+
+use data_augmentation::augment_utterances;
+use dataset::{Dataset, Entity, Utterance};
+use errors::*;
+use failure::ResultExt;
+use language::{parse_language, FromLanguage};
+use models::{CrfArgs, FeatureFactory, ProcessingUnitMetadata, SlotFillerConfiguration,
+             SlotFillerModel};
+use nlu_utils::language::Language as NluUtilsLanguage;
+use nlu_utils::string::normalize;
+use nlu_utils::token::{tokenize, tokenize_light, Token};
+use resources::SharedResources;
+use resources::stemmer::Stemmer;
+use slot_filler::crf_utils::{encode_tag, positive_tagging, TaggingScheme, OUTSIDE};
+use slot_filler::feature_processor::ProbabilisticFeatureProcessor;
+use utils::{seeded_rng, EntityName, SlotName};
+
+const CRF_MODEL_FILENAME: &str = "model.crfsuite";
+
+/// Configuration of the `CRFSlotFiller` training, which can be read from the `config` of a
+/// `slot_filler.json` file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CRFSlotFillerConfig {
+    pub tagging_scheme: u8,
+    /// Feature factories, whose `collections` and `entity_labels` arguments, for entity matches
+    /// and builtin entity matches respectively, are fitted on the dataset when missing
+    pub feature_factory_configs: Vec<FeatureFactory>,
+    #[serde(default)]
+    pub crf_args: CrfArgs,
+    #[serde(default)]
+    pub data_augmentation_config: SlotFillerDataAugmentationConfig,
+    #[serde(default)]
+    pub random_seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SlotFillerDataAugmentationConfig {
+    /// Minimum number of training utterances, which is reached by generating utterances
+    pub min_utterances: usize,
+}
+
+impl Default for SlotFillerDataAugmentationConfig {
+    fn default() -> Self {
+        Self { min_utterances: 200 }
+    }
+}
+
+/// Trains the slot filler of an intent, and writes it in the directory layout expected by
+/// `build_slot_filler`
+pub struct CRFSlotFillerTrainer {
+    config: CRFSlotFillerConfig,
+}
+
+impl CRFSlotFillerTrainer {
+    pub fn new(config: CRFSlotFillerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn fit<P: AsRef<Path>>(
+        &self,
+        dataset: &Dataset,
+        intent_name: &str,
+        shared_resources: Arc<SharedResources>,
+        path: P,
+    ) -> Result<SlotFillerModel> {
+        let path = path.as_ref();
+        let language = parse_language(&dataset.language)?;
+        let nlu_utils_language = NluUtilsLanguage::from_language(language);
+        let tagging_scheme = TaggingScheme::from_u8(self.config.tagging_scheme)?;
+        let intent = dataset
+            .intents
+            .get(intent_name)
+            .ok_or_else(|| SnipsNluError::UnknownIntent(intent_name.to_string()))?;
+        let slot_name_mapping = get_slot_name_mapping(&intent.utterances);
+        let feature_factory_configs = fit_feature_factories(
+            &self.config.feature_factory_configs,
+            dataset,
+            &slot_name_mapping,
+            &shared_resources,
+        )?;
+
+        fs::create_dir_all(path)
+            .with_context(|_| format!("Cannot create slot filler directory {:?}", path))?;
+        let crf_model_file = if slot_name_mapping.is_empty() {
+            // No tagger is needed when the intent has no slot
+            None
+        } else {
+            let feature_processor = ProbabilisticFeatureProcessor::new(
+                &feature_factory_configs,
+                shared_resources,
+            )?;
+            let mut rng = seeded_rng(self.config.random_seed);
+            let utterances = augment_utterances(
+                dataset,
+                intent_name,
+                self.config.data_augmentation_config.min_utterances,
+                &mut rng,
+            );
+            let mut trainer = CRFSuiteTrainer::new(false);
+            trainer.select(&self.config.crf_args.algorithm, "crf1d")?;
+            let crf_parameters = self.config.crf_args.parameters
+                .iter()
+                .sorted_by_key(|&(name, _)| name);
+            for (name, value) in crf_parameters {
+                trainer.set(name, &crf_parameter_value(value))?;
+            }
+            for utterance in utterances {
+                let (tokens, tags) = utterance_to_sample(&utterance, tagging_scheme,
+                                                         nlu_utils_language);
+                if tokens.is_empty() {
+                    continue;
+                }
+                let features = feature_processor.compute_features(&&*tokens);
+                let encoded_tags = tags.iter().map(|tag| encode_tag(tag)).collect_vec();
+                trainer.append(&features, &encoded_tags, 0)?;
+            }
+            let crf_model_path = path.join(CRF_MODEL_FILENAME);
+            trainer.train(&crf_model_path, -1)
+                .with_context(|_| format!("Cannot train CRF model {:?}", crf_model_path))?;
+            Some(CRF_MODEL_FILENAME.to_string())
+        };
+
+        let model = SlotFillerModel {
+            language_code: dataset.language.clone(),
+            intent: intent_name.to_string(),
+            slot_name_mapping,
+            crf_model_file,
+            config: SlotFillerConfiguration {
+                tagging_scheme: self.config.tagging_scheme,
+                feature_factory_configs,
+                crf_args: self.config.crf_args.clone(),
+            },
+        };
+        let model_file = fs::File::create(path.join("slot_filler.json"))?;
+        serde_json::to_writer(model_file, &model)?;
+        let metadata_file = fs::File::create(path.join("metadata.json"))?;
+        serde_json::to_writer(metadata_file, &ProcessingUnitMetadata::CrfSlotFiller)?;
+        Ok(model)
+    }
+}
+
+fn get_slot_name_mapping(utterances: &[Utterance]) -> HashMap<SlotName, EntityName> {
+    utterances
+        .iter()
+        .flat_map(|utterance| utterance.data.iter())
+        .filter_map(|chunk| match (chunk.slot_name.as_ref(), chunk.entity.as_ref()) {
+            (Some(slot_name), Some(entity)) => Some((slot_name.clone(), entity.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Tokenizes the utterance and tags its tokens according to the slots of the utterance
+fn utterance_to_sample(
+    utterance: &Utterance,
+    tagging_scheme: TaggingScheme,
+    language: NluUtilsLanguage,
+) -> (Vec<Token>, Vec<String>) {
+    let text = utterance.text();
+    let tokens = tokenize(&text, language);
+    let mut tags = Vec::with_capacity(tokens.len());
+    let mut chunk_start = 0;
+    for chunk in &utterance.data {
+        let chunk_end = chunk_start + chunk.text.chars().count();
+        let nb_chunk_tokens = tokens
+            .iter()
+            .filter(|token| token.char_range.start >= chunk_start
+                && token.char_range.start < chunk_end)
+            .count();
+        let slot_name = chunk.slot_name.as_ref().map(|name| name.as_str()).unwrap_or(OUTSIDE);
+        tags.extend(positive_tagging(tagging_scheme, slot_name, nb_chunk_tokens));
+        chunk_start = chunk_end;
+    }
+    (tokens, tags)
+}
+
+/// Fills the arguments of the feature factories which depend on the training data
+fn fit_feature_factories(
+    feature_factory_configs: &[FeatureFactory],
+    dataset: &Dataset,
+    slot_name_mapping: &HashMap<SlotName, EntityName>,
+    shared_resources: &SharedResources,
+) -> Result<Vec<FeatureFactory>> {
+    let language = NluUtilsLanguage::from_language(parse_language(&dataset.language)?);
+    let intent_entities: BTreeSet<&EntityName> = slot_name_mapping.values().collect();
+    feature_factory_configs
+        .iter()
+        .map(|factory| {
+            let mut factory = factory.clone();
+            match factory.factory_name.as_ref() {
+                "entity_match" if !factory.args.contains_key("collections") => {
+                    let use_stemming = factory
+                        .args
+                        .get("use_stemming")
+                        .and_then(|use_stemming| use_stemming.as_bool())
+                        .unwrap_or(false);
+                    let stemmer = if use_stemming {
+                        Some(shared_resources.get_stemmer()?)
+                    } else {
+                        None
+                    };
+                    let collections: serde_json::Map<String, Value> = intent_entities
+                        .iter()
+                        .filter_map(|entity_name| dataset
+                            .custom_entity(entity_name)
+                            .map(|entity| (entity_name, entity)))
+                        .map(|(entity_name, entity)| {
+                            let values = entity
+                                .utterances()
+                                .into_iter()
+                                .map(|utterance| tokenize_light(utterance, language)
+                                    .iter()
+                                    .map(|token| match stemmer.as_ref() {
+                                        Some(stemmer) =>
+                                            stemmer.stem(&normalize(token)).to_string(),
+                                        None => normalize(token),
+                                    })
+                                    .join(" "))
+                                .collect::<BTreeSet<_>>()
+                                .into_iter()
+                                .map(Value::from)
+                                .collect_vec();
+                            (entity_name.to_string(), Value::Array(values))
+                        })
+                        .collect();
+                    factory.args.insert("collections".to_string(), Value::Object(collections));
+                }
+                "builtin_entity_match" if !factory.args.contains_key("entity_labels") => {
+                    let entity_labels = dataset
+                        .entities
+                        .iter()
+                        .filter_map(|(name, entity)| match entity {
+                            Entity::Builtin(_) => Some(name),
+                            Entity::Custom(_) => None,
+                        })
+                        .sorted()
+                        .into_iter()
+                        .map(|entity| Value::from(entity.as_str()))
+                        .collect_vec();
+                    factory.args.insert("entity_labels".to_string(), Value::Array(entity_labels));
+                }
+                _ => (),
+            }
+            Ok(factory)
+        })
+        .collect()
+}
+
+fn crf_parameter_value(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Bool(boolean) => if *boolean { "1".to_string() } else { "0".to_string() },
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slot_filler::build_slot_filler;
+    use snips_nlu_ontology::Language;
+    use rand::random;
+    use std::env;
+    use std::process;
+    use vfs::LocalFileSystem;
+
+    fn sample_dataset() -> Dataset {
+        serde_json::from_str(r#"{
+            "language": "en",
+            "intents": {
+                "MakeTea": {
+                    "utterances": [
+                        {
+                            "data": [
+                                {"text": "make me a "},
+                                {"text": "hot", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " cup of tea"}
+                            ]
+                        },
+                        {
+                            "data": [
+                                {"text": "i want a "},
+                                {"text": "very cold", "entity": "temperature", "slot_name": "temp"},
+                                {"text": " tea"}
+                            ]
+                        }
+                    ]
+                }
+            },
+            "entities": {
+                "temperature": {
+                    "data": [
+                        {"value": "hot", "synonyms": []},
+                        {"value": "very cold", "synonyms": ["iced"]}
+                    ],
+                    "use_synonyms": true,
+                    "automatically_extensible": true
+                },
+                "snips/number": {}
+            }
+        }"#).unwrap()
+    }
+
+    fn sample_config() -> CRFSlotFillerConfig {
+        serde_json::from_str(r#"{
+            "tagging_scheme": 1,
+            "feature_factory_configs": [
+                {
+                    "factory_name": "ngram",
+                    "args": {"n": 1, "use_stemming": false, "common_words_gazetteer_name": null},
+                    "offsets": [-1, 0, 1]
+                },
+                {
+                    "factory_name": "entity_match",
+                    "args": {"use_stemming": false, "tagging_scheme_code": 2},
+                    "offsets": [0]
+                },
+                {
+                    "factory_name": "builtin_entity_match",
+                    "args": {"tagging_scheme_code": 1},
+                    "offsets": [0]
+                }
+            ],
+            "crf_args": {"algorithm": "lbfgs", "c1": 0.1, "c2": 0.1},
+            "data_augmentation_config": {"min_utterances": 20},
+            "random_seed": 42
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn utterance_to_sample_works() {
+        // Given
+        let utterance = &sample_dataset().intents["MakeTea"].utterances[1];
+
+        // When
+        let (tokens, tags) =
+            utterance_to_sample(utterance, TaggingScheme::BIO, NluUtilsLanguage::EN);
+
+        // Then
+        let token_values = tokens.into_iter().map(|token| token.value).collect_vec();
+        assert_eq!(vec!["i", "want", "a", "very", "cold", "tea"], token_values);
+        assert_eq!(vec!["O", "O", "O", "B-temp", "I-temp", "O"], tags);
+    }
+
+    #[test]
+    fn fit_feature_factories_works() {
+        // Given
+        let dataset = sample_dataset();
+        let slot_name_mapping = hashmap!["temp".to_string() => "temperature".to_string()];
+        let shared_resources = SharedResources::new(Language::EN);
+
+        // When
+        let factories = fit_feature_factories(
+            &sample_config().feature_factory_configs,
+            &dataset,
+            &slot_name_mapping,
+            &shared_resources,
+        ).unwrap();
+
+        // Then
+        let expected_collections: Value =
+            serde_json::from_str(r#"{"temperature": ["hot", "iced", "very cold"]}"#).unwrap();
+        let expected_entity_labels: Value = serde_json::from_str(r#"["snips/number"]"#).unwrap();
+        assert_eq!(Some(&expected_collections), factories[1].args.get("collections"));
+        assert_eq!(Some(&expected_entity_labels), factories[2].args.get("entity_labels"));
+    }
+
+    #[test]
+    fn fit_should_produce_loadable_slot_filler() {
+        // Given
+        let dataset = sample_dataset();
+        let shared_resources = Arc::new(SharedResources::new(Language::EN));
+        let path = env::temp_dir().join(format!(
+            "snips_nlu_crf_training_test_{}_{}", process::id(), random::<u32>()));
+        let trainer = CRFSlotFillerTrainer::new(sample_config());
+
+        // When
+        trainer.fit(&dataset, "MakeTea", shared_resources.clone(), &path).unwrap();
+        let slot_filler = build_slot_filler(&LocalFileSystem, &path, shared_resources).unwrap();
+        let slots = slot_filler.get_slots("make me a hot cup of tea").unwrap();
+        fs::remove_dir_all(&path).unwrap();
+
+        // Then
+        assert_eq!(vec!["temp".to_string()],
+                   slots.into_iter().map(|slot| slot.slot_name).collect_vec());
+    }
+}
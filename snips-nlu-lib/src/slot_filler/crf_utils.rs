@@ -29,11 +29,25 @@ impl TaggingScheme {
             0 => Ok(TaggingScheme::IO),
             1 => Ok(TaggingScheme::BIO),
             2 => Ok(TaggingScheme::BILOU),
-            _ => bail!("Unknown tagging scheme identifier: {}", i),
+            _ => Err(SnipsNluError::InvalidModel(
+                format!("unknown tagging scheme identifier: {}", i)).into()),
         }
     }
 }
 
+// We need to use base64 encoding to ensure ascii encoding because of encoding issues in
+// python-crfsuite
+
+/// Decodes a tag of the CRF model, where tags are stored in base64
+pub fn decode_tag(tag: &str) -> Result<String> {
+    let bytes = ::base64::decode(tag)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+pub fn encode_tag(tag: &str) -> String {
+    ::base64::encode(tag)
+}
+
 pub fn get_substitution_label<'a>(labels: &[&'a str]) -> &'a str {
     if labels.contains(&OUTSIDE) {
         OUTSIDE
@@ -218,14 +232,12 @@ pub fn tags_to_slots(
                 entity: intent_slots_mapping
                     .get(&s.slot_name)
                     .ok_or_else(|| {
-                        format_err!(
-                            "Missing slot to entity mapping for slot name: {}",
-                            s.slot_name
-                        )
+                        SnipsNluError::UnknownSlot(s.slot_name.clone())
                     })?
                     .to_string(),
                 char_range: s.char_range,
                 slot_name: s.slot_name,
+                score_upper_bound: None,
             })
         })
         .collect()
@@ -370,6 +382,7 @@ mod tests {
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -387,6 +400,7 @@ mod tests {
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -399,6 +413,7 @@ mod tests {
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -414,6 +429,7 @@ mod tests {
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -432,6 +448,7 @@ mod tests {
                         value: "light blue bird blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -447,6 +464,7 @@ mod tests {
                         value: "bird birdy".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -498,6 +516,7 @@ mod tests {
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -515,6 +534,7 @@ mod tests {
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -527,6 +547,7 @@ mod tests {
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -542,6 +563,7 @@ mod tests {
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -560,12 +582,14 @@ mod tests {
                         value: "light blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                     InternalSlot {
                         char_range: 16..25,
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -581,12 +605,14 @@ mod tests {
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                     InternalSlot {
                         char_range: 5..10,
                         value: "birdy".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -605,12 +631,14 @@ mod tests {
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                     InternalSlot {
                         char_range: 14..24,
                         value: "white bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -662,6 +690,7 @@ mod tests {
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -679,6 +708,7 @@ mod tests {
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -691,6 +721,7 @@ mod tests {
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -706,6 +737,7 @@ mod tests {
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -724,12 +756,14 @@ mod tests {
                         value: "light blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                     InternalSlot {
                         char_range: 16..25,
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -745,12 +779,14 @@ mod tests {
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                     InternalSlot {
                         char_range: 5..10,
                         value: "birdy".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -769,18 +805,21 @@ mod tests {
                         value: "light bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                     InternalSlot {
                         char_range: 11..15,
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                     InternalSlot {
                         char_range: 16..25,
                         value: "blue bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
@@ -797,18 +836,21 @@ mod tests {
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                     InternalSlot {
                         char_range: 5..9,
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                     InternalSlot {
                         char_range: 10..14,
                         value: "bird".to_string(),
                         entity: slot_name.to_string(),
                         slot_name: slot_name.to_string(),
+                        score_upper_bound: None,
                     },
                 ],
             },
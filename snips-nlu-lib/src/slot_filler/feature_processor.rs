@@ -1,17 +1,20 @@
+use arc_swap::ArcSwap;
 use itertools::Itertools;
 use std::collections::HashMap;
-use std::str::FromStr;
+use std::mem;
 
-use builtin_entity_parsing::BuiltinEntityParserFactory;
 use super::crf_utils::TaggingScheme;
 use super::features;
 use models::FeatureFactory;
 use errors::*;
+use memory::HeapSize;
+use nlu_utils::string::normalize;
 use nlu_utils::token::Token;
-use resources::gazetteer::{get_gazetteer, HashSetGazetteer};
-use resources::stemmer::get_stemmer;
-use resources::word_clusterer::get_word_clusterer;
-use snips_nlu_ontology::{BuiltinEntityKind, Language};
+use resources::SharedResources;
+use resources::gazetteer::CompactGazetteer;
+use resources::stemmer::{CompactStemmer, Stemmer};
+use snips_nlu_ontology::BuiltinEntityKind;
+use utils::{PreparedUpdate, SwapUpdate};
 use std::sync::Arc;
 
 pub struct ProbabilisticFeatureProcessor {
@@ -19,11 +22,13 @@ pub struct ProbabilisticFeatureProcessor {
 }
 
 impl ProbabilisticFeatureProcessor {
-    // TODO add a `GazetteerProvider` to this signature
-    pub fn new(features: &[FeatureFactory]) -> Result<ProbabilisticFeatureProcessor> {
+    pub fn new(
+        features: &[FeatureFactory],
+        shared_resources: Arc<SharedResources>,
+    ) -> Result<ProbabilisticFeatureProcessor> {
         let functions = features
             .iter()
-            .map(|f| get_feature_function(f))
+            .map(|f| get_feature_function(f, &shared_resources))
             .collect::<Result<Vec<Vec<_>>>>()?
             .into_iter()
             .flat_map(|fs| fs)
@@ -31,6 +36,36 @@ impl ProbabilisticFeatureProcessor {
 
         Ok(ProbabilisticFeatureProcessor { functions })
     }
+
+    /// Prepares the replacement of the values matched by the `entity_match` feature of the entity,
+    /// where each value is given as the list of its tokens
+    pub fn prepare_entity_values<'a>(
+        &'a self,
+        entity_name: &str,
+        values: &[Vec<String>],
+    ) -> Box<PreparedUpdate + 'a> {
+        let mut updates: Vec<Box<PreparedUpdate + 'a>> = vec![];
+        for entity_gazetteer in self.functions
+            .iter()
+            .filter_map(|f| f.entity_gazetteer.as_ref())
+            .filter(|gazetteer| gazetteer.entity_name == entity_name)
+        {
+            let normalized_values = values
+                .iter()
+                .map(|tokens| tokens
+                    .iter()
+                    .map(|token| match entity_gazetteer.stemmer {
+                        Some(ref stemmer) => stemmer.stem(&normalize(token)).to_string(),
+                        None => normalize(token),
+                    })
+                    .join(" "))
+                .unique()
+                .collect_vec();
+            let gazetteer = CompactGazetteer::from(normalized_values.into_iter());
+            updates.push(Box::new(SwapUpdate::new(&entity_gazetteer.values, gazetteer)));
+        }
+        Box::new(updates)
+    }
 }
 
 impl ProbabilisticFeatureProcessor {
@@ -39,8 +74,14 @@ impl ProbabilisticFeatureProcessor {
         self.functions
             .iter()
             .fold(vec![vec![]; input.len()], |mut acc, f| {
+                // The entity values are loaded once for the whole input, so that they stay the
+                // same for all its tokens
+                let entity_values = f.entity_gazetteer
+                    .as_ref()
+                    .map(|gazetteer| gazetteer.values.load());
+                let entity_values = entity_values.as_ref().map(|values| &**values);
                 (0..input.len()).foreach(|i| {
-                    if let Some(value) = (f.function)(input, i) {
+                    if let Some(value) = (f.function)(input, i, entity_values) {
                         f.offsets.iter().foreach(|&(offset, ref key)| {
                             if i as i32 - offset >= 0 && i as i32 - offset < input.len() as i32 {
                                 acc[(i as i32 - offset) as usize].push(
@@ -55,15 +96,74 @@ impl ProbabilisticFeatureProcessor {
     }
 }
 
+impl HeapSize for ProbabilisticFeatureProcessor {
+    fn heap_size(&self) -> usize {
+        self.functions.capacity() * mem::size_of::<FeatureFunction>()
+            + self.functions
+                .iter()
+                .map(|f| {
+                    f.offsets.heap_size()
+                        + f.entity_gazetteer
+                            .as_ref()
+                            .map_or(0, |gazetteer| gazetteer.values.load().heap_size())
+                })
+                .sum::<usize>()
+    }
+}
+
 struct FeatureFunction {
-    function: Box<Fn(&[Token], usize) -> Option<String> + Send + Sync>,
+    /// Computes the feature of a token, given the current values of the entity gazetteer if any
+    function: Box<Fn(&[Token], usize, Option<&CompactGazetteer>) -> Option<String> + Send + Sync>,
     offsets: Vec<(i32, String)>,
+    /// Gazetteer of the custom entity matched by the function, if any
+    entity_gazetteer: Option<EntityGazetteer>,
+}
+
+/// Values of a custom entity, which can be replaced at runtime when values are added to or removed
+/// from the entity
+struct EntityGazetteer {
+    entity_name: String,
+    stemmer: Option<Arc<CompactStemmer>>,
+    values: ArcSwap<CompactGazetteer>,
 }
 
 impl FeatureFunction {
     fn new<T>(key: &str, offsets: Vec<i32>, function: T) -> FeatureFunction
         where
             T: Fn(&[Token], usize) -> Option<String> + Send + Sync + 'static,
+    {
+        Self::with_optional_gazetteer(key, offsets, None, move |t, i, _| function(t, i))
+    }
+
+    /// Feature function matching the values of a custom entity, which are given by its gazetteer
+    fn with_entity_gazetteer<T>(
+        key: &str,
+        offsets: Vec<i32>,
+        entity_gazetteer: EntityGazetteer,
+        function: T,
+    ) -> FeatureFunction
+        where
+            T: Fn(&[Token], usize, &CompactGazetteer) -> Option<String> + Send + Sync + 'static,
+    {
+        Self::with_optional_gazetteer(
+            key,
+            offsets,
+            Some(entity_gazetteer),
+            move |t, i, gazetteer| gazetteer.and_then(|gazetteer| function(t, i, gazetteer)),
+        )
+    }
+
+    fn with_optional_gazetteer<T>(
+        key: &str,
+        offsets: Vec<i32>,
+        entity_gazetteer: Option<EntityGazetteer>,
+        function: T,
+    ) -> FeatureFunction
+        where
+            T: Fn(&[Token], usize, Option<&CompactGazetteer>) -> Option<String>
+                + Send
+                + Sync
+                + 'static,
     {
         let offsets = offsets
             .into_iter()
@@ -81,25 +181,32 @@ impl FeatureFunction {
         FeatureFunction {
             offsets,
             function: Box::new(function),
+            entity_gazetteer,
         }
     }
 }
 
-fn get_feature_function(f: &FeatureFactory) -> Result<Vec<FeatureFunction>> {
+fn get_feature_function(
+    f: &FeatureFactory,
+    shared_resources: &SharedResources,
+) -> Result<Vec<FeatureFunction>> {
     let offsets = f.offsets.clone();
     match f.factory_name.as_ref() {
         "is_digit" => Ok(vec![is_digit_feature_function(offsets)?]),
         "length" => Ok(vec![length_feature_function(offsets)?]),
         "is_first" => Ok(vec![is_first_feature_function(offsets)?]),
         "is_last" => Ok(vec![is_last_feature_function(offsets)?]),
-        "ngram" => Ok(vec![ngram_feature_function(&f.args, offsets)?]),
+        "ngram" => Ok(vec![ngram_feature_function(&f.args, offsets, shared_resources)?]),
         "shape_ngram" => Ok(vec![shape_ngram_feature_function(&f.args, offsets)?]),
         "prefix" => Ok(vec![prefix_feature_function(&f.args, offsets)?]),
         "suffix" => Ok(vec![suffix_feature_function(&f.args, offsets)?]),
-        "entity_match" => entity_match_feature_function(&f.args, &offsets),
-        "builtin_entity_match" => builtin_entity_match_feature_function(&f.args, &offsets),
-        "word_cluster" => Ok(vec![word_cluster_feature_function(&f.args, offsets)?]),
-        _ => bail!("Feature {} not implemented", f.factory_name),
+        "entity_match" => entity_match_feature_function(&f.args, &offsets, shared_resources),
+        "builtin_entity_match" => builtin_entity_match_feature_function(
+            &f.args, &offsets, shared_resources),
+        "word_cluster" => Ok(vec![
+            word_cluster_feature_function(&f.args, offsets, shared_resources)?
+        ]),
+        _ => Err(SnipsNluError::UnsupportedFeature(f.factory_name.clone()).into()),
     }
 }
 
@@ -130,21 +237,19 @@ fn is_last_feature_function(offsets: Vec<i32>) -> Result<FeatureFunction> {
 fn ngram_feature_function(
     args: &HashMap<String, ::serde_json::Value>,
     offsets: Vec<i32>,
+    shared_resources: &SharedResources,
 ) -> Result<FeatureFunction> {
     let n = parse_as_u64(args, "n")? as usize;
-    let language = Language::from_str(&parse_as_string(args, "language_code")?)?;
     let common_words_gazetteer_name = parse_as_opt_string(args, "common_words_gazetteer_name")?;
     let use_stemming = parse_as_bool(args, "use_stemming")?;
     let common_words_gazetteer: Option<Arc<_>> = if let Some(gazetteer) = common_words_gazetteer_name
-        .map(|name| get_gazetteer(name, language)) {
+        .map(|name| shared_resources.get_gazetteer(&name)) {
         Some(gazetteer?)
     } else {
         None
     };
     let stemmer_opt: Option<Arc<_>> = if use_stemming {
-        let stemmer = get_stemmer(language)
-            .ok_or_else(|| format_err!("No stemmer found for language {:?}", language))?;
-        Some(stemmer)
+        Some(shared_resources.get_stemmer()?)
     } else {
         None
     };
@@ -202,16 +307,14 @@ fn suffix_feature_function(
 fn entity_match_feature_function(
     args: &HashMap<String, ::serde_json::Value>,
     offsets: &[i32],
+    shared_resources: &SharedResources,
 ) -> Result<Vec<FeatureFunction>> {
     let collections = parse_as_vec_of_vec(args, "collections")?;
-    let language = Language::from_str(&parse_as_string(args, "language_code")?)?;
     let tagging_scheme_code = parse_as_u64(args, "tagging_scheme_code")? as u8;
     let use_stemming = parse_as_bool(args, "use_stemming")?;
     let tagging_scheme = TaggingScheme::from_u8(tagging_scheme_code)?;
     let opt_stemmer: Option<Arc<_>> = if use_stemming {
-        let stemmer = get_stemmer(language)
-            .ok_or_else(|| format_err!("No stemmer found for language {:?}", language))?;
-        Some(stemmer)
+        Some(shared_resources.get_stemmer()?)
     } else {
         None
     };
@@ -219,15 +322,20 @@ fn entity_match_feature_function(
         .into_iter()
         .map(|(entity_name, values)| {
             let opt_stemmer_cloned = opt_stemmer.clone();
-            let entity_gazetteer = HashSetGazetteer::from(values.into_iter());
-            Ok(FeatureFunction::new(
+            let entity_gazetteer = EntityGazetteer {
+                entity_name: entity_name.clone(),
+                stemmer: opt_stemmer.clone(),
+                values: ArcSwap::from_pointee(CompactGazetteer::from(values.into_iter())),
+            };
+            Ok(FeatureFunction::with_entity_gazetteer(
                 &format!("entity_match_{}", entity_name),
                 offsets.to_vec(),
-                move |tokens, token_index| {
+                entity_gazetteer,
+                move |tokens, token_index, entity_values| {
                     features::get_gazetteer_match(
                         tokens,
                         token_index,
-                        &entity_gazetteer,
+                        entity_values,
                         opt_stemmer_cloned.as_ref().map(|s| s.as_ref()),
                         tagging_scheme,
                     )
@@ -240,33 +348,29 @@ fn entity_match_feature_function(
 fn builtin_entity_match_feature_function(
     args: &HashMap<String, ::serde_json::Value>,
     offsets: &[i32],
+    shared_resources: &SharedResources,
 ) -> Result<Vec<FeatureFunction>> {
     let builtin_entity_labels = parse_as_vec_string(args, "entity_labels")?;
-    let language_code = parse_as_string(args, "language_code")?;
     let tagging_scheme_code = parse_as_u64(args, "tagging_scheme_code")? as u8;
     let tagging_scheme = TaggingScheme::from_u8(tagging_scheme_code)?;
     builtin_entity_labels
         .into_iter()
         .map(|label| {
-            let builtin_parser = Language::from_str(&language_code)
-                .ok()
-                .map(BuiltinEntityParserFactory::get);
+            let builtin_parser = shared_resources.builtin_entity_parser.clone();
             let builtin_entity_kind = BuiltinEntityKind::from_identifier(&label).ok();
             Ok(FeatureFunction::new(
                 &format!("builtin_entity_match_{}", &label),
                 offsets.to_vec(),
                 move |tokens, token_index| {
-                    if let (Some(parser), Some(builtin_entity_kind)) =
-                    (builtin_parser.as_ref(), builtin_entity_kind)
-                        {
-                            features::get_builtin_entity_match(
-                                tokens,
-                                token_index,
-                                &**parser,
-                                builtin_entity_kind,
-                                tagging_scheme,
-                            )
-                        } else {
+                    if let Some(builtin_entity_kind) = builtin_entity_kind {
+                        features::get_builtin_entity_match(
+                            tokens,
+                            token_index,
+                            &*builtin_parser,
+                            builtin_entity_kind,
+                            tagging_scheme,
+                        )
+                    } else {
                         None
                     }
                 },
@@ -278,10 +382,10 @@ fn builtin_entity_match_feature_function(
 fn word_cluster_feature_function(
     args: &HashMap<String, ::serde_json::Value>,
     offsets: Vec<i32>,
+    shared_resources: &SharedResources,
 ) -> Result<FeatureFunction> {
     let cluster_name = parse_as_string(args, "cluster_name")?;
-    let language = Language::from_str(&parse_as_string(args, "language_code")?)?;
-    let word_clusterer = get_word_clusterer(cluster_name.clone(), language)?;
+    let word_clusterer = shared_resources.get_word_clusterer(&cluster_name)?;
     Ok(FeatureFunction::new(
         &format!("word_cluster_{}", cluster_name),
         offsets,
@@ -291,9 +395,9 @@ fn word_cluster_feature_function(
 
 fn parse_as_string(args: &HashMap<String, ::serde_json::Value>, arg_name: &str) -> Result<String> {
     Ok(args.get(arg_name)
-        .ok_or_else(|| format_err!("can't retrieve '{}' parameter", arg_name))?
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("can't retrieve '{}' parameter", arg_name)))?
         .as_str()
-        .ok_or_else(|| format_err!("'{}' isn't a string", arg_name))?
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("'{}' isn't a string", arg_name)))?
         .to_string())
 }
 
@@ -302,7 +406,7 @@ fn parse_as_opt_string(
     arg_name: &str,
 ) -> Result<Option<String>> {
     Ok(args.get(arg_name)
-        .ok_or_else(|| format_err!("can't retrieve '{}' parameter", arg_name))?
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("can't retrieve '{}' parameter", arg_name)))?
         .as_str()
         .map(|s| s.to_string()))
 }
@@ -312,13 +416,13 @@ fn parse_as_vec_string(
     arg_name: &str,
 ) -> Result<Vec<String>> {
     args.get(arg_name)
-        .ok_or_else(|| format_err!("can't retrieve '{}' parameter", arg_name))?
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("can't retrieve '{}' parameter", arg_name)))?
         .as_array()
-        .ok_or_else(|| format_err!("'{}' isn't an array", arg_name))?
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("'{}' isn't an array", arg_name)))?
         .iter()
         .map(|v| {
             Ok(v.as_str()
-                .ok_or_else(|| format_err!("'{}' is not a string", v))?
+                .ok_or_else(|| SnipsNluError::InvalidModel(format!("'{}' is not a string", v)))?
                 .to_string())
         })
         .collect()
@@ -329,17 +433,17 @@ fn parse_as_vec_of_vec(
     arg_name: &str,
 ) -> Result<Vec<(String, Vec<String>)>> {
     args.get(arg_name)
-        .ok_or_else(|| format_err!("can't retrieve '{}' parameter", arg_name))?
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("can't retrieve '{}' parameter", arg_name)))?
         .as_object()
-        .ok_or_else(|| format_err!("'{}' isn't a map", arg_name))?
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("'{}' isn't a map", arg_name)))?
         .into_iter()
         .map(|(k, v)| {
             let values: Result<Vec<_>> = v.as_array()
-                .ok_or_else(|| format_err!("'{}' is not a vec", v))?
+                .ok_or_else(|| SnipsNluError::InvalidModel(format!("'{}' is not a vec", v)))?
                 .into_iter()
                 .map(|item| {
                     Ok(item.as_str()
-                        .ok_or_else(|| format_err!("'{}' is not a string", item))?
+                        .ok_or_else(|| SnipsNluError::InvalidModel(format!("'{}' is not a string", item)))?
                         .to_string())
                 })
                 .collect();
@@ -350,16 +454,16 @@ fn parse_as_vec_of_vec(
 
 fn parse_as_bool(args: &HashMap<String, ::serde_json::Value>, arg_name: &str) -> Result<bool> {
     Ok(args.get(arg_name)
-        .ok_or_else(|| format_err!("can't retrieve '{}' parameter", arg_name))?
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("can't retrieve '{}' parameter", arg_name)))?
         .as_bool()
-        .ok_or_else(|| format_err!("'{}' isn't a bool", arg_name))?)
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("'{}' isn't a bool", arg_name)))?)
 }
 
 fn parse_as_u64(args: &HashMap<String, ::serde_json::Value>, arg_name: &str) -> Result<u64> {
     Ok(args.get(arg_name)
-        .ok_or_else(|| format_err!("can't retrieve '{}' parameter", arg_name))?
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("can't retrieve '{}' parameter", arg_name)))?
         .as_u64()
-        .ok_or_else(|| format_err!("'{}' isn't a u64", arg_name))?)
+        .ok_or_else(|| SnipsNluError::InvalidModel(format!("'{}' isn't a u64", arg_name)))?)
 }
 
 #[cfg(test)]
@@ -454,4 +558,42 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn prepare_entity_values_works() {
+        // Given
+        let args: HashMap<String, ::serde_json::Value> = ::serde_json::from_str(r#"{
+            "collections": {"beverage": ["coffee", "tea"]},
+            "tagging_scheme_code": 2,
+            "use_stemming": false
+        }"#).unwrap();
+        let shared_resources = SharedResources::new(::snips_nlu_ontology::Language::EN);
+        let fp = ProbabilisticFeatureProcessor {
+            functions: entity_match_feature_function(&args, &[0], &shared_resources).unwrap(),
+        };
+        let tokens = tokenize("a hot Chocolate milk please", Language::EN);
+
+        // When
+        let features_before = fp.compute_features(&tokens.as_slice());
+        let update = fp.prepare_entity_values(
+            "beverage",
+            &[vec!["tea".to_string()], vec!["chocolate".to_string(), "milk".to_string()]],
+        );
+        let features_before_commit = fp.compute_features(&tokens.as_slice());
+        update.commit();
+        let features_after = fp.compute_features(&tokens.as_slice());
+
+        // Then
+        assert!(features_before.iter().all(|token_features| token_features.is_empty()));
+        assert_eq!(features_before, features_before_commit);
+        let key = "entity_match_beverage".to_string();
+        let expected_features = vec![
+            vec![],
+            vec![],
+            vec![(key.clone(), "B-".to_string())],
+            vec![(key, "L-".to_string())],
+            vec![],
+        ];
+        assert_eq!(expected_features, features_after);
+    }
 }
@@ -82,12 +82,11 @@ pub fn ngram<S: Stemmer, G: Gazetteer>(
                 .map(|token| {
                     let normalized_value = normalize(&token.value);
                     let stemmed_value = stemmer
-                        .map_or(normalized_value.to_string(),
-                                |s| s.stem(&normalized_value));
+                        .map_or(&*normalized_value, |s| s.stem(&normalized_value));
                     common_words_gazetteer
-                        .map_or(stemmed_value.clone(),
-                                |g| if g.contains(&stemmed_value) {
-                                    stemmed_value.clone()
+                        .map_or(stemmed_value.to_string(),
+                                |g| if g.contains(stemmed_value) {
+                                    stemmed_value.to_string()
                                 } else {
                                     "rare_word".to_string()
                                 })
@@ -128,7 +127,9 @@ pub fn get_word_cluster<C: WordClusterer>(
     if token_index >= tokens.len() {
         return None;
     }
-    word_clusterer.get_cluster(&tokens[token_index].value.to_lowercase())
+    word_clusterer
+        .get_cluster(&tokens[token_index].value.to_lowercase())
+        .map(|cluster| cluster.to_string())
 }
 
 pub fn get_builtin_entity_match(
@@ -157,7 +158,7 @@ pub fn get_builtin_entity_match(
 fn normalize_tokens<S: Stemmer>(tokens: &[Token], stemmer: Option<&S>) -> Vec<String> {
     tokens
         .iter()
-        .map(|t| stemmer.map_or(normalize(&t.value), |s| s.stem(&normalize(&t.value))))
+        .map(|t| stemmer.map_or(normalize(&t.value), |s| s.stem(&normalize(&t.value)).to_string()))
         .collect_vec()
 }
 
@@ -168,8 +169,8 @@ mod tests {
     use nlu_utils::language::Language as NluUtilsLanguage;
     use nlu_utils::token::tokenize;
     use snips_nlu_ontology::Language;
-    use resources::stemmer::HashMapStemmer;
-    use resources::gazetteer::HashSetGazetteer;
+    use resources::stemmer::CompactStemmer;
+    use resources::gazetteer::CompactGazetteer;
 
     #[test]
     fn is_digit_works() {
@@ -286,8 +287,8 @@ mod tests {
         assert_ngrams_eq(
             expected_ngrams,
             &tokens,
-            None as Option<&HashMapStemmer>,
-            None as Option<&HashSetGazetteer>,
+            None as Option<&CompactStemmer>,
+            None as Option<&CompactGazetteer>,
         );
     }
 
@@ -296,7 +297,7 @@ mod tests {
         // Given
         let language = NluUtilsLanguage::EN;
         let tokens = tokenize("I love House Music", language);
-        let common_words_gazetteer = HashSetGazetteer::from(
+        let common_words_gazetteer = CompactGazetteer::from(
             vec!["i".to_string(), "love".to_string(), "music".to_string()].into_iter(),
         );
 
@@ -325,7 +326,7 @@ mod tests {
         assert_ngrams_eq(
             expected_ngrams,
             &tokens,
-            None as Option<&HashMapStemmer>,
+            None as Option<&CompactStemmer>,
             Some(&common_words_gazetteer),
         );
     }
@@ -337,11 +338,11 @@ mod tests {
         let tokens = tokenize("I love House Music", language);
         struct TestStemmer;
         impl Stemmer for TestStemmer {
-            fn stem(&self, value: &str) -> String {
+            fn stem<'a>(&'a self, value: &'a str) -> &'a str {
                 if value == "house" {
-                    "hous".to_string()
+                    "hous"
                 } else {
-                    value.to_string()
+                    value
                 }
             }
         }
@@ -374,7 +375,7 @@ mod tests {
             expected_ngrams,
             &tokens,
             Some(&stemmer),
-            None as Option<&HashSetGazetteer>,
+            None as Option<&CompactGazetteer>,
         );
     }
 
@@ -382,7 +383,7 @@ mod tests {
     fn get_gazetteer_match_works() {
         // Given
         let language = NluUtilsLanguage::EN;
-        let gazetteer = HashSetGazetteer::from(
+        let gazetteer = CompactGazetteer::from(
             vec![
                 "bird".to_string(),
                 "blue bird".to_string(),
@@ -398,7 +399,7 @@ mod tests {
             &tokens,
             token_index,
             &gazetteer,
-            None as Option<&HashMapStemmer>,
+            None as Option<&CompactStemmer>,
             tagging_scheme,
         );
 
@@ -411,18 +412,18 @@ mod tests {
         // Given
         struct TestStemmer;
         impl Stemmer for TestStemmer {
-            fn stem(&self, value: &str) -> String {
+            fn stem<'a>(&'a self, value: &'a str) -> &'a str {
                 if value == "birds" {
-                    "bird".to_string()
+                    "bird"
                 } else {
-                    value.to_string()
+                    value
                 }
             }
         }
 
         let language = NluUtilsLanguage::EN;
         let stemmer = TestStemmer {};
-        let gazetteer = HashSetGazetteer::from(
+        let gazetteer = CompactGazetteer::from(
             vec![
                 "bird".to_string(),
                 "blue bird".to_string(),
@@ -474,9 +475,9 @@ mod tests {
         // Given
         struct TestWordClusterer;
         impl WordClusterer for TestWordClusterer {
-            fn get_cluster(&self, word: &str) -> Option<String> {
+            fn get_cluster(&self, word: &str) -> Option<&str> {
                 if word == "bird" {
-                    Some("010101".to_string())
+                    Some("010101")
                 } else {
                     None
                 }
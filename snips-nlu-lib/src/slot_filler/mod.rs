@@ -1,38 +1,62 @@
 pub mod crf_slot_filler;
+mod crf_tagger_pool;
+mod crf_training;
 mod crf_utils;
 mod feature_processor;
 mod features;
 mod features_utils;
 
-use std::fs::File;
 use std::path::Path;
+use std::sync::Arc;
 
 use errors::*;
+use explanation::{SlotFillingExplanation, TaggedToken};
 use failure::ResultExt;
-use serde_json;
+use memory::HeapSize;
 
 pub use self::crf_slot_filler::*;
+pub use self::crf_training::{CRFSlotFillerConfig, CRFSlotFillerTrainer,
+                              SlotFillerDataAugmentationConfig};
 use self::crf_utils::TaggingScheme;
 use models::ProcessingUnitMetadata;
 use nlu_utils::token::Token;
+use resources::SharedResources;
 use slot_utils::InternalSlot;
-use utils::FromPath;
+use utils::{FromPath, PreparedUpdate};
+use vfs::FileSystem;
 
-pub trait SlotFiller: FromPath + Send + Sync {
+pub trait SlotFiller: FromPath + HeapSize + Send + Sync {
     fn get_tagging_scheme(&self) -> TaggingScheme;
     fn get_slots(&self, text: &str) -> Result<Vec<InternalSlot>>;
+    /// Same as `get_slots`, along with the tags from which the slots have been built
+    fn get_slots_explained(&self, text: &str)
+        -> Result<(Vec<InternalSlot>, SlotFillingExplanation)>;
+    /// Tags each token of the text, along with its features and the marginal probabilities of
+    /// the tags
+    fn tag_tokens(&self, text: &str) -> Result<Vec<TaggedToken>>;
     fn get_sequence_probability(&self, tokens: &[Token], tags: Vec<String>) -> Result<f64>;
+    /// Prepares the replacement of the utterances of a custom entity which are matched by the slot
+    /// filler features, which only takes effect once the returned update is committed
+    fn prepare_entity_utterances<'a>(
+        &'a self,
+        entity: &str,
+        utterances: &[String],
+    ) -> Box<PreparedUpdate + 'a>;
 }
 
-pub fn build_slot_filler<P: AsRef<Path>>(path: P) -> Result<Box<SlotFiller>> {
+pub fn build_slot_filler<P: AsRef<Path>>(
+    file_system: &FileSystem,
+    path: P,
+    shared_resources: Arc<SharedResources>,
+) -> Result<Box<SlotFiller>> {
     let metadata_path = path.as_ref().join("metadata.json");
-    let metadata_file = File::open(&metadata_path)
+    let metadata_file = file_system.open(&metadata_path)
         .with_context(|_| format!("Cannot open slot filler metadata file '{:?}'",
                                   &metadata_path))?;
-    let metadata: ProcessingUnitMetadata = serde_json::from_reader(metadata_file)
-        .with_context(|_| "Cannot deserialize slot filler json data")?;
+    let metadata = ProcessingUnitMetadata::from_reader(metadata_file)
+        .with_context(|_| SnipsNluError::InvalidJson(metadata_path.to_string_lossy().into_owned()))?;
     match metadata {
-        ProcessingUnitMetadata::CrfSlotFiller => Ok(Box::new(CRFSlotFiller::from_path(path)?) as _),
-        _ => Err(format_err!("{:?} is not a slot filler", metadata))
+        ProcessingUnitMetadata::CrfSlotFiller => Ok(Box::new(CRFSlotFiller::from_vfs(file_system, path, shared_resources)?) as _),
+        _ => Err(SnipsNluError::WrongProcessingUnit(format!("{:?}", metadata), "a slot filler").into())
     }
 }
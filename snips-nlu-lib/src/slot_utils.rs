@@ -1,42 +1,134 @@
 use std::ops::Range;
+use std::slice;
 
 use builtin_entity_parsing::CachingBuiltinEntityParser;
+use fuzzy_matching::FuzzyEntityMatcher;
 use models::nlu_engine::DatasetMetadata;
-use snips_nlu_ontology::{BuiltinEntityKind, Slot, SlotValue};
-use nlu_utils::string::normalize;
+use serde::{Serialize, Serializer};
+use snips_nlu_ontology::{BuiltinEntityKind, Language, Slot, SlotValue};
+use nlu_utils::string::{normalize, substring_with_char_range};
 use utils::{EntityName, SlotName};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InternalSlot {
     pub value: String,
     pub char_range: Range<usize>,
     pub entity: EntityName,
     pub slot_name: SlotName,
+    pub score_upper_bound: Option<f32>,
+}
+
+/// Resolved slot along with its score upper bound, when available
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredSlot {
+    pub slot: Slot,
+    /// Lowest marginal probability among the tags of the slot tokens, which is an upper bound of
+    /// the probability of the whole slot rather than this probability itself
+    pub score_upper_bound: Option<f32>,
+    /// Coordinated values of the slot, such as "milk" and "eggs" in "add milk and eggs", in which
+    /// case `slot` spans all these values, and `slot.value` is only the value of the first one.
+    ///
+    /// In json, the `value` of such a slot is the list of the values of its coordinated values.
+    pub values: Option<Vec<ScoredSlot>>,
+    /// Similarity between the raw value and the entity value it has been resolved to, which is
+    /// only reported when the slot has been resolved by fuzzy matching
+    pub match_score: Option<f32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedSlot<'a> {
+    #[serde(flatten)]
+    slot: &'a Slot,
+    score_upper_bound: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_score: Option<f32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedCoordinatedSlot<'a> {
+    raw_value: &'a str,
+    value: Vec<&'a SlotValue>,
+    range: &'a Option<Range<usize>>,
+    entity: &'a str,
+    slot_name: &'a str,
+    score_upper_bound: Option<f32>,
+    values: &'a [ScoredSlot],
+}
+
+impl Serialize for ScoredSlot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        match self.values {
+            Some(ref values) => SerializedCoordinatedSlot {
+                raw_value: &self.slot.raw_value,
+                value: values.iter().map(|value| &value.slot.value).collect(),
+                range: &self.slot.range,
+                entity: &self.slot.entity,
+                slot_name: &self.slot.slot_name,
+                score_upper_bound: self.score_upper_bound,
+                values,
+            }.serialize(serializer),
+            None => SerializedSlot {
+                slot: &self.slot,
+                score_upper_bound: self.score_upper_bound,
+                match_score: self.match_score,
+            }.serialize(serializer),
+        }
+    }
+}
+
+impl ScoredSlot {
+    /// Slots of the individual values, which are the coordinated values of the slot if any, or
+    /// the slot itself
+    pub fn individual_values(&self) -> &[ScoredSlot] {
+        self.values
+            .as_ref()
+            .map(|values| &**values)
+            .unwrap_or_else(|| slice::from_ref(self))
+    }
+}
+
+impl From<ScoredSlot> for Slot {
+    fn from(scored_slot: ScoredSlot) -> Self {
+        scored_slot.slot
+    }
 }
 
 fn convert_to_custom_slot(
     slot: InternalSlot,
     opt_resolved_value: Option<String>,
-) -> Slot {
+) -> ScoredSlot {
     let value = opt_resolved_value
         .map(|resolved_value| SlotValue::Custom(resolved_value.into()))
         .unwrap_or_else(|| SlotValue::Custom(slot.value.clone().into()));
-    Slot {
-        raw_value: slot.value,
-        value,
-        range: Some(slot.char_range),
-        entity: slot.entity,
-        slot_name: slot.slot_name,
+    ScoredSlot {
+        slot: Slot {
+            raw_value: slot.value,
+            value,
+            range: Some(slot.char_range),
+            entity: slot.entity,
+            slot_name: slot.slot_name,
+        },
+        score_upper_bound: slot.score_upper_bound,
+        values: None,
+        match_score: None,
     }
 }
 
-fn convert_to_builtin_slot(slot: InternalSlot, slot_value: SlotValue) -> Slot {
-    Slot {
-        raw_value: slot.value,
-        value: slot_value,
-        range: Some(slot.char_range),
-        entity: slot.entity,
-        slot_name: slot.slot_name,
+fn convert_to_builtin_slot(slot: InternalSlot, slot_value: SlotValue) -> ScoredSlot {
+    ScoredSlot {
+        slot: Slot {
+            raw_value: slot.value,
+            value: slot_value,
+            range: Some(slot.char_range),
+            entity: slot.entity,
+            slot_name: slot.slot_name,
+        },
+        score_upper_bound: slot.score_upper_bound,
+        values: None,
+        match_score: None,
     }
 }
 
@@ -46,7 +138,8 @@ pub fn resolve_slots(
     dataset_metadata: &DatasetMetadata,
     parser: &CachingBuiltinEntityParser,
     filter_entity_kinds: Option<&[BuiltinEntityKind]>,
-) -> Vec<Slot> {
+    fuzzy_matching: Option<(&FuzzyEntityMatcher, f32)>,
+) -> Vec<ScoredSlot> {
     let builtin_entities = parser.extract_entities(text, filter_entity_kinds, false);
     slots
         .into_iter()
@@ -75,6 +168,14 @@ pub fn resolve_slots(
                             .or(entity.utterances.get(&normalize(&slot.value)))
                             .map(|reference_value|
                                 convert_to_custom_slot(slot.clone(), Some(reference_value.clone())))
+                            .or_else(|| fuzzy_matching
+                                .and_then(|(matcher, threshold)|
+                                    matcher.find_match(&slot.entity, &slot.value, threshold))
+                                .map(|fuzzy_match| ScoredSlot {
+                                    match_score: Some(fuzzy_match.score),
+                                    ..convert_to_custom_slot(
+                                        slot.clone(), Some(fuzzy_match.resolved_value))
+                                }))
                             .or_else(|| if entity.automatically_extensible {
                                 Some(convert_to_custom_slot(slot, None))
                             } else {
@@ -87,10 +188,188 @@ pub fn resolve_slots(
         .collect()
 }
 
+/// Splits the custom slots which consist of several values separated by commas or coordinating
+/// conjunctions, such as "milk and eggs", so that each value is resolved on its own and then
+/// grouped by `group_coordinated_slots`.
+///
+/// Slots whose whole value is a value of their entity, such as "salt and pepper", are kept as is.
+pub fn split_coordinated_slots(
+    slots: Vec<InternalSlot>,
+    dataset_metadata: &DatasetMetadata,
+    language: Language,
+) -> Vec<InternalSlot> {
+    let conjunctions = coordinating_conjunctions(language);
+    slots
+        .into_iter()
+        .flat_map(|slot| {
+            let is_entity_value = match dataset_metadata.entities.get(&slot.entity) {
+                Some(entity) => {
+                    entity.utterances.contains_key(&slot.value)
+                        || entity.utterances.contains_key(&normalize(&slot.value))
+                }
+                // Builtin slots are never split
+                None => true,
+            };
+            let value_ranges = if is_entity_value {
+                vec![]
+            } else {
+                split_on_conjunctions(&slot.value, conjunctions)
+            };
+            if value_ranges.len() < 2 {
+                return vec![slot];
+            }
+            value_ranges
+                .into_iter()
+                .map(|range| InternalSlot {
+                    value: substring_with_char_range(slot.value.clone(), &range),
+                    char_range: slot.char_range.start + range.start
+                        ..slot.char_range.start + range.end,
+                    entity: slot.entity.clone(),
+                    slot_name: slot.slot_name.clone(),
+                    score_upper_bound: slot.score_upper_bound,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Char ranges of the parts of the text which are separated by commas or conjunctions
+fn split_on_conjunctions(text: &str, conjunctions: &[&str]) -> Vec<Range<usize>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut parts: Vec<Range<usize>> = vec![];
+    let mut current_part: Option<Range<usize>> = None;
+    let mut word_start = None;
+    for index in 0..=chars.len() {
+        let is_separator_char = chars
+            .get(index)
+            .map(|c| c.is_whitespace() || *c == ',')
+            .unwrap_or(true);
+        if !is_separator_char {
+            word_start = word_start.or(Some(index));
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            let word: String = chars[start..index].iter().collect();
+            if conjunctions.contains(&&*word.to_lowercase()) {
+                parts.extend(current_part.take());
+            } else {
+                current_part = Some(current_part.map(|part| part.start).unwrap_or(start)..index);
+            }
+        }
+        if chars.get(index) == Some(&',') {
+            parts.extend(current_part.take());
+        }
+    }
+    parts.extend(current_part);
+    parts
+}
+
+/// Groups the adjacent slots which have the same name and are only separated by commas or
+/// coordinating conjunctions, such as in "add milk, eggs and butter", into a single slot holding
+/// the list of their values.
+///
+/// The groups are only exposed by the `NluEngineResult`, as an `IntentParserResult` returns each
+/// coordinated value as a separate slot.
+pub fn group_coordinated_slots(
+    text: &str,
+    slots: Vec<ScoredSlot>,
+    language: Language,
+) -> Vec<ScoredSlot> {
+    let conjunctions = coordinating_conjunctions(language);
+    let mut groups: Vec<Vec<ScoredSlot>> = Vec::with_capacity(slots.len());
+    for slot in slots {
+        let is_coordinated = groups
+            .last()
+            .and_then(|group| group.last())
+            .map(|previous_slot| are_coordinated(text, previous_slot, &slot, conjunctions))
+            .unwrap_or(false);
+        if is_coordinated {
+            groups.last_mut().unwrap().push(slot);
+        } else {
+            groups.push(vec![slot]);
+        }
+    }
+    groups
+        .into_iter()
+        .map(|mut group| {
+            if group.len() == 1 {
+                group.pop().unwrap()
+            } else {
+                merge_coordinated_slots(text, group)
+            }
+        })
+        .collect()
+}
+
+/// Conjunctions are matched against the words separated by whitespaces and commas, hence Japanese
+/// and Korean, where values are mostly coordinated by particles attached to them, have none
+fn coordinating_conjunctions(language: Language) -> &'static [&'static str] {
+    match language {
+        Language::DE => &["und", "oder", "sowie"],
+        Language::EN => &["and", "or", "&"],
+        Language::ES => &["y", "e", "o", "u"],
+        Language::FR => &["et", "ou"],
+        Language::JA | Language::KO => &[],
+    }
+}
+
+fn are_coordinated(
+    text: &str,
+    slot: &ScoredSlot,
+    next_slot: &ScoredSlot,
+    conjunctions: &[&str],
+) -> bool {
+    if slot.slot.slot_name != next_slot.slot.slot_name {
+        return false;
+    }
+    let (range, next_range) = match (slot.slot.range.as_ref(), next_slot.slot.range.as_ref()) {
+        (Some(range), Some(next_range)) if range.end <= next_range.start => (range, next_range),
+        _ => return false,
+    };
+    let separator = substring_with_char_range(text.to_string(), &(range.end..next_range.start));
+    let mut words = separator
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .peekable();
+    let has_comma = separator.contains(',');
+    if words.peek().is_none() {
+        return has_comma;
+    }
+    words.all(|word| conjunctions.contains(&&*word.to_lowercase()))
+}
+
+fn merge_coordinated_slots(text: &str, values: Vec<ScoredSlot>) -> ScoredSlot {
+    let range = {
+        let first_range = values[0].slot.range.as_ref().unwrap();
+        let last_range = values[values.len() - 1].slot.range.as_ref().unwrap();
+        first_range.start..last_range.end
+    };
+    let score_upper_bound = values
+        .iter()
+        .map(|value| value.score_upper_bound)
+        .fold(Some(1.0f32), |score, value_score| {
+            score.and_then(|score| value_score.map(|value_score| score.min(value_score)))
+        });
+    let slot = Slot {
+        raw_value: substring_with_char_range(text.to_string(), &range),
+        value: values[0].slot.value.clone(),
+        range: Some(range),
+        entity: values[0].slot.entity.clone(),
+        slot_name: values[0].slot.slot_name.clone(),
+    };
+    ScoredSlot {
+        slot,
+        score_upper_bound,
+        values: Some(values),
+        match_score: None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use snips_nlu_ontology::{AmountOfMoneyValue, Language, OrdinalValue, Precision};
+    use serde_json;
+    use snips_nlu_ontology::{AmountOfMoneyValue, OrdinalValue, Precision};
     use std::collections::HashMap;
     use models::nlu_engine::Entity;
 
@@ -104,18 +383,21 @@ mod tests {
                 char_range: 5..14,
                 entity: "snips/amountOfMoney".to_string(),
                 slot_name: "amount".to_string(),
+                score_upper_bound: Some(0.9),
             },
             InternalSlot {
                 value: "10th".to_string(),
                 char_range: 22..26,
                 entity: "snips/ordinal".to_string(),
                 slot_name: "ranking".to_string(),
+                score_upper_bound: Some(0.8),
             },
             InternalSlot {
                 value: "subscriber".to_string(),
                 char_range: 27..37,
                 entity: "userType".to_string(),
                 slot_name: "userType".to_string(),
+                score_upper_bound: None,
             }
         ];
         let parser = CachingBuiltinEntityParser::new(Language::EN, 1000);
@@ -133,36 +415,238 @@ mod tests {
         // When
         let filter_entity_kinds = &[BuiltinEntityKind::AmountOfMoney, BuiltinEntityKind::Ordinal];
         let actual_results = resolve_slots(
-            text, slots, &dataset_metadata, &parser, Some(filter_entity_kinds));
+            text, slots, &dataset_metadata, &parser, Some(filter_entity_kinds), None);
 
         // Then
         let expected_results = vec![
-            Slot {
-                raw_value: "5 dollars".to_string(),
-                value: SlotValue::AmountOfMoney(AmountOfMoneyValue {
-                    value: 5.0,
-                    precision: Precision::Exact,
-                    unit: Some("$".to_string()),
-                }),
-                range: Some(5..14),
-                entity: "snips/amountOfMoney".to_string(),
-                slot_name: "amount".to_string(),
+            ScoredSlot {
+                slot: Slot {
+                    raw_value: "5 dollars".to_string(),
+                    value: SlotValue::AmountOfMoney(AmountOfMoneyValue {
+                        value: 5.0,
+                        precision: Precision::Exact,
+                        unit: Some("$".to_string()),
+                    }),
+                    range: Some(5..14),
+                    entity: "snips/amountOfMoney".to_string(),
+                    slot_name: "amount".to_string(),
+                },
+                score_upper_bound: Some(0.9),
+                values: None,
+                match_score: None,
             },
-            Slot {
-                raw_value: "10th".to_string(),
-                value: SlotValue::Ordinal(OrdinalValue { value: 10 }),
-                range: Some(22..26),
-                entity: "snips/ordinal".to_string(),
-                slot_name: "ranking".to_string(),
+            ScoredSlot {
+                slot: Slot {
+                    raw_value: "10th".to_string(),
+                    value: SlotValue::Ordinal(OrdinalValue { value: 10 }),
+                    range: Some(22..26),
+                    entity: "snips/ordinal".to_string(),
+                    slot_name: "ranking".to_string(),
+                },
+                score_upper_bound: Some(0.8),
+                values: None,
+                match_score: None,
             },
-            Slot {
-                raw_value: "subscriber".to_string(),
-                value: SlotValue::Custom("member".to_string().into()),
-                range: Some(27..37),
-                entity: "userType".to_string(),
-                slot_name: "userType".to_string()
+            ScoredSlot {
+                slot: Slot {
+                    raw_value: "subscriber".to_string(),
+                    value: SlotValue::Custom("member".to_string().into()),
+                    range: Some(27..37),
+                    entity: "userType".to_string(),
+                    slot_name: "userType".to_string()
+                },
+                score_upper_bound: None,
+                values: None,
+                match_score: None,
             }
         ];
         assert_eq!(expected_results, actual_results);
     }
+
+    #[test]
+    fn resolve_slots_should_use_fuzzy_matching() {
+        // Given
+        let text = "Make me a capuccino and a latte";
+        let slots = vec![
+            InternalSlot {
+                value: "capuccino".to_string(),
+                char_range: 10..19,
+                entity: "coffee".to_string(),
+                slot_name: "coffee".to_string(),
+                score_upper_bound: None,
+            },
+            InternalSlot {
+                value: "latte".to_string(),
+                char_range: 26..31,
+                entity: "coffee".to_string(),
+                slot_name: "coffee".to_string(),
+                score_upper_bound: None,
+            },
+        ];
+        let parser = CachingBuiltinEntityParser::new(Language::EN, 1000);
+        let entity = Entity {
+            automatically_extensible: false,
+            utterances: hashmap! { "cappuccino".to_string() => "Cappuccino".to_string() },
+        };
+        let dataset_metadata = DatasetMetadata {
+            language_code: Language::EN.to_string(),
+            entities: hashmap! { "coffee".to_string() => entity },
+            slot_name_mappings: HashMap::new(),
+        };
+        let matcher = FuzzyEntityMatcher::new(&dataset_metadata.entities, None, Language::EN);
+
+        // When
+        let resolved_slots = resolve_slots(
+            text, slots, &dataset_metadata, &parser, None, Some((&matcher, 0.8)));
+
+        // Then
+        assert_eq!(1, resolved_slots.len());
+        let expected_value = SlotValue::Custom("Cappuccino".to_string().into());
+        assert_eq!(expected_value, resolved_slots[0].slot.value);
+        assert_eq!("capuccino", resolved_slots[0].slot.raw_value);
+        assert_eq!(Some(0.9), resolved_slots[0].match_score);
+    }
+
+    fn custom_slot(value: &str, range: Range<usize>, slot_name: &str) -> ScoredSlot {
+        ScoredSlot {
+            slot: Slot {
+                raw_value: value.to_string(),
+                value: SlotValue::Custom(value.to_string().into()),
+                range: Some(range),
+                entity: "food".to_string(),
+                slot_name: slot_name.to_string(),
+            },
+            score_upper_bound: Some(0.8),
+            values: None,
+            match_score: None,
+        }
+    }
+
+    #[test]
+    fn group_coordinated_slots_works() {
+        // Given
+        let text = "add milk, eggs and butter to the list of Bob or Alice";
+        let slots = vec![
+            custom_slot("milk", 4..8, "item"),
+            custom_slot("eggs", 10..14, "item"),
+            custom_slot("butter", 19..25, "item"),
+            custom_slot("list", 33..37, "list"),
+            custom_slot("Bob", 41..44, "owner"),
+            custom_slot("Alice", 48..53, "owner"),
+        ];
+
+        // When
+        let grouped_slots = group_coordinated_slots(text, slots.clone(), Language::EN);
+
+        // Then
+        let expected_slots = vec![
+            ScoredSlot {
+                slot: Slot {
+                    raw_value: "milk, eggs and butter".to_string(),
+                    value: SlotValue::Custom("milk".to_string().into()),
+                    range: Some(4..25),
+                    entity: "food".to_string(),
+                    slot_name: "item".to_string(),
+                },
+                score_upper_bound: Some(0.8),
+                values: Some(slots[0..3].to_vec()),
+                match_score: None,
+            },
+            slots[3].clone(),
+            ScoredSlot {
+                slot: Slot {
+                    raw_value: "Bob or Alice".to_string(),
+                    value: SlotValue::Custom("Bob".to_string().into()),
+                    range: Some(41..53),
+                    entity: "food".to_string(),
+                    slot_name: "owner".to_string(),
+                },
+                score_upper_bound: Some(0.8),
+                values: Some(slots[4..6].to_vec()),
+                match_score: None,
+            },
+        ];
+        assert_eq!(expected_slots, grouped_slots);
+        assert_eq!(&slots[0..3], grouped_slots[0].individual_values());
+        assert_eq!(&slots[3..4], grouped_slots[1].individual_values());
+    }
+
+    #[test]
+    fn split_coordinated_slots_works() {
+        // Given
+        let internal_slot = |value: &str, char_range: Range<usize>| InternalSlot {
+            value: value.to_string(),
+            char_range,
+            entity: "food".to_string(),
+            slot_name: "item".to_string(),
+            score_upper_bound: Some(0.7),
+        };
+        let slots = vec![
+            internal_slot("milk, eggs and butter", 4..25),
+            internal_slot("salt and pepper", 30..45),
+            internal_slot("and cheese", 50..60),
+        ];
+        let entity = Entity {
+            automatically_extensible: true,
+            utterances: hashmap! {
+                "milk".to_string() => "milk".to_string(),
+                "salt and pepper".to_string() => "salt and pepper".to_string(),
+            },
+        };
+        let dataset_metadata = DatasetMetadata {
+            language_code: Language::EN.to_string(),
+            entities: hashmap! { "food".to_string() => entity },
+            slot_name_mappings: HashMap::new(),
+        };
+
+        // When
+        let split_slots = split_coordinated_slots(slots.clone(), &dataset_metadata, Language::EN);
+
+        // Then
+        let expected_slots = vec![
+            internal_slot("milk", 4..8),
+            internal_slot("eggs", 10..14),
+            internal_slot("butter", 19..25),
+            slots[1].clone(),
+            slots[2].clone(),
+        ];
+        assert_eq!(expected_slots, split_slots);
+    }
+
+    #[test]
+    fn coordinated_slots_should_be_serialized_with_list_value() {
+        // Given
+        let text = "add milk and eggs";
+        let slots = vec![custom_slot("milk", 4..8, "item"), custom_slot("eggs", 13..17, "item")];
+        let grouped_slots = group_coordinated_slots(text, slots, Language::EN);
+
+        // When
+        let serialized_slot = serde_json::to_value(&grouped_slots[0]).unwrap();
+
+        // Then
+        let expected_value = serde_json::to_value(vec![
+            SlotValue::Custom("milk".to_string().into()),
+            SlotValue::Custom("eggs".to_string().into()),
+        ]).unwrap();
+        assert_eq!("milk and eggs", serialized_slot["rawValue"]);
+        assert_eq!(expected_value, serialized_slot["value"]);
+        assert_eq!(2, serialized_slot["values"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn group_coordinated_slots_should_not_group_separated_slots() {
+        // Given
+        let text = "add milk with eggs, then butter";
+        let slots = vec![
+            custom_slot("milk", 4..8, "item"),
+            custom_slot("eggs", 14..18, "item"),
+            custom_slot("butter", 25..31, "item"),
+        ];
+
+        // When
+        let grouped_slots = group_coordinated_slots(text, slots.clone(), Language::EN);
+
+        // Then
+        assert_eq!(slots, grouped_slots);
+    }
 }
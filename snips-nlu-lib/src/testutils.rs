@@ -1,5 +1,7 @@
 use ndarray::prelude::*;
 
+use slot_utils::InternalSlot;
+
 pub fn assert_epsilon_eq_array1(a: &Array1<f32>, b: &Array1<f32>, epsilon: f32) {
     assert_eq!(a.dim(), b.dim());
     for (index, elem_a) in a.indexed_iter() {
@@ -11,3 +13,10 @@ pub fn epsilon_eq(a: f32, b: f32, epsilon: f32) -> bool {
     let diff = a - b;
     diff < epsilon && diff > -epsilon
 }
+
+pub fn without_score_upper_bounds(slots: Vec<InternalSlot>) -> Vec<InternalSlot> {
+    slots
+        .into_iter()
+        .map(|slot| InternalSlot { score_upper_bound: None, ..slot })
+        .collect()
+}
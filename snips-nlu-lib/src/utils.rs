@@ -1,13 +1,98 @@
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 
+use arc_swap::ArcSwap;
 use errors::*;
+use rand::{FromEntropy, SeedableRng};
+use rand::rngs::StdRng;
+use resources::SharedResources;
+use vfs::{FileSystem, LocalFileSystem};
 
 pub type IntentName = String;
 pub type SlotName = String;
 pub type EntityName = String;
 
 pub trait FromPath {
-    fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> where Self: Sized;
+    fn from_path<P: AsRef<Path>>(
+        path: P,
+        shared_resources: Arc<SharedResources>,
+    ) -> Result<Self> where Self: Sized {
+        Self::from_vfs(&LocalFileSystem, path, shared_resources)
+    }
+
+    fn from_vfs<P: AsRef<Path>>(
+        file_system: &FileSystem,
+        path: P,
+        shared_resources: Arc<SharedResources>,
+    ) -> Result<Self> where Self: Sized;
+}
+
+/// `f32` value which can be shared between threads and updated without locking
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn new(value: f32) -> Self {
+        AtomicF32(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn store(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed)
+    }
+}
+
+/// Update of a component which has been entirely prepared beforehand, so that committing it can
+/// no longer fail
+pub trait PreparedUpdate {
+    fn commit(self: Box<Self>);
+}
+
+/// Replacement of the value shared through an `ArcSwap`, which the readers see as a whole
+pub struct SwapUpdate<'a, T: 'a> {
+    target: &'a ArcSwap<T>,
+    value: Arc<T>,
+}
+
+impl<'a, T> SwapUpdate<'a, T> {
+    pub fn new(target: &'a ArcSwap<T>, value: T) -> Self {
+        Self {
+            target,
+            value: Arc::new(value),
+        }
+    }
+}
+
+impl<'a, T> PreparedUpdate for SwapUpdate<'a, T> {
+    fn commit(self: Box<Self>) {
+        let update = *self;
+        update.target.store(update.value);
+    }
+}
+
+impl<'a> PreparedUpdate for Vec<Box<PreparedUpdate + 'a>> {
+    fn commit(self: Box<Self>) {
+        for update in *self {
+            update.commit();
+        }
+    }
+}
+
+/// Random number generator used in training, which is deterministic when a seed is provided
+pub fn seeded_rng(random_seed: Option<u64>) -> StdRng {
+    match random_seed {
+        Some(seed) => {
+            let mut seed_bytes = [0; 32];
+            for (i, byte) in seed_bytes.iter_mut().take(8).enumerate() {
+                *byte = (seed >> (8 * i)) as u8;
+            }
+            StdRng::from_seed(seed_bytes)
+        }
+        None => StdRng::from_entropy(),
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+
+use errors::*;
+use failure::{Error, ResultExt};
+use zip::ZipArchive;
+
+/// Read-only file system from which the models and resources are loaded
+pub trait FileSystem: Send + Sync {
+    /// Opens the file located at `path`
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<Read + 'a>>;
+
+    /// Returns whether or not a file or a directory exists at `path`
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Returns the paths of the entries contained in the directory located at `path`
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>>;
+
+    /// Reads the whole content of the file located at `path`
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let mut content = vec![];
+        self.open(path)?.read_to_end(&mut content)?;
+        Ok(content)
+    }
+}
+
+/// File system backed by the local disk
+pub struct LocalFileSystem;
+
+impl FileSystem for LocalFileSystem {
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<Read + 'a>> {
+        let file = fs::File::open(path).map_err(|error| io_error(error, path))?;
+        Ok(Box::new(file))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        path.read_dir()
+            .map_err(|error| io_error(error, path))?
+            .map(|dir_entry| Ok(dir_entry?.path()))
+            .collect()
+    }
+}
+
+/// File system which holds all its files in memory
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads all the files of a zip archive into memory, without writing anything on disk
+    pub fn from_zip<R: Read + Seek>(reader: R) -> Result<Self> {
+        let mut archive = ZipArchive::new(reader)
+            .with_context(|_| "Could not read zip data")?;
+        let mut file_system = Self::new();
+        for file_index in 0..archive.len() {
+            let mut file = archive.by_index(file_index)?;
+            if (&*file.name()).ends_with('/') || (&*file.name()).ends_with('\\') {
+                continue;
+            }
+            let path = file.sanitized_name();
+            let mut content = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut content)
+                .with_context(|_| format!("Could not read zipped file {:?}", path))?;
+            file_system.insert(path, content);
+        }
+        Ok(file_system)
+    }
+
+    pub fn insert<P: AsRef<Path>>(&mut self, path: P, content: Vec<u8>) {
+        self.files.insert(normalize_path(path.as_ref()), content);
+    }
+
+    /// Returns the paths of the top level entries of the file system
+    pub fn root_entries(&self) -> Vec<PathBuf> {
+        self.read_dir(Path::new("")).unwrap_or_else(|_| vec![])
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<Read + 'a>> {
+        let content = self.files
+            .get(&normalize_path(path))
+            .ok_or_else(|| missing_file(path))?;
+        Ok(Box::new(&content[..]))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        let path = normalize_path(path);
+        self.files.keys().any(|file_path| file_path.starts_with(&path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let path = normalize_path(path);
+        let mut entries: Vec<PathBuf> = self.files
+            .keys()
+            .filter_map(|file_path| file_path.strip_prefix(&path).ok())
+            .filter_map(|relative_path| relative_path.components().next())
+            .map(|component| path.join(component.as_os_str()))
+            .collect();
+        if entries.is_empty() {
+            return Err(missing_file(&path));
+        }
+        entries.sort();
+        entries.dedup();
+        Ok(entries)
+    }
+}
+
+fn io_error(error: io::Error, path: &Path) -> Error {
+    if error.kind() == io::ErrorKind::NotFound {
+        missing_file(path)
+    } else {
+        error.into()
+    }
+}
+
+fn missing_file(path: &Path) -> Error {
+    SnipsNluError::MissingFile(path.to_string_lossy().into_owned()).into()
+}
+
+/// Turns the path into a relative path without `.` and `..` components, where `..` removes the
+/// previous component and is ignored at the root
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized_path = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized_path.push(name),
+            Component::ParentDir => {
+                normalized_path.pop();
+            }
+            _ => {}
+        }
+    }
+    normalized_path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::file_path;
+
+    #[test]
+    fn memory_file_system_works() {
+        // Given
+        let mut file_system = MemoryFileSystem::new();
+        file_system.insert("engine/nlu_engine.json", b"{}".to_vec());
+        file_system.insert("engine/resources/en/metadata.json", b"{}".to_vec());
+        file_system.insert("engine/resources/fr/metadata.json", b"{}".to_vec());
+
+        // When
+        let content = file_system.read(Path::new("./engine/nlu_engine.json")).unwrap();
+        let entries = file_system.read_dir(Path::new("engine/resources")).unwrap();
+
+        // Then
+        assert_eq!(b"{}".to_vec(), content);
+        assert_eq!(
+            vec![
+                PathBuf::from("engine/resources/en"),
+                PathBuf::from("engine/resources/fr"),
+            ],
+            entries
+        );
+        assert!(file_system.exists(Path::new("engine/resources")));
+        assert!(!file_system.exists(Path::new("engine/intent_parser")));
+        assert!(file_system.open(Path::new("engine/unknown.json")).is_err());
+    }
+
+    #[test]
+    fn normalize_path_works() {
+        // Given
+        let paths = vec![
+            "./engine/resources",
+            "engine/resources/../nlu_engine.json",
+            "/../engine/resources/..",
+        ];
+
+        // When
+        let normalized_paths = paths
+            .into_iter()
+            .map(|path| normalize_path(Path::new(path)))
+            .collect::<Vec<_>>();
+
+        // Then
+        let expected_paths = vec![
+            PathBuf::from("engine/resources"),
+            PathBuf::from("engine/nlu_engine.json"),
+            PathBuf::from("engine"),
+        ];
+        assert_eq!(expected_paths, normalized_paths);
+    }
+
+    #[test]
+    fn should_report_missing_files() {
+        // Given
+        let file_system = MemoryFileSystem::new();
+
+        // When
+        let error = file_system.read(Path::new("engine/nlu_engine.json")).unwrap_err();
+
+        // Then
+        match SnipsNluError::find_in(&error) {
+            Some(SnipsNluError::MissingFile(path)) => assert_eq!("engine/nlu_engine.json", path),
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn memory_file_system_from_zip_works() {
+        // Given
+        let zip_path = file_path("tests")
+            .join("models")
+            .join("trained_engine.zip");
+        let zip_file = fs::File::open(zip_path).unwrap();
+
+        // When
+        let file_system = MemoryFileSystem::from_zip(zip_file).unwrap();
+
+        // Then
+        assert_eq!(vec![PathBuf::from("trained_engine")], file_system.root_entries());
+        assert!(file_system.exists(Path::new("trained_engine/nlu_engine.json")));
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use failure::ResultExt;
+use snips_nlu_lib::{EngineHandle, SnipsNluEngine};
+
+use Result;
+
+/// Loads an engine from its directory, or from its zip archive
+pub fn load_engine<P: AsRef<Path>>(path: P) -> Result<SnipsNluEngine> {
+    let path = path.as_ref();
+    if path.is_file() {
+        let zip_file = File::open(path)
+            .with_context(|_| format!("Cannot open nlu engine archive {:?}", path))?;
+        SnipsNluEngine::from_zip(zip_file)
+    } else {
+        SnipsNluEngine::from_path(path)
+    }
+}
+
+struct LoadedEngine {
+    path: PathBuf,
+    engine: EngineHandle,
+    last_modified: Mutex<SystemTime>,
+}
+
+/// Engines served by the server, indexed by name, which can be reloaded when their files change
+#[derive(Default)]
+pub struct EngineStore {
+    engines: BTreeMap<String, LoadedEngine>,
+}
+
+impl EngineStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load<P: AsRef<Path>>(&mut self, name: &str, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let last_modified = last_modified(path)?;
+        let engine = load_engine(path)
+            .with_context(|_| format!("Cannot load engine '{}' from {:?}", name, path))?;
+        self.engines.insert(
+            name.to_string(),
+            LoadedEngine {
+                path: path.to_path_buf(),
+                engine: EngineHandle::new(engine),
+                last_modified: Mutex::new(last_modified),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.engines.keys().cloned().collect()
+    }
+
+    /// Returns the engine with the given name, or the only engine of the store when no name is
+    /// given
+    pub fn get(&self, name: Option<&str>) -> Option<Arc<SnipsNluEngine>> {
+        let loaded_engine = match name {
+            Some(name) => self.engines.get(name),
+            None if self.engines.len() == 1 => self.engines.values().next(),
+            None => None,
+        };
+        loaded_engine.map(|loaded_engine| loaded_engine.engine.engine())
+    }
+
+    /// Reloads the engines whose files have been modified since they were loaded, and returns the
+    /// outcome of each reload.
+    ///
+    /// Requests being processed keep using the previous engine, and an engine which fails to
+    /// reload is kept until its files change again.
+    pub fn reload_modified(&self) -> Vec<(String, Result<()>)> {
+        self.engines
+            .iter()
+            .filter_map(|(name, loaded_engine)| {
+                let modified = match last_modified(&loaded_engine.path) {
+                    Ok(modified) => modified,
+                    Err(error) => return Some((name.clone(), Err(error))),
+                };
+                let mut last_modified = loaded_engine.last_modified.lock().unwrap();
+                if modified <= *last_modified {
+                    return None;
+                }
+                *last_modified = modified;
+                let result = load_engine(&loaded_engine.path).map(|engine| {
+                    loaded_engine.engine.swap(engine);
+                });
+                Some((name.clone(), result))
+            })
+            .collect()
+    }
+}
+
+/// Most recent modification time of a file, or of the files of a directory
+fn last_modified(path: &Path) -> Result<SystemTime> {
+    let metadata = fs::metadata(path)
+        .with_context(|_| format!("Cannot read metadata of {:?}", path))?;
+    let mut modified = metadata.modified()?;
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            modified = modified.max(last_modified(&entry?.path())?);
+        }
+    }
+    Ok(modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use file_path;
+    use std::env;
+    use std::process;
+    use std::thread;
+    use std::time::Duration;
+
+    fn copy_dir(source: &Path, target: &Path) {
+        fs::create_dir_all(target).unwrap();
+        for entry in fs::read_dir(source).unwrap() {
+            let entry = entry.unwrap();
+            let target_path = target.join(entry.file_name());
+            if entry.path().is_dir() {
+                copy_dir(&entry.path(), &target_path);
+            } else {
+                fs::copy(entry.path(), target_path).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn reload_modified_should_reload_modified_engines() {
+        // Given
+        let engine_path =
+            env::temp_dir().join(format!("snips_nlu_server_reload_test_{}", process::id()));
+        let _ = fs::remove_dir_all(&engine_path);
+        copy_dir(&file_path("tests").join("models").join("trained_engine"), &engine_path);
+        let mut engines = EngineStore::new();
+        engines.load("coffee", &engine_path).unwrap();
+        let initial_engine = engines.get(None).unwrap();
+
+        // When
+        let unmodified_reloads = engines.reload_modified();
+        // Make sure that the modification time changes, whatever its resolution
+        thread::sleep(Duration::from_millis(1100));
+        let model_path = engine_path.join("nlu_engine.json");
+        let model = fs::read(&model_path).unwrap();
+        fs::write(&model_path, model).unwrap();
+        let modified_reloads = engines.reload_modified();
+
+        // Then
+        assert!(unmodified_reloads.is_empty());
+        assert_eq!(1, modified_reloads.len());
+        assert_eq!("coffee", &modified_reloads[0].0);
+        assert!(modified_reloads[0].1.is_ok());
+        assert!(!Arc::ptr_eq(&initial_engine, &engines.get(Some("coffee")).unwrap()));
+        fs::remove_dir_all(&engine_path).unwrap();
+    }
+
+    #[test]
+    fn get_should_require_a_name_when_several_engines_are_loaded() {
+        // Given
+        let mut engines = EngineStore::new();
+        let models_path = file_path("tests").join("models");
+        engines.load("first", models_path.join("trained_engine")).unwrap();
+        engines.load("second", models_path.join("trained_engine.zip")).unwrap();
+
+        // When / Then
+        assert!(engines.get(None).is_none());
+        assert!(engines.get(Some("second")).is_some());
+        assert!(engines.get(Some("third")).is_none());
+    }
+}
@@ -0,0 +1,154 @@
+use std::io::{BufRead, BufReader, Read, Write};
+
+use failure::ResultExt;
+
+use Result;
+
+/// Maximum size of the headers of a request
+const MAX_HEADERS_SIZE: usize = 64 * 1024;
+/// Maximum size of the body of a request
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(status: u16, body: String) -> Self {
+        Self { status, body }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Reads an HTTP/1.1 request whose body, if any, is delimited by a `Content-Length` header
+pub fn read_request<R: Read>(reader: R) -> Result<Request> {
+    let mut reader = BufReader::new(reader);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(_version)) => (method.to_string(), target.to_string()),
+        _ => return Err(format_err!("Invalid request line: {:?}", request_line.trim_right())),
+    };
+    // The query string is not used by any route
+    let path = target.split('?').next().unwrap_or("").to_string();
+
+    let mut content_length = 0;
+    let mut headers_size = request_line.len();
+    loop {
+        let mut header = String::new();
+        let header_size = reader.read_line(&mut header)?;
+        headers_size += header_size;
+        if header_size == 0 || headers_size > MAX_HEADERS_SIZE {
+            return Err(format_err!("Incomplete or too large request headers"));
+        }
+        let header = header.trim_right();
+        if header.is_empty() {
+            break;
+        }
+        let mut name_and_value = header.splitn(2, ':');
+        let name = name_and_value.next().unwrap_or("").trim();
+        let value = name_and_value.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse::<usize>()
+                .with_context(|_| format!("Invalid Content-Length header: {:?}", value))?;
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err(format_err!("Request body of {} bytes is too large", content_length));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Request { method, path, body })
+}
+
+/// Writes the response and signals that the connection is closed afterwards
+pub fn write_response<W: Write>(mut writer: W, response: &Response) -> Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        response.reason(),
+        response.body.len(),
+        response.body
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_request_works() {
+        // Given
+        let data = "POST /parse?verbose=1 HTTP/1.1\r\n\
+                    Host: localhost\r\n\
+                    content-length: 17\r\n\r\n\
+                    {\"input\": \"tea\"}\n";
+
+        // When
+        let request = read_request(data.as_bytes()).unwrap();
+
+        // Then
+        let expected_request = Request {
+            method: "POST".to_string(),
+            path: "/parse".to_string(),
+            body: b"{\"input\": \"tea\"}\n".to_vec(),
+        };
+        assert_eq!(expected_request, request);
+    }
+
+    #[test]
+    fn read_request_should_fail_on_truncated_headers() {
+        // Given
+        let data = "GET /health HTTP/1.1\r\nHost: localhost\r\n";
+
+        // When
+        let result = read_request(data.as_bytes());
+
+        // Then
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn write_response_works() {
+        // Given
+        let mut output = vec![];
+        let response = Response::json(404, "{}".to_string());
+
+        // When
+        write_response(&mut output, &response).unwrap();
+
+        // Then
+        let expected_output = "HTTP/1.1 404 Not Found\r\n\
+                               Content-Type: application/json\r\n\
+                               Content-Length: 2\r\n\
+                               Connection: close\r\n\r\n{}";
+        assert_eq!(expected_output, String::from_utf8(output).unwrap());
+    }
+}
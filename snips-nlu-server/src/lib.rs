@@ -0,0 +1,586 @@
+#[macro_use]
+extern crate failure;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate snips_nlu_lib;
+
+#[cfg(test)]
+extern crate dinghy_test;
+
+mod engines;
+mod http;
+
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use snips_nlu_lib::dataset::EntityValue;
+use snips_nlu_lib::{SnipsNluEngine, SnipsNluError};
+
+pub use engines::{load_engine, EngineStore};
+use http::{read_request, write_response, Request, Response};
+
+pub type Result<T> = ::std::result::Result<T, ::failure::Error>;
+
+/// Default number of threads handling the connections
+pub const DEFAULT_WORKERS: usize = 8;
+/// Default timeout of the reads and writes on a connection
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// HTTP server exposing the engines of an `EngineStore` with the following json routes:
+///
+/// - `POST /parse`, with a `{"input": ..., "intents_filter": [...]}` body, where the filter is
+///   optional, returns the parsing result in the same json as
+///   `snips_nlu_engine_run_parse_into_json`
+/// - `POST /parse_batch`, with a `{"inputs": [...], "intents_filter": [...]}` body, returns the
+///   list of parsing results
+/// - `POST /parse_detailed` and `POST /parse_detailed_batch`, with the same bodies, return the
+///   detailed parsing results, in the same json as
+///   `snips_nlu_engine_run_detailed_parse_into_json`
+/// - `POST /extract_slot`, with a `{"input": ..., "intent": ..., "slot": ...}` body, returns the
+///   extracted slot or `null`
+/// - `POST /add_entity_values`, with a `{"entity": ..., "values": [{"value": ..., "synonyms":
+///   [...]}]}` body, adds values to a custom entity of the engine
+/// - `POST /remove_entity_values`, with a `{"entity": ..., "values": [...]}` body, removes values
+///   from a custom entity of the engine, along with their synonyms
+/// - `GET /health` returns the names of the loaded engines
+///
+/// The entity values added or removed at runtime are lost when the engine is reloaded.
+/// When several engines are loaded, request bodies must specify the name of the engine to use in
+/// an `engine` field.
+pub struct Server {
+    listener: TcpListener,
+    engines: Arc<EngineStore>,
+    workers: usize,
+    timeout: Option<Duration>,
+}
+
+#[derive(Deserialize)]
+struct ParseRequest {
+    engine: Option<String>,
+    input: String,
+    intents_filter: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct ParseBatchRequest {
+    engine: Option<String>,
+    inputs: Vec<String>,
+    intents_filter: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct ExtractSlotRequest {
+    engine: Option<String>,
+    input: String,
+    intent: String,
+    slot: String,
+}
+
+#[derive(Deserialize)]
+struct AddEntityValuesRequest {
+    engine: Option<String>,
+    entity: String,
+    values: Vec<EntityValue>,
+}
+
+#[derive(Deserialize)]
+struct RemoveEntityValuesRequest {
+    engine: Option<String>,
+    entity: String,
+    values: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct StatusResponse {
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    engines: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(address: A, engines: EngineStore) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            engines: Arc::new(engines),
+            workers: DEFAULT_WORKERS,
+            timeout: Some(DEFAULT_TIMEOUT),
+        })
+    }
+
+    /// Sets the number of threads handling the connections, beyond which incoming connections
+    /// wait to be accepted
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Sets the timeout of the reads and writes on a connection, so that idle clients do not hold
+    /// a worker forever. A zero timeout disables the timeouts.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = if timeout == Duration::from_secs(0) {
+            None
+        } else {
+            Some(timeout)
+        };
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Checks periodically whether the files of the engines have changed, and reloads the
+    /// modified engines
+    pub fn watch_engines(&self, interval: Duration) {
+        let engines = self.engines.clone();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            for (name, result) in engines.reload_modified() {
+                match result {
+                    Ok(()) => eprintln!("Reloaded engine '{}'", name),
+                    Err(error) => eprintln!("Cannot reload engine '{}': {}", name, error),
+                }
+            }
+        });
+    }
+
+    /// Serves the requests on a fixed number of worker threads, each of them handling one
+    /// connection at a time
+    pub fn run(self) -> Result<()> {
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(self.workers);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..self.workers {
+            let receiver = receiver.clone();
+            let engines = self.engines.clone();
+            thread::spawn(move || loop {
+                let stream = match receiver.lock().map(|receiver| receiver.recv()) {
+                    Ok(Ok(stream)) => stream,
+                    _ => return,
+                };
+                if let Err(error) = handle_connection(stream, &engines) {
+                    eprintln!("Cannot handle connection: {}", error);
+                }
+            });
+        }
+
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    eprintln!("Cannot accept connection: {}", error);
+                    continue;
+                }
+            };
+            let timeouts = stream
+                .set_read_timeout(self.timeout)
+                .and_then(|_| stream.set_write_timeout(self.timeout));
+            if let Err(error) = timeouts {
+                eprintln!("Cannot set connection timeouts: {}", error);
+                continue;
+            }
+            sender
+                .send(stream)
+                .map_err(|_| format_err!("All the connection workers have stopped"))?;
+        }
+        Ok(())
+    }
+}
+
+fn handle_connection(stream: TcpStream, engines: &EngineStore) -> Result<()> {
+    let response = match read_request(&stream) {
+        Ok(request) => handle_request(&request, engines),
+        Err(error) => error_response(400, error.to_string()),
+    };
+    write_response(&stream, &response)
+}
+
+fn handle_request(request: &Request, engines: &EngineStore) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/health") => json_response(&HealthResponse {
+            status: "ok",
+            engines: engines.names(),
+        }),
+        ("POST", "/parse") => with_body(request, |body: ParseRequest| {
+            let engine = get_engine(engines, body.engine.as_ref())?;
+            let filter = body.intents_filter.as_ref().map(|intents| &**intents);
+            engine
+                .parse(&body.input, filter)
+                .map(|result| json_response(&result))
+                .map_err(engine_error_response)
+        }),
+        ("POST", "/parse_batch") => with_body(request, |body: ParseBatchRequest| {
+            let engine = get_engine(engines, body.engine.as_ref())?;
+            let filter = body.intents_filter.as_ref().map(|intents| &**intents);
+            let inputs: Vec<&str> = body.inputs.iter().map(|input| input.as_str()).collect();
+            engine
+                .parse_batch(&inputs, filter)
+                .map(|results| json_response(&results))
+                .map_err(engine_error_response)
+        }),
+        ("POST", "/parse_detailed") => with_body(request, |body: ParseRequest| {
+            let engine = get_engine(engines, body.engine.as_ref())?;
+            let filter = body.intents_filter.as_ref().map(|intents| &**intents);
+            engine
+                .parse_detailed(&body.input, filter)
+                .map(|result| json_response(&result))
+                .map_err(engine_error_response)
+        }),
+        ("POST", "/parse_detailed_batch") => with_body(request, |body: ParseBatchRequest| {
+            let engine = get_engine(engines, body.engine.as_ref())?;
+            let filter = body.intents_filter.as_ref().map(|intents| &**intents);
+            let inputs: Vec<&str> = body.inputs.iter().map(|input| input.as_str()).collect();
+            engine
+                .parse_detailed_batch(&inputs, filter)
+                .map(|results| json_response(&results))
+                .map_err(engine_error_response)
+        }),
+        ("POST", "/extract_slot") => with_body(request, |body: ExtractSlotRequest| {
+            let engine = get_engine(engines, body.engine.as_ref())?;
+            engine
+                .extract_slot(body.input, &body.intent, &body.slot)
+                .map(|slot| json_response(&slot))
+                .map_err(engine_error_response)
+        }),
+        ("POST", "/add_entity_values") => with_body(request, |body: AddEntityValuesRequest| {
+            let engine = get_engine(engines, body.engine.as_ref())?;
+            engine
+                .add_entity_values(&body.entity, &body.values)
+                .map(|_| json_response(&StatusResponse { status: "ok" }))
+                .map_err(engine_error_response)
+        }),
+        ("POST", "/remove_entity_values") => {
+            with_body(request, |body: RemoveEntityValuesRequest| {
+                let engine = get_engine(engines, body.engine.as_ref())?;
+                engine
+                    .remove_entity_values(&body.entity, &body.values)
+                    .map(|_| json_response(&StatusResponse { status: "ok" }))
+                    .map_err(engine_error_response)
+            })
+        }
+        (_, "/health")
+        | (_, "/parse")
+        | (_, "/parse_batch")
+        | (_, "/parse_detailed")
+        | (_, "/parse_detailed_batch")
+        | (_, "/extract_slot")
+        | (_, "/add_entity_values")
+        | (_, "/remove_entity_values") => {
+            error_response(405, format!("Method {} is not allowed", request.method))
+        }
+        (_, path) => error_response(404, format!("Unknown route '{}'", path)),
+    }
+}
+
+/// Deserializes the json body of the request and handles it, where errors are returned as
+/// responses
+fn with_body<B, F>(request: &Request, handler: F) -> Response
+where
+    B: ::serde::de::DeserializeOwned,
+    F: FnOnce(B) -> ::std::result::Result<Response, Response>,
+{
+    match serde_json::from_slice(&request.body) {
+        Ok(body) => handler(body).unwrap_or_else(|response| response),
+        Err(error) => error_response(400, format!("Invalid request body: {}", error)),
+    }
+}
+
+fn get_engine(
+    engines: &EngineStore,
+    name: Option<&String>,
+) -> ::std::result::Result<Arc<SnipsNluEngine>, Response> {
+    engines.get(name.map(|name| name.as_str())).ok_or_else(|| match name {
+        Some(name) => error_response(404, format!("Unknown engine '{}'", name)),
+        None => error_response(400, "The engine must be specified".to_string()),
+    })
+}
+
+fn engine_error_response(error: ::failure::Error) -> Response {
+    let status = match SnipsNluError::find_in(&error) {
+        Some(SnipsNluError::UnknownIntent(_))
+        | Some(SnipsNluError::UnknownSlot(_))
+        | Some(SnipsNluError::UnknownEntity(_)) => 400,
+        _ => 500,
+    };
+    error_response(status, error.to_string())
+}
+
+fn json_response<T: Serialize>(body: &T) -> Response {
+    match serde_json::to_string(body) {
+        Ok(json) => Response::json(200, json),
+        Err(error) => error_response(500, error.to_string()),
+    }
+}
+
+fn error_response(status: u16, error: String) -> Response {
+    let body = serde_json::to_string(&ErrorResponse { error })
+        .unwrap_or_else(|_| "{}".to_string());
+    Response::json(status, body)
+}
+
+#[cfg(test)]
+fn file_path(filename: &str) -> ::std::path::PathBuf {
+    ::dinghy_test::try_test_file_path("data")
+        .unwrap_or_else(|| "../data".into())
+        .join(filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::io::{Read, Write};
+
+    fn start_server(engines: EngineStore) -> SocketAddr {
+        start_configured_server(Server::bind("127.0.0.1:0", engines).unwrap())
+    }
+
+    fn start_configured_server(server: Server) -> SocketAddr {
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run().unwrap());
+        address
+    }
+
+    fn send_request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        ).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body_start = response.find("\r\n\r\n").unwrap() + 4;
+        (status, serde_json::from_str(&response[body_start..]).unwrap())
+    }
+
+    fn trained_engine_path() -> ::std::path::PathBuf {
+        file_path("tests").join("models").join("trained_engine")
+    }
+
+    fn trained_engines() -> EngineStore {
+        let mut engines = EngineStore::new();
+        engines.load("coffee", trained_engine_path()).unwrap();
+        engines
+    }
+
+    #[test]
+    fn health_works() {
+        // Given
+        let address = start_server(EngineStore::new());
+
+        // When
+        let (status, body) = send_request(address, "GET", "/health", "");
+
+        // Then
+        assert_eq!(200, status);
+        assert_eq!("ok", body["status"]);
+        assert_eq!(Value::Array(vec![]), body["engines"]);
+    }
+
+    #[test]
+    fn idle_connections_should_time_out() {
+        // Given
+        let server = Server::bind("127.0.0.1:0", EngineStore::new())
+            .unwrap()
+            .workers(1)
+            .timeout(Duration::from_millis(100));
+        let address = start_configured_server(server);
+        let _idle_stream = TcpStream::connect(address).unwrap();
+
+        // When
+        let (status, _) = send_request(address, "GET", "/health", "");
+
+        // Then
+        assert_eq!(200, status);
+    }
+
+    #[test]
+    fn zero_timeout_should_disable_timeouts() {
+        // Given
+        let server = Server::bind("127.0.0.1:0", EngineStore::new())
+            .unwrap()
+            .timeout(Duration::from_secs(0));
+        let address = start_configured_server(server);
+
+        // When
+        let (status, _) = send_request(address, "GET", "/health", "");
+
+        // Then
+        assert_eq!(200, status);
+    }
+
+    #[test]
+    fn should_reject_unknown_routes_and_invalid_bodies() {
+        // Given
+        let address = start_server(EngineStore::new());
+
+        // When
+        let (unknown_route_status, _) = send_request(address, "GET", "/unknown", "");
+        let (wrong_method_status, _) = send_request(address, "GET", "/parse", "");
+        let (invalid_body_status, body) = send_request(address, "POST", "/parse", "{");
+        let (missing_engine_status, _) =
+            send_request(address, "POST", "/parse", r#"{"input": "hello"}"#);
+
+        // Then
+        assert_eq!(404, unknown_route_status);
+        assert_eq!(405, wrong_method_status);
+        assert_eq!(400, invalid_body_status);
+        assert!(body["error"].as_str().unwrap().starts_with("Invalid request body"));
+        assert_eq!(400, missing_engine_status);
+    }
+
+    #[test]
+    fn parse_works() {
+        // Given
+        let address = start_server(trained_engines());
+        let body = r#"{"input": "Make me two cups of coffee", "intents_filter": ["MakeCoffee"]}"#;
+
+        // When
+        let (status, result) = send_request(address, "POST", "/parse", body);
+
+        // Then
+        let expected_result = serde_json::to_value(
+            load_engine(trained_engine_path())
+                .unwrap()
+                .parse("Make me two cups of coffee", Some(&["MakeCoffee".to_string()]))
+                .unwrap()
+        ).unwrap();
+        assert_eq!(200, status);
+        assert_eq!(expected_result, result);
+        assert_eq!("MakeCoffee", result["intent"]["intentName"]);
+        assert!(result.get("rejectedIntent").is_none());
+    }
+
+    #[test]
+    fn parse_detailed_works() {
+        // Given
+        let address = start_server(trained_engines());
+        let body = r#"{"input": "Make me two cups of coffee"}"#;
+
+        // When
+        let (status, result) = send_request(address, "POST", "/parse_detailed", body);
+        let (batch_status, results) = send_request(
+            address,
+            "POST",
+            "/parse_detailed_batch",
+            r#"{"inputs": ["Make me two cups of coffee"]}"#,
+        );
+
+        // Then
+        let expected_result = serde_json::to_value(
+            load_engine(trained_engine_path())
+                .unwrap()
+                .parse_detailed("Make me two cups of coffee", None)
+                .unwrap()
+        ).unwrap();
+        assert_eq!(200, status);
+        assert_eq!(expected_result, result);
+        assert_eq!(200, batch_status);
+        assert_eq!(Value::Array(vec![expected_result]), results);
+    }
+
+    #[test]
+    fn parse_batch_works() {
+        // Given
+        let address = start_server(trained_engines());
+        let body = r#"{"engine": "coffee", "inputs": ["Make me two cups of coffee", "hello"]}"#;
+
+        // When
+        let (status, results) = send_request(address, "POST", "/parse_batch", body);
+
+        // Then
+        let expected_results = serde_json::to_value(
+            load_engine(trained_engine_path())
+                .unwrap()
+                .parse_batch(&["Make me two cups of coffee", "hello"], None)
+                .unwrap()
+        ).unwrap();
+        assert_eq!(200, status);
+        assert_eq!(expected_results, results);
+        assert_eq!("hello", results[1]["input"]);
+    }
+
+    #[test]
+    fn extract_slot_works() {
+        // Given
+        let address = start_server(trained_engines());
+        let body = r#"{"input": "hot", "intent": "MakeTea", "slot": "beverage_temperature"}"#;
+        let unknown_intent_body =
+            r#"{"input": "hot", "intent": "MakeSoup", "slot": "temperature"}"#;
+
+        // When
+        let (status, slot) = send_request(address, "POST", "/extract_slot", body);
+        let (unknown_intent_status, _) =
+            send_request(address, "POST", "/extract_slot", unknown_intent_body);
+
+        // Then
+        assert_eq!(200, status);
+        assert_eq!("hot", slot["rawValue"]);
+        assert_eq!(400, unknown_intent_status);
+    }
+
+    #[test]
+    fn add_and_remove_entity_values_works() {
+        // Given
+        let address = start_server(trained_engines());
+        let input = "Make 3 lukewarm teas";
+        let parse_body = format!(r#"{{"input": "{}"}}"#, input);
+        let add_body = r#"{
+            "entity": "Temperature",
+            "values": [{"value": "warm", "synonyms": ["lukewarm"]}]
+        }"#;
+        let remove_body = r#"{"entity": "Temperature", "values": ["warm"]}"#;
+        let unknown_entity_body = r#"{"entity": "Flavour", "values": ["vanilla"]}"#;
+
+        // When
+        let (add_status, _) = send_request(address, "POST", "/add_entity_values", add_body);
+        let (_, result_after_add) = send_request(address, "POST", "/parse", &parse_body);
+        let (remove_status, _) =
+            send_request(address, "POST", "/remove_entity_values", remove_body);
+        let (_, result_after_remove) = send_request(address, "POST", "/parse", &parse_body);
+        let (unknown_entity_status, body) =
+            send_request(address, "POST", "/remove_entity_values", unknown_entity_body);
+
+        // Then
+        let expected_result_after_remove = serde_json::to_value(
+            load_engine(trained_engine_path())
+                .unwrap()
+                .parse(input, None)
+                .unwrap()
+        ).unwrap();
+        assert_eq!(200, add_status);
+        assert_eq!("MakeTea", result_after_add["intent"]["intentName"]);
+        let temperature_slot = result_after_add["slots"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|slot| slot["slotName"] == "beverage_temperature")
+            .unwrap()
+            .clone();
+        assert_eq!("lukewarm", temperature_slot["rawValue"]);
+        assert_eq!("warm", temperature_slot["value"]["value"]);
+        assert_eq!(200, remove_status);
+        assert_eq!(expected_result_after_remove, result_after_remove);
+        assert_eq!(400, unknown_entity_status);
+        assert_eq!("Unknown entity 'Flavour'", body["error"]);
+    }
+}
@@ -0,0 +1,111 @@
+extern crate clap;
+extern crate failure;
+extern crate snips_nlu_server;
+
+use clap::{App, Arg};
+use snips_nlu_server::{EngineStore, Server};
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+fn main() {
+    let matches = App::new("snips-nlu-server")
+        .about("HTTP server exposing Snips NLU engines through a json API")
+        .arg(Arg::with_name("engine")
+            .short("e")
+            .long("engine")
+            .required(true)
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("[NAME=]PATH")
+            .help("trained nlu engine directory or zip archive, which can be repeated, where \
+                   the name of the engine defaults to the name of its file"))
+        .arg(Arg::with_name("address")
+            .short("a")
+            .long("address")
+            .takes_value(true)
+            .default_value("127.0.0.1:8000")
+            .help("address to listen on"))
+        .arg(Arg::with_name("reload_interval")
+            .long("reload-interval")
+            .takes_value(true)
+            .default_value("5")
+            .help("interval, in seconds, at which modified engines are reloaded, 0 disabling \
+                   the reloading"))
+        .arg(Arg::with_name("workers")
+            .long("workers")
+            .takes_value(true)
+            .default_value("8")
+            .help("number of threads handling the connections"))
+        .arg(Arg::with_name("timeout")
+            .long("timeout")
+            .takes_value(true)
+            .default_value("30")
+            .help("timeout, in seconds, of the reads and writes on a connection, 0 disabling \
+                   the timeout"))
+        .get_matches();
+
+    let mut engines = EngineStore::new();
+    for engine_arg in matches.values_of("engine").unwrap() {
+        let (name, path) = match engine_arg.find('=') {
+            Some(index) => (engine_arg[..index].to_string(), &engine_arg[index + 1..]),
+            None => {
+                let name = Path::new(engine_arg)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| engine_arg.to_string());
+                (name, engine_arg)
+            }
+        };
+        eprintln!("Loading engine '{}' from {}...", name, path);
+        if let Err(error) = engines.load(&name, path) {
+            exit_with_error(&error);
+        }
+    }
+
+    let reload_interval: u64 = matches
+        .value_of("reload_interval")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|_| {
+            eprintln!("Error: the reload interval must be a number of seconds");
+            process::exit(2)
+        });
+    let workers: usize = matches
+        .value_of("workers")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|_| {
+            eprintln!("Error: the number of workers must be a positive number");
+            process::exit(2)
+        });
+    let timeout: u64 = matches
+        .value_of("timeout")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|_| {
+            eprintln!("Error: the timeout must be a number of seconds");
+            process::exit(2)
+        });
+    let address = matches.value_of("address").unwrap();
+    let server = Server::bind(address, engines)
+        .unwrap_or_else(|error| exit_with_error(&error))
+        .workers(workers)
+        .timeout(Duration::from_secs(timeout));
+    if reload_interval > 0 {
+        server.watch_engines(Duration::from_secs(reload_interval));
+    }
+    eprintln!("Listening on {}", address);
+    if let Err(error) = server.run() {
+        exit_with_error(&error);
+    }
+}
+
+fn exit_with_error(error: &failure::Error) -> ! {
+    eprintln!("Error: {}", error);
+    for cause in error.iter_causes() {
+        eprintln!("  caused by: {}", cause);
+    }
+    process::exit(1)
+}